@@ -0,0 +1,1551 @@
+//! The registry of built-in functions.
+//!
+//! Each entry names a function, its aliases and how many arguments it takes;
+//! the evaluator checks the count before calling the handler, so handlers can
+//! index `args` directly.
+
+use crate::constants;
+use crate::datetime::{self, Span};
+use crate::distributions;
+use crate::evaluator::Evaluator;
+use crate::finance;
+use crate::format::NumberFormat;
+use crate::interval::Interval;
+use crate::matrix::{self, Matrix};
+use crate::number_theory;
+use crate::parser::Token;
+use crate::quadrature::{self, IntegrationError};
+use crate::random;
+use crate::roots;
+use crate::rounding::{self, RoundingMode};
+use crate::settings::AngleMode;
+use crate::special;
+use crate::stats;
+use crate::symbolic::{self, Expr};
+use crate::trig;
+use crate::uncertainty::Uncertain;
+use crate::value::Value;
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+
+/// How many arguments a built-in accepts.
+pub enum Arity {
+    Exact(usize),
+    Between(usize, usize),
+    AtLeast(usize),
+}
+
+impl Arity {
+    pub fn check(&self, name: &str, count: usize) -> Result<(), String> {
+        let (ok, expected) = match *self {
+            Arity::Exact(n) => (count == n, n.to_string()),
+            Arity::Between(lo, hi) => ((lo..=hi).contains(&count), format!("{} to {}", lo, hi)),
+            Arity::AtLeast(n) => (count >= n, format!("at least {}", n)),
+        };
+        if ok {
+            return Ok(());
+        }
+        let plural = if expected == "1" { "" } else { "s" };
+        Err(format!(
+            "{}() expects {} argument{}, got {}",
+            name, expected, plural, count
+        ))
+    }
+}
+
+pub enum Handler {
+    /// Takes the evaluated arguments.
+    Values(fn(&Evaluator, &[Value]) -> Result<Value, String>),
+    /// Takes the arguments as written, for functions of a formula such as `diff`.
+    Tokens(fn(&mut Evaluator, &[Vec<Token>]) -> Result<Value, String>),
+}
+
+pub struct Builtin {
+    pub name: &'static str,
+    pub aliases: &'static [&'static str],
+    pub arity: Arity,
+    pub call: Handler,
+    /// Whether the result depends only on the arguments, so a call can be cached.
+    pub pure: bool,
+}
+
+const fn builtin(
+    name: &'static str,
+    aliases: &'static [&'static str],
+    arity: Arity,
+    call: fn(&Evaluator, &[Value]) -> Result<Value, String>,
+) -> Builtin {
+    Builtin {
+        name,
+        aliases,
+        arity,
+        call: Handler::Values(call),
+        pure: true,
+    }
+}
+
+/// A built-in that works on its arguments unevaluated.
+const fn symbolic(
+    name: &'static str,
+    aliases: &'static [&'static str],
+    arity: Arity,
+    call: fn(&mut Evaluator, &[Vec<Token>]) -> Result<Value, String>,
+) -> Builtin {
+    Builtin {
+        name,
+        aliases,
+        arity,
+        call: Handler::Tokens(call),
+        pure: true,
+    }
+}
+
+impl Builtin {
+    /// Whether the arguments are formulas, such as the first argument of `diff`.
+    pub fn takes_formulas(&self) -> bool {
+        matches!(self.call, Handler::Tokens(_))
+    }
+
+    /// Marks a built-in whose result can change between calls, such as `rand()`.
+    const fn impure(self) -> Builtin {
+        Builtin {
+            pure: false,
+            ..self
+        }
+    }
+}
+
+use Arity::{AtLeast, Between, Exact};
+
+pub static BUILTINS: &[Builtin] = &[
+    // Trigonometry, in the session's angle unit
+    builtin("sin", &[], Exact(1), |ev, args| {
+        let mode = ev.settings().angle;
+        ev.apply_unary(
+            &args[0],
+            |x| trig::sin(x, mode),
+            |x| trig::cos(x, mode) * trig::radians_per_unit(mode),
+            |i| ev.interval_angle(i, mode, AngleMode::Radians)?.sin(),
+        )
+    }),
+    builtin("cos", &[], Exact(1), |ev, args| {
+        let mode = ev.settings().angle;
+        ev.apply_unary(
+            &args[0],
+            |x| trig::cos(x, mode),
+            |x| -trig::sin(x, mode) * trig::radians_per_unit(mode),
+            |i| ev.interval_angle(i, mode, AngleMode::Radians)?.cos(),
+        )
+    }),
+    builtin("tan", &[], Exact(1), |ev, args| {
+        let mode = ev.settings().angle;
+        if !ev.settings().interval && !ev.settings().ieee {
+            trig::tan(args[0].nominal(), mode)?;
+        }
+        ev.apply_unary(
+            &args[0],
+            |x| trig::tan(x, mode).unwrap_or(f64::NAN),
+            |x| trig::radians_per_unit(mode) / trig::cos(x, mode).powi(2),
+            |i| ev.interval_angle(i, mode, AngleMode::Radians)?.tan(),
+        )
+    }),
+    builtin("arcsin", &["asin"], Exact(1), |ev, args| {
+        check_domain(
+            ev,
+            &args[0],
+            |x| x.abs() <= 1.0,
+            "arcsin() argument must be within [-1, 1]",
+        )?;
+        let mode = ev.settings().angle;
+        ev.apply_unary(
+            &args[0],
+            |x| trig::from_radians(x.asin(), mode),
+            |x| 1.0 / (1.0 - x * x).sqrt() / trig::radians_per_unit(mode),
+            |i| ev.interval_angle(&i.asin()?, AngleMode::Radians, mode),
+        )
+    }),
+    builtin("arccos", &["acos"], Exact(1), |ev, args| {
+        check_domain(
+            ev,
+            &args[0],
+            |x| x.abs() <= 1.0,
+            "arccos() argument must be within [-1, 1]",
+        )?;
+        let mode = ev.settings().angle;
+        ev.apply_unary(
+            &args[0],
+            |x| trig::from_radians(x.acos(), mode),
+            |x| -1.0 / (1.0 - x * x).sqrt() / trig::radians_per_unit(mode),
+            |i| ev.interval_angle(&i.acos()?, AngleMode::Radians, mode),
+        )
+    }),
+    builtin("arctan", &["atan"], Exact(1), |ev, args| {
+        let mode = ev.settings().angle;
+        ev.apply_unary(
+            &args[0],
+            |x| trig::from_radians(x.atan(), mode),
+            |x| 1.0 / (1.0 + x * x) / trig::radians_per_unit(mode),
+            |i| ev.interval_angle(&i.atan()?, AngleMode::Radians, mode),
+        )
+    }),
+    builtin("atan2", &["arctan2"], Exact(2), |ev, args| {
+        let mode = ev.settings().angle;
+        propagate(
+            args,
+            "atan2",
+            [0.0; 2],
+            |[y, x]| Ok(trig::from_radians(y.atan2(x), mode)),
+            |[y, x]| {
+                let scale = (x * x + y * y) * trig::radians_per_unit(mode);
+                [x / scale, -y / scale]
+            },
+        )
+    }),
+    builtin("deg", &[], Exact(1), |ev, args| {
+        ev.convert_angle(args[0].clone(), AngleMode::Radians, AngleMode::Degrees)
+    }),
+    builtin("rad", &[], Exact(1), |ev, args| {
+        ev.convert_angle(args[0].clone(), AngleMode::Degrees, AngleMode::Radians)
+    }),
+    // Hyperbolic functions
+    builtin("sinh", &[], Exact(1), |ev, args| {
+        ev.apply_unary(&args[0], f64::sinh, f64::cosh, |i| {
+            Ok(i.increasing(f64::sinh))
+        })
+    }),
+    builtin("cosh", &[], Exact(1), |ev, args| {
+        ev.apply_unary(&args[0], f64::cosh, f64::sinh, |i| Ok(i.cosh()))
+    }),
+    builtin("tanh", &[], Exact(1), |ev, args| {
+        ev.apply_unary(
+            &args[0],
+            f64::tanh,
+            |x| 1.0 / x.cosh().powi(2),
+            |i| Ok(i.increasing(f64::tanh)),
+        )
+    }),
+    builtin("asinh", &["arcsinh"], Exact(1), |ev, args| {
+        ev.apply_unary(
+            &args[0],
+            f64::asinh,
+            |x| 1.0 / (x * x + 1.0).sqrt(),
+            |i| Ok(i.increasing(f64::asinh)),
+        )
+    }),
+    builtin("acosh", &["arccosh"], Exact(1), |ev, args| {
+        check_domain(
+            ev,
+            &args[0],
+            |x| x >= 1.0,
+            "acosh() argument must be at least 1",
+        )?;
+        ev.apply_unary(
+            &args[0],
+            f64::acosh,
+            |x| 1.0 / (x * x - 1.0).sqrt(),
+            |i| {
+                if i.lo >= 1.0 {
+                    Ok(i.increasing(f64::acosh))
+                } else {
+                    Err("acosh() argument must be at least 1".to_string())
+                }
+            },
+        )
+    }),
+    builtin("atanh", &["arctanh"], Exact(1), |ev, args| {
+        check_domain(
+            ev,
+            &args[0],
+            |x| x.abs() < 1.0,
+            "atanh() argument must be within (-1, 1)",
+        )?;
+        ev.apply_unary(
+            &args[0],
+            f64::atanh,
+            |x| 1.0 / (1.0 - x * x),
+            |i| {
+                if -1.0 < i.lo && i.hi < 1.0 {
+                    Ok(i.increasing(f64::atanh))
+                } else {
+                    Err("atanh() argument must be within (-1, 1)".to_string())
+                }
+            },
+        )
+    }),
+    // Powers and logarithms
+    builtin("sqrt", &[], Exact(1), |ev, args| {
+        check_domain(
+            ev,
+            &args[0],
+            |x| x >= 0.0,
+            "sqrt() argument must be non-negative",
+        )?;
+        ev.apply_unary(
+            &args[0],
+            f64::sqrt,
+            |x| 0.5 / x.sqrt(),
+            |i| {
+                if i.lo >= 0.0 {
+                    Ok(i.increasing(f64::sqrt))
+                } else {
+                    Err("sqrt() argument must be non-negative".to_string())
+                }
+            },
+        )
+    }),
+    builtin("cbrt", &[], Exact(1), |ev, args| {
+        ev.apply_unary(
+            &args[0],
+            f64::cbrt,
+            |x| 1.0 / (3.0 * x.cbrt().powi(2)),
+            |i| Ok(i.increasing(f64::cbrt)),
+        )
+    }),
+    builtin("exp", &[], Exact(1), |ev, args| {
+        ev.apply_unary(&args[0], f64::exp, f64::exp, Interval::exp)
+    }),
+    builtin("log", &["ln"], Between(1, 2), |ev, args| {
+        check_domain(ev, &args[0], |x| x > 0.0, "log() argument must be positive")?;
+        let ln = ev.apply_unary(&args[0], f64::ln, |x| 1.0 / x, Interval::ln)?;
+        match args.get(1) {
+            None => Ok(ln),
+            Some(base) => {
+                check_domain(
+                    ev,
+                    base,
+                    |b| b > 0.0 && b != 1.0,
+                    "log() base must be positive and not 1",
+                )?;
+                let ln_base = ev.apply_unary(base, f64::ln, |x| 1.0 / x, Interval::ln)?;
+                ev.apply_operator(&Token::Divide, ln, ln_base)
+            }
+        }
+    }),
+    builtin("log10", &[], Exact(1), |ev, args| {
+        check_domain(
+            ev,
+            &args[0],
+            |x| x > 0.0,
+            "log10() argument must be positive",
+        )?;
+        ev.apply_unary(
+            &args[0],
+            f64::log10,
+            |x| 1.0 / (x * std::f64::consts::LN_10),
+            |i| i.ln().map(|_| i.increasing(f64::log10)),
+        )
+    }),
+    builtin("log2", &[], Exact(1), |ev, args| {
+        check_domain(
+            ev,
+            &args[0],
+            |x| x > 0.0,
+            "log2() argument must be positive",
+        )?;
+        ev.apply_unary(
+            &args[0],
+            f64::log2,
+            |x| 1.0 / (x * std::f64::consts::LN_2),
+            |i| i.ln().map(|_| i.increasing(f64::log2)),
+        )
+    }),
+    builtin("hypot", &[], AtLeast(2), |_, args| {
+        // ∂h/∂x = x/h, taken as 0 at the origin
+        propagate_list(args, "hypot", hypot, |values| {
+            let h = hypot(values);
+            let slope = |x: &f64| if h == 0.0 { 0.0 } else { x / h };
+            values.iter().map(slope).collect()
+        })
+    }),
+    // Rounding and sign
+    builtin("abs", &[], Exact(1), |ev, args| {
+        ev.apply_unary(&args[0], f64::abs, f64::signum, |i| Ok(i.abs()))
+    }),
+    builtin("sign", &["sgn"], Exact(1), |ev, args| {
+        let sign = |x: f64| if x == 0.0 { 0.0 } else { x.signum() };
+        ev.apply_unary(&args[0], sign, |_| 0.0, |i| Ok(i.increasing_exact(sign)))
+    }),
+    builtin("floor", &[], Exact(1), |ev, args| {
+        ev.apply_unary(
+            &args[0],
+            f64::floor,
+            |_| 0.0,
+            |i| Ok(i.increasing_exact(f64::floor)),
+        )
+    }),
+    builtin("ceil", &[], Exact(1), |ev, args| {
+        ev.apply_unary(
+            &args[0],
+            f64::ceil,
+            |_| 0.0,
+            |i| Ok(i.increasing_exact(f64::ceil)),
+        )
+    }),
+    // round(x[, decimals[, mode]]) uses the session's rounding mode unless given one
+    builtin("round", &[], Between(1, 3), |ev, args| {
+        let decimals = args.get(1).map_or(Ok(0), |d| decimal_count(d, "round"))?;
+        let mode = rounding_mode(ev, args.get(2), "round")?;
+        round_with(ev, &args[0], move |x| rounding::round(x, decimals, mode))
+    }),
+    builtin("trunc", &[], Between(1, 2), |ev, args| {
+        let decimals = args.get(1).map_or(Ok(0), |d| decimal_count(d, "trunc"))?;
+        round_with(ev, &args[0], move |x| {
+            rounding::round(x, decimals, RoundingMode::TowardZero)
+        })
+    }),
+    builtin("roundsig", &[], Between(2, 3), |ev, args| {
+        let digits = match integer_arg(&args[1], "roundsig")? {
+            n @ 1..=17 => n as u32,
+            _ => return Err("roundsig() keeps 1 to 17 significant digits".to_string()),
+        };
+        let mode = rounding_mode(ev, args.get(2), "roundsig")?;
+        round_with(ev, &args[0], move |x| {
+            rounding::round_significant(x, digits, mode)
+        })
+    }),
+    builtin("mround", &[], Between(2, 3), |ev, args| {
+        let [multiple] = numbers(&args[1..2], "mround")?;
+        let mode = rounding_mode(ev, args.get(2), "mround")?;
+        round_with(ev, &args[0], move |x| {
+            rounding::round_multiple(x, multiple, mode)
+        })
+    }),
+    builtin("min", &[], AtLeast(1), |_, args| {
+        extreme(args, "min", f64::min)
+    }),
+    builtin("max", &[], AtLeast(1), |_, args| {
+        extreme(args, "max", f64::max)
+    }),
+    // Combinatorics
+    builtin("fact", &["factorial"], Exact(1), |ev, args| {
+        ev.factorial(&args[0])
+    }),
+    builtin("comb", &[], Exact(2), |_, args| {
+        let [n, k] = counts(args, "comb")?;
+        let mut res = 1u128;
+        for i in 0..k {
+            res = res.checked_mul(n - i).ok_or("comb() overflow")?;
+            res = res.checked_div(i + 1).ok_or("comb() division by zero")?;
+        }
+        Ok(Value::Number(res as f64))
+    }),
+    builtin("perm", &[], Exact(2), |_, args| {
+        let [n, k] = counts(args, "perm")?;
+        let mut res = 1u128;
+        for i in 0..k {
+            res = res.checked_mul(n - i).ok_or("perm() overflow")?;
+        }
+        Ok(Value::Number(res as f64))
+    }),
+    // IEEE-754 classification
+    builtin("isnan", &[], Exact(1), |_, args| {
+        let [x] = numbers(args, "isnan")?;
+        Ok(truth(x.is_nan()))
+    }),
+    builtin("isinf", &[], Exact(1), |_, args| {
+        let [x] = numbers(args, "isinf")?;
+        Ok(truth(x.is_infinite()))
+    }),
+    builtin("isfinite", &[], Exact(1), |_, args| {
+        let [x] = numbers(args, "isfinite")?;
+        Ok(truth(x.is_finite()))
+    }),
+    // Special functions
+    builtin("gamma", &[], Exact(1), |ev, args| {
+        propagate(
+            args,
+            "gamma",
+            [0.0],
+            |[x]| {
+                // Signed infinity at ±0, as 1/x, and no value at the negative integers
+                let pole = if x == 0.0 { 1.0 / x } else { f64::NAN };
+                finite(ev, "gamma", or_pole(ev, special::gamma(x), pole)?)
+            },
+            |[x]| [special::gamma(x).unwrap_or(f64::NAN) * special::digamma(x).unwrap_or(f64::NAN)],
+        )
+    }),
+    builtin("lgamma", &[], Exact(1), |ev, args| {
+        propagate(
+            args,
+            "lgamma",
+            [0.0],
+            |[x]| {
+                finite(
+                    ev,
+                    "lgamma",
+                    or_pole(ev, special::lgamma(x), f64::INFINITY)?,
+                )
+            },
+            |[x]| [special::digamma(x).unwrap_or(f64::NAN)],
+        )
+    }),
+    builtin("digamma", &[], Exact(1), |ev, args| {
+        let [x] = numbers(args, "digamma")?;
+        ev.special_result("digamma", or_pole(ev, special::digamma(x), f64::NAN)?)
+    }),
+    // ∂B/∂a = B·(ψ(a) - ψ(a + b)), and likewise for b
+    builtin("beta", &[], Exact(2), |ev, args| {
+        propagate(
+            args,
+            "beta",
+            [0.0; 2],
+            |[a, b]| finite(ev, "beta", or_pole(ev, special::beta(a, b), f64::NAN)?),
+            |[a, b]| {
+                let psi = |x| special::digamma(x).unwrap_or(f64::NAN);
+                let value = special::beta(a, b).unwrap_or(f64::NAN);
+                [value * (psi(a) - psi(a + b)), value * (psi(b) - psi(a + b))]
+            },
+        )
+    }),
+    builtin("erf", &[], Exact(1), |_, args| {
+        let slope = |x: f64| 2.0 / std::f64::consts::PI.sqrt() * (-x * x).exp();
+        propagate(
+            args,
+            "erf",
+            [0.0],
+            |[x]| Ok(special::erf(x)),
+            |[x]| [slope(x)],
+        )
+    }),
+    builtin("erfc", &[], Exact(1), |_, args| {
+        let slope = |x: f64| -2.0 / std::f64::consts::PI.sqrt() * (-x * x).exp();
+        propagate(
+            args,
+            "erfc",
+            [0.0],
+            |[x]| Ok(special::erfc(x)),
+            |[x]| [slope(x)],
+        )
+    }),
+    builtin("erfinv", &[], Exact(1), |ev, args| {
+        propagate(
+            args,
+            "erfinv",
+            [0.0],
+            |[x]| finite(ev, "erfinv", special::erfinv(x)?),
+            |[x]| {
+                let y = special::erfinv(x).unwrap_or(f64::NAN);
+                [std::f64::consts::PI.sqrt() / 2.0 * (y * y).exp()]
+            },
+        )
+    }),
+    // J0' = -J1 and J1' = J0 - J1/x, which is 1/2 at 0
+    builtin("j0", &["besselj0"], Exact(1), |_, args| {
+        propagate(
+            args,
+            "j0",
+            [0.0],
+            |[x]| Ok(special::bessel_j0(x)),
+            |[x]| [-special::bessel_j1(x)],
+        )
+    }),
+    builtin("j1", &["besselj1"], Exact(1), |_, args| {
+        let slope = |x: f64| match x {
+            0.0 => 0.5,
+            x => special::bessel_j0(x) - special::bessel_j1(x) / x,
+        };
+        propagate(
+            args,
+            "j1",
+            [0.0],
+            |[x]| Ok(special::bessel_j1(x)),
+            |[x]| [slope(x)],
+        )
+    }),
+    builtin("gammainc", &[], Exact(2), |_, args| {
+        let [a, x] = numbers(args, "gammainc")?;
+        special::incomplete_gamma(a, x, false).map(Value::Number)
+    }),
+    builtin("gammaincc", &[], Exact(2), |_, args| {
+        let [a, x] = numbers(args, "gammaincc")?;
+        special::incomplete_gamma(a, x, true).map(Value::Number)
+    }),
+    // Probability distributions; location and scale parameters are optional
+    builtin("normpdf", &[], Between(1, 3), |_, args| {
+        propagate(
+            args,
+            "normpdf",
+            [0.0, 0.0, 1.0],
+            |[x, mu, sigma]| distributions::normal_pdf(x, mu, sigma),
+            |[x, mu, sigma]| {
+                let density = distributions::normal_pdf(x, mu, sigma).unwrap_or(f64::NAN);
+                let z = (x - mu) / sigma;
+                let slope = -z * density / sigma;
+                [slope, -slope, density * (z * z - 1.0) / sigma]
+            },
+        )
+    }),
+    builtin("normcdf", &[], Between(1, 3), |_, args| {
+        propagate(
+            args,
+            "normcdf",
+            [0.0, 0.0, 1.0],
+            |[x, mu, sigma]| distributions::normal_cdf(x, mu, sigma),
+            |[x, mu, sigma]| {
+                let density = distributions::normal_pdf(x, mu, sigma).unwrap_or(f64::NAN);
+                [density, -density, -density * (x - mu) / sigma]
+            },
+        )
+    }),
+    builtin("invnorm", &["norminv"], Between(1, 3), |ev, args| {
+        let [p, mu, sigma] = numbers_or(args, "invnorm", [0.0, 0.0, 1.0])?;
+        ev.special_result("invnorm", distributions::normal_inv(p, mu, sigma)?)
+    }),
+    builtin("tpdf", &[], Exact(2), |_, args| {
+        let [x, df] = numbers(args, "tpdf")?;
+        distributions::t_pdf(x, df).map(Value::Number)
+    }),
+    builtin("tcdf", &[], Exact(2), |_, args| {
+        let [x, df] = numbers(args, "tcdf")?;
+        distributions::t_cdf(x, df).map(Value::Number)
+    }),
+    builtin("invt", &["tinv"], Exact(2), |ev, args| {
+        let [p, df] = numbers(args, "invt")?;
+        ev.special_result("invt", distributions::t_inv(p, df)?)
+    }),
+    builtin("chi2pdf", &[], Exact(2), |ev, args| {
+        let [x, df] = numbers(args, "chi2pdf")?;
+        ev.special_result("chi2pdf", distributions::chi2_pdf(x, df)?)
+    }),
+    builtin("chi2cdf", &[], Exact(2), |_, args| {
+        let [x, df] = numbers(args, "chi2cdf")?;
+        distributions::chi2_cdf(x, df).map(Value::Number)
+    }),
+    builtin("invchi2", &["chi2inv"], Exact(2), |ev, args| {
+        let [p, df] = numbers(args, "invchi2")?;
+        ev.special_result("invchi2", distributions::chi2_inv(p, df)?)
+    }),
+    builtin("binompmf", &["binompdf"], Exact(3), |_, args| {
+        let [k, n, p] = numbers(args, "binompmf")?;
+        distributions::binomial_pmf(k, n, p).map(Value::Number)
+    }),
+    builtin("binomcdf", &[], Exact(3), |_, args| {
+        let [k, n, p] = numbers(args, "binomcdf")?;
+        distributions::binomial_cdf(k, n, p).map(Value::Number)
+    }),
+    builtin("invbinom", &["binominv"], Exact(3), |_, args| {
+        let [q, n, p] = numbers(args, "invbinom")?;
+        distributions::binomial_inv(q, n, p).map(Value::Number)
+    }),
+    builtin("poissonpmf", &["poissonpdf"], Exact(2), |_, args| {
+        let [k, lambda] = numbers(args, "poissonpmf")?;
+        distributions::poisson_pmf(k, lambda).map(Value::Number)
+    }),
+    builtin("poissoncdf", &[], Exact(2), |_, args| {
+        let [k, lambda] = numbers(args, "poissoncdf")?;
+        distributions::poisson_cdf(k, lambda).map(Value::Number)
+    }),
+    builtin("invpoisson", &["poissoninv"], Exact(2), |ev, args| {
+        let [p, lambda] = numbers(args, "invpoisson")?;
+        ev.special_result("invpoisson", distributions::poisson_inv(p, lambda)?)
+    }),
+    builtin("exppdf", &[], Between(1, 2), |_, args| {
+        let [x, rate] = numbers_or(args, "exppdf", [0.0, 1.0])?;
+        distributions::exponential_pdf(x, rate).map(Value::Number)
+    }),
+    builtin("expcdf", &[], Between(1, 2), |_, args| {
+        let [x, rate] = numbers_or(args, "expcdf", [0.0, 1.0])?;
+        distributions::exponential_cdf(x, rate).map(Value::Number)
+    }),
+    builtin("invexp", &["expinv"], Between(1, 2), |ev, args| {
+        let [p, rate] = numbers_or(args, "invexp", [0.0, 1.0])?;
+        ev.special_result("invexp", distributions::exponential_inv(p, rate)?)
+    }),
+    builtin("unifpdf", &[], Between(1, 3), |_, args| {
+        let [x, a, b] = numbers_or(args, "unifpdf", [0.0, 0.0, 1.0])?;
+        distributions::uniform_pdf(x, a, b).map(Value::Number)
+    }),
+    builtin("unifcdf", &[], Between(1, 3), |_, args| {
+        let [x, a, b] = numbers_or(args, "unifcdf", [0.0, 0.0, 1.0])?;
+        distributions::uniform_cdf(x, a, b).map(Value::Number)
+    }),
+    builtin("invunif", &["unifinv"], Between(1, 3), |_, args| {
+        let [p, a, b] = numbers_or(args, "invunif", [0.0, 0.0, 1.0])?;
+        distributions::uniform_inv(p, a, b).map(Value::Number)
+    }),
+    // Linear algebra
+    builtin("transpose", &[], Exact(1), |_, args| {
+        Ok(Value::Matrix(
+            matrix_arg(&args[0], "transpose")?.transpose(),
+        ))
+    }),
+    builtin("det", &[], Exact(1), |_, args| {
+        matrix_arg(&args[0], "det")?.det().map(Value::Number)
+    }),
+    builtin("inv", &[], Exact(1), |_, args| {
+        matrix_arg(&args[0], "inv")?.inv().map(Value::Matrix)
+    }),
+    builtin("rank", &[], Exact(1), |_, args| {
+        Ok(Value::Number(matrix_arg(&args[0], "rank")?.rank() as f64))
+    }),
+    builtin("trace", &[], Exact(1), |_, args| {
+        matrix_arg(&args[0], "trace")?.trace().map(Value::Number)
+    }),
+    builtin("eig", &["eigenvalues"], Exact(1), |_, args| {
+        let eigenvalues = matrix_arg(&args[0], "eig")?.eigenvalues()?;
+        Ok(Value::Matrix(Matrix::column(eigenvalues)))
+    }),
+    builtin("dot", &[], Exact(2), |_, args| {
+        matrix::dot(matrix_arg(&args[0], "dot")?, matrix_arg(&args[1], "dot")?).map(Value::Number)
+    }),
+    builtin("cross", &[], Exact(2), |_, args| {
+        matrix::cross(
+            matrix_arg(&args[0], "cross")?,
+            matrix_arg(&args[1], "cross")?,
+        )
+        .map(Value::Matrix)
+    }),
+    // solve(A, b) for matrices, unless it is given an equation in a free variable
+    symbolic("solve", &[], Between(2, 4), |ev, args| {
+        let free = matches!(args[1].as_slice(), [Token::Variable(name)] if !ev.binds(name));
+        if free || args[0].contains(&Token::Equals) {
+            return solve_equation(ev, args);
+        }
+        if args.len() != 2 {
+            return Err(
+                "solve() expects a matrix and a vector, or an equation and a variable".to_string(),
+            );
+        }
+        let (a, b) = (ev.evaluate(&args[0])?, ev.evaluate(&args[1])?);
+        let (a, b) = (matrix_arg(&a, "solve")?, matrix_arg(&b, "solve")?);
+        a.solve(b).map(Value::Matrix)
+    }),
+    // Statistics over numbers and lists
+    builtin("mean", &[], AtLeast(1), |_, args| {
+        propagate_list(args, "mean", stats::mean, |values| {
+            vec![1.0 / values.len() as f64; values.len()]
+        })
+    }),
+    builtin("median", &[], AtLeast(1), |_, args| {
+        Ok(Value::Number(stats::median(&number_list(args, "median")?)))
+    }),
+    builtin("mode", &[], AtLeast(1), |_, args| {
+        Ok(match stats::mode(&number_list(args, "mode")?).as_slice() {
+            [single] => Value::Number(*single),
+            modes => Value::Matrix(Matrix::column(modes.to_vec())),
+        })
+    }),
+    builtin("variance", &["var"], AtLeast(1), |_, args| {
+        stats::variance(&number_list(args, "variance")?).map(Value::Number)
+    }),
+    builtin("pvariance", &["pvar"], AtLeast(1), |_, args| {
+        Ok(Value::Number(stats::pvariance(&number_list(
+            args,
+            "pvariance",
+        )?)))
+    }),
+    builtin("stdev", &["std"], AtLeast(1), |_, args| {
+        stats::variance(&number_list(args, "stdev")?).map(|v| Value::Number(v.sqrt()))
+    }),
+    builtin("pstdev", &["pstd"], AtLeast(1), |_, args| {
+        Ok(Value::Number(
+            stats::pvariance(&number_list(args, "pstdev")?).sqrt(),
+        ))
+    }),
+    builtin("skewness", &[], AtLeast(1), |_, args| {
+        stats::skewness(&number_list(args, "skewness")?).map(Value::Number)
+    }),
+    builtin("kurtosis", &[], AtLeast(1), |_, args| {
+        stats::kurtosis(&number_list(args, "kurtosis")?).map(Value::Number)
+    }),
+    builtin("quartiles", &[], AtLeast(1), |_, args| {
+        let quartiles = stats::quartiles(&number_list(args, "quartiles")?);
+        Ok(Value::Matrix(Matrix::column(quartiles.to_vec())))
+    }),
+    builtin("percentile", &[], AtLeast(2), |_, args| {
+        let (Value::Number(p), data) = args.split_last().unwrap() else {
+            return Err("percentile() expects values followed by a percentile, e.g. percentile([1, 5, 9], 90)".to_string());
+        };
+        if !(0.0..=100.0).contains(p) {
+            return Err("percentile() expects a percentile from 0 to 100".to_string());
+        }
+        Ok(Value::Number(stats::percentile(
+            &number_list(data, "percentile")?,
+            *p,
+        )))
+    }),
+    builtin("correlation", &["corr"], Exact(2), |_, args| {
+        let [x, y] = paired_lists(args, "correlation")?;
+        stats::correlation(&x, &y).map(Value::Number)
+    }),
+    builtin("covariance", &["cov"], Exact(2), |_, args| {
+        let [x, y] = paired_lists(args, "covariance")?;
+        stats::covariance(&x, &y).map(Value::Number)
+    }),
+    // Number theory on exact integers
+    builtin("gcd", &[], AtLeast(1), |_, args| {
+        let numbers = magnitudes(args, "gcd")?;
+        exact_integer(numbers.into_iter().fold(0, number_theory::gcd))
+    }),
+    builtin("lcm", &[], AtLeast(1), |_, args| {
+        let numbers = magnitudes(args, "lcm")?;
+        let lcm = numbers.into_iter().try_fold(1, number_theory::lcm);
+        exact_integer(lcm.ok_or("lcm() overflow")?)
+    }),
+    builtin("isprime", &[], Exact(1), |_, args| {
+        let n = integer_arg(&args[0], "isprime")?;
+        Ok(truth(n > 0 && number_theory::is_prime(n as u64)))
+    }),
+    builtin("nextprime", &[], Exact(1), |_, args| {
+        let n = integer_arg(&args[0], "nextprime")?;
+        exact_integer(number_theory::next_prime(n.max(0) as u64))
+    }),
+    // A formula in one free variable is factored as a polynomial instead
+    symbolic("factor", &[], Exact(1), |ev, args| {
+        if let Ok(formula) = Expr::parse(&args[0]) {
+            match free_symbols(ev, &formula).as_slice() {
+                [] => {}
+                [var] => {
+                    let formula = formula.expand_derivatives(ev.settings().angle)?;
+                    return Ok(Value::Text(symbolic::factor(&formula, var)?.to_string()));
+                }
+                vars => {
+                    return Err(format!(
+                        "factor() expects a polynomial in one variable, got {}",
+                        vars.join(", ")
+                    ));
+                }
+            }
+        }
+        let n = integer_arg(&ev.evaluate(&args[0])?, "factor")?;
+        if n == 0 {
+            return Err("factor() expects a non-zero integer".to_string());
+        }
+        let sign = if n < 0 { "-1 * " } else { "" };
+        Ok(Value::Text(
+            match number_theory::factorize(n.unsigned_abs()) {
+                factors if factors.is_empty() => format!("{}1", sign),
+                factors => format!("{}{}", sign, number_theory::format_factors(&factors)),
+            },
+        ))
+    }),
+    builtin("divisors", &[], Exact(1), |_, args| {
+        let n = positive_integer_arg(&args[0], "divisors")?;
+        let divisors = number_theory::divisors(n).into_iter().map(|d| d as f64);
+        Ok(Value::Matrix(Matrix::column(divisors.collect())))
+    }),
+    builtin("totient", &[], Exact(1), |_, args| {
+        exact_integer(number_theory::totient(positive_integer_arg(
+            &args[0], "totient",
+        )?))
+    }),
+    builtin("modpow", &[], Exact(3), |_, args| {
+        let (b, e) = (
+            integer_arg(&args[0], "modpow")?,
+            integer_arg(&args[1], "modpow")?,
+        );
+        let m = positive_integer_arg(&args[2], "modpow")?;
+        number_theory::mod_pow(b, e, m).and_then(exact_integer)
+    }),
+    builtin("modinv", &[], Exact(2), |_, args| {
+        let a = integer_arg(&args[0], "modinv")?;
+        let m = positive_integer_arg(&args[1], "modinv")?;
+        number_theory::mod_inv(a, m).and_then(exact_integer)
+    }),
+    builtin("crt", &[], Exact(2), |_, args| {
+        let residues = number_list(&args[..1], "crt")?
+            .into_iter()
+            .map(|r| integer(r, "crt"))
+            .collect::<Result<Vec<i64>, String>>()?;
+        let moduli = number_list(&args[1..], "crt")?
+            .into_iter()
+            .map(|m| match integer(m, "crt")? {
+                m if m >= 1 => Ok(m as u64),
+                _ => Err("crt() moduli must be positive".to_string()),
+            })
+            .collect::<Result<Vec<u64>, String>>()?;
+        let (x, _) = number_theory::crt(&residues, &moduli)?;
+        exact_integer(x)
+    }),
+    // Time value of money; the optional `type` is 1 for payments at period starts
+    builtin("pv", &[], Between(3, 5), |_, args| {
+        let [rate, nper, pmt, fv, due] = numbers_or(args, "pv", [0.0; 5])?;
+        finance::pv(rate, nper, pmt, fv, due != 0.0).map(Value::Number)
+    }),
+    builtin("fv", &[], Between(3, 5), |_, args| {
+        let [rate, nper, pmt, pv, due] = numbers_or(args, "fv", [0.0; 5])?;
+        finance::fv(rate, nper, pmt, pv, due != 0.0).map(Value::Number)
+    }),
+    builtin("amortize", &[], Exact(3), |ev, args| {
+        let [principal, rate, periods] = numbers(args, "amortize")?;
+        let schedule = finance::amortization(principal, rate, periods)?;
+        let format = &ev.settings().format;
+        Ok(Value::Text(finance::schedule_table(&schedule, |amount| {
+            format.format_value(&Value::Number(amount))
+        })))
+    }),
+    builtin("pmt", &[], Between(3, 5), |_, args| {
+        let [rate, nper, pv, fv, due] = numbers_or(args, "pmt", [0.0; 5])?;
+        finance::pmt(rate, nper, pv, fv, due != 0.0).map(Value::Number)
+    }),
+    builtin("nper", &[], Between(3, 5), |_, args| {
+        let [rate, pmt, pv, fv, due] = numbers_or(args, "nper", [0.0; 5])?;
+        finance::nper(rate, pmt, pv, fv, due != 0.0).map(Value::Number)
+    }),
+    builtin("rate", &[], Between(3, 6), |_, args| {
+        let [nper, pmt, pv, fv, due, guess] =
+            numbers_or(args, "rate", [0.0, 0.0, 0.0, 0.0, 0.0, 0.1])?;
+        finance::rate(nper, pmt, pv, fv, due != 0.0, guess).map(Value::Number)
+    }),
+    builtin("npv", &[], AtLeast(2), |_, args| {
+        let [rate] = numbers(&args[..1], "npv")?;
+        finance::npv(rate, &number_list(&args[1..], "npv")?).map(Value::Number)
+    }),
+    builtin("irr", &[], Between(1, 2), |_, args| {
+        let flows = number_list(&args[..1], "irr")?;
+        let [guess] = numbers_or(&args[1..], "irr", [0.1])?;
+        finance::irr(&flows, guess).map(Value::Number)
+    }),
+    builtin("xirr", &[], AtLeast(3), |_, args| {
+        let flows = number_list(&args[..1], "xirr")?;
+        // One date per cash flow, then an optional guess
+        let (dates, guess) = match &args[1..] {
+            [dates @ .., Value::Number(guess)] => (dates, *guess),
+            dates => (dates, 0.1),
+        };
+        let days = dates
+            .iter()
+            .map(|date| match (date, &args[1]) {
+                (Value::Date(date), Value::Date(first)) => {
+                    Ok((*date - *first).num_seconds() as f64 / 86400.0)
+                }
+                _ => Err("xirr() expects a list of cash flows and then their dates".to_string()),
+            })
+            .collect::<Result<Vec<f64>, String>>()?;
+        finance::xirr(&flows, &days, guess).map(Value::Number)
+    }),
+    // Dates and durations
+    builtin("date", &[], Between(1, 6), |_, args| match args {
+        [Value::Text(text)] => datetime::parse_date(text).map(Value::Date),
+        [Value::Date(date)] => Ok(Value::Date(*date)),
+        parts if parts.len() >= 3 => {
+            let parts = parts
+                .iter()
+                .map(|p| match p {
+                    Value::Number(n) => Ok(*n),
+                    _ => Err("date() expects numbers".to_string()),
+                })
+                .collect::<Result<Vec<f64>, String>>()?;
+            datetime::from_parts(&parts).map(Value::Date)
+        }
+        _ => Err(
+            "date() expects year, month, day[, hour, minute, second] or a text date".to_string(),
+        ),
+    }),
+    builtin("today", &[], Exact(0), |_, _| {
+        Ok(Value::Date(chrono::Local::now().date_naive().into()))
+    })
+    .impure(),
+    builtin("now", &[], Exact(0), |_, _| {
+        Ok(Value::Date(chrono::Local::now().naive_local()))
+    })
+    .impure(),
+    builtin("weekday", &[], Exact(1), |_, args| match &args[0] {
+        Value::Date(date) => Ok(Value::Text(datetime::weekday_name(*date).to_string())),
+        _ => Err("weekday() expects a date".to_string()),
+    }),
+    builtin("businessdays", &[], Exact(2), |_, args| match args {
+        [Value::Date(start), Value::Date(end)] => {
+            Ok(Value::Number(datetime::business_days(*start, *end) as f64))
+        }
+        _ => Err("businessdays() expects a start and an end date".to_string()),
+    }),
+    builtin("workday", &[], Exact(2), |_, args| match args {
+        [Value::Date(date), Value::Number(n)] if n.fract() == 0.0 => {
+            datetime::add_business_days(*date, *n as i64).map(Value::Date)
+        }
+        _ => Err("workday() expects a date and a whole number of days".to_string()),
+    }),
+    builtin("duration", &[], Exact(1), |_, args| match &args[0] {
+        Value::Text(text) => Span::parse_iso(text).map(Value::Duration),
+        _ => Err("duration() expects an ISO-8601 text such as \"P1DT12H\"".to_string()),
+    }),
+    // Randomness, from the session's seeded generator
+    builtin("rand", &["random"], Exact(0), |_, _| {
+        Ok(Value::Number(random::with(|g| g.next_f64())))
+    })
+    .impure(),
+    builtin("randint", &[], Exact(2), |_, args| {
+        let (a, b) = (
+            integer_arg(&args[0], "randint")?,
+            integer_arg(&args[1], "randint")?,
+        );
+        if a > b {
+            return Err(format!("randint() expects a ≤ b, got {} and {}", a, b));
+        }
+        Ok(Value::Number(random::with(|g| g.int_between(a, b)) as f64))
+    })
+    .impure(),
+    builtin("randn", &[], Between(0, 2), |_, args| {
+        let [mu, sigma] = numbers_or(args, "randn", [0.0, 1.0])?;
+        if !(sigma >= 0.0 && sigma.is_finite()) {
+            return Err(format!("randn() expects sigma ≥ 0, got {}", sigma));
+        }
+        Ok(Value::Number(random::with(|g| g.normal(mu, sigma))))
+    })
+    .impure(),
+    builtin("choice", &[], AtLeast(1), |_, args| match args {
+        // One list picks an element, several arguments pick one of them
+        [Value::Matrix(m)] => {
+            let items = m.as_vector()?;
+            Ok(Value::Number(
+                items[random::with(|g| g.below(items.len() as u64)) as usize],
+            ))
+        }
+        _ => Ok(args[random::with(|g| g.below(args.len() as u64)) as usize].clone()),
+    })
+    .impure(),
+    // Calculus on formulas
+    symbolic("diff", &["derivative"], Between(2, 3), |ev, args| {
+        let var = variable_arg(&args[1], "diff")?;
+        let derivative = Expr::parse(&args[0])?.derivative(&var, ev.settings().angle)?;
+        match args.get(2) {
+            None => Ok(Value::Text(derivative.to_string())),
+            Some(at) => {
+                let at = ev.evaluate(at)?;
+                ev.evaluate_at(&derivative.to_tokens(), &var, at)
+            }
+        }
+    }),
+    symbolic("simplify", &[], Exact(1), |ev, args| {
+        let formula = Expr::parse(&args[0])?.expand_derivatives(ev.settings().angle)?;
+        Ok(Value::Text(symbolic::simplify(&formula).to_string()))
+    }),
+    symbolic("expand", &[], Exact(1), |ev, args| {
+        let formula = Expr::parse(&args[0])?.expand_derivatives(ev.settings().angle)?;
+        Ok(Value::Text(symbolic::expand(&formula)?.to_string()))
+    }),
+    symbolic("integrate", &["integral"], Exact(4), |ev, args| {
+        let var = variable_arg(&args[1], "integrate")?;
+        let formula = Expr::parse(&args[0])?
+            .expand_derivatives(ev.settings().angle)?
+            .to_tokens();
+        let a = bound_arg(ev, &args[2], "integrate")?;
+        let b = bound_arg(ev, &args[3], "integrate")?;
+        // The first evaluation error stops the integration and is reported as is
+        let mut failure = None;
+        let integral = quadrature::integrate(
+            |x| {
+                if failure.is_some() {
+                    return f64::NAN;
+                }
+                formula_at(ev, &formula, &var, x, "integrate").unwrap_or_else(|e| {
+                    failure = Some(e);
+                    f64::NAN
+                })
+            },
+            a,
+            b,
+        );
+        if let Some(e) = failure {
+            return Err(e);
+        }
+        match integral {
+            Ok(value) => Ok(Value::Number(value)),
+            Err(e @ IntegrationError::NotConverged { .. }) => {
+                Err(format!("integrate() did not converge: {}", e))
+            }
+            Err(e) => Err(format!("integrate(): {}", e)),
+        }
+    }),
+    symbolic("root", &[], Exact(4), |ev, args| {
+        let var = variable_arg(&args[1], "root")?;
+        let formula = Expr::parse_equation(&args[0])?
+            .expand_derivatives(ev.settings().angle)?
+            .to_tokens();
+        let a = bound_arg(ev, &args[2], "root")?;
+        let b = bound_arg(ev, &args[3], "root")?;
+        if !(a.is_finite() && b.is_finite()) {
+            return Err("root() needs a finite interval to search".to_string());
+        }
+        let (a, b) = (a.min(b), a.max(b));
+        let found = scan_roots(ev, &formula, &var, &roots::grid(a, b), "root")?;
+        match found.as_slice() {
+            [] => Err(format!("root() found no root between {} and {}", a, b)),
+            [root] => Ok(Value::Number(*root)),
+            _ => Ok(Value::Matrix(Matrix::column(found))),
+        }
+    }),
+    // Formatting
+    builtin("format", &[], Between(1, 2), |ev, args| {
+        let format = match args.get(1) {
+            None => ev.settings().format.clone(),
+            Some(Value::Text(spec)) => NumberFormat::from_spec(spec)?,
+            Some(_) => {
+                return Err("format() expects a text spec, e.g. \"sci digits=3\"".to_string());
+            }
+        };
+        Ok(Value::Text(format.format_value(&args[0])))
+    }),
+];
+
+lazy_static! {
+    static ref REGISTRY: HashMap<&'static str, &'static Builtin> = BUILTINS
+        .iter()
+        .flat_map(|b| std::iter::once(b.name)
+            .chain(b.aliases.iter().copied())
+            .map(move |n| (n, b)))
+        .collect();
+}
+
+/// Finds a built-in by name or alias, ignoring case.
+pub fn lookup(name: &str) -> Option<&'static Builtin> {
+    REGISTRY.get(name.to_lowercase().as_str()).copied()
+}
+
+/// Outside IEEE mode, rejects an argument for which `valid` fails.
+fn check_domain(
+    ev: &Evaluator,
+    arg: &Value,
+    valid: fn(f64) -> bool,
+    message: &str,
+) -> Result<(), String> {
+    if ev.settings().ieee || ev.settings().interval || matches!(arg, Value::Interval(_)) {
+        return Ok(());
+    }
+    match arg {
+        Value::Number(_) | Value::Uncertain(_) if !valid(arg.nominal()) => Err(message.to_string()),
+        _ => Ok(()),
+    }
+}
+
+/// The variable a formula is taken with respect to, such as the `x` in `diff(x^2, x)`.
+fn variable_arg(tokens: &[Token], name: &str) -> Result<String, String> {
+    match tokens {
+        [Token::Variable(var)] => Ok(var.clone()),
+        _ => Err(format!(
+            "{}() expects a variable name as its second argument",
+            name
+        )),
+    }
+}
+
+/// Symbols in a formula that are neither variables nor constants, in order.
+fn free_symbols(ev: &Evaluator, formula: &Expr) -> Vec<String> {
+    let known = |name: &str| {
+        ev.binds(name)
+            || constants::lookup_bare(name).is_some()
+            || matches!(name.to_lowercase().as_str(), "inf" | "infinity" | "nan")
+    };
+    formula
+        .symbols()
+        .into_iter()
+        .filter(|name| !known(name))
+        .collect()
+}
+
+/// A bound of integration, which may be `inf` or `-inf` outside IEEE mode too.
+fn bound_arg(ev: &mut Evaluator, tokens: &[Token], name: &str) -> Result<f64, String> {
+    let infinite = |name: &str| matches!(name.to_lowercase().as_str(), "inf" | "infinity");
+    match tokens {
+        [Token::Variable(name)] if infinite(name) => return Ok(f64::INFINITY),
+        [Token::UnaryMinus, Token::Variable(name)] if infinite(name) => {
+            return Ok(f64::NEG_INFINITY);
+        }
+        _ => {}
+    }
+    match ev.evaluate(tokens)? {
+        Value::Number(n) if !n.is_nan() => Ok(n),
+        Value::Percent(p) => Ok(p / 100.0),
+        _ => Err(format!("{}() expects plain numbers or inf as bounds", name)),
+    }
+}
+
+/// The value of a formula at `x`, which must come out as a plain number.
+fn formula_at(
+    ev: &mut Evaluator,
+    formula: &[Token],
+    var: &str,
+    x: f64,
+    name: &str,
+) -> Result<f64, String> {
+    match ev.evaluate_at(formula, var, Value::Number(x))? {
+        Value::Number(y) => Ok(y),
+        other => Err(format!(
+            "{}() expects the formula to give plain numbers, got {}",
+            name, other
+        )),
+    }
+}
+
+/// `solve(equation, x[, a, b])`: exact when the equation is linear in `x`,
+/// otherwise every root found between `a` and `b`, or across a wide range when
+/// they are left out. Several roots come back as a list.
+fn solve_equation(ev: &mut Evaluator, args: &[Vec<Token>]) -> Result<Value, String> {
+    let var = variable_arg(&args[1], "solve")?;
+    let angle = ev.settings().angle;
+    let formula = Expr::parse_equation(&args[0])?.expand_derivatives(angle)?;
+    let tokens = formula.to_tokens();
+    let (points, a, b) = match args {
+        [_, _] => {
+            let points = roots::wide_grid();
+            let (a, b) = (points[0], points[points.len() - 1]);
+            (points, a, b)
+        }
+        [_, _, a, b] => {
+            let (a, b) = (bound_arg(ev, a, "solve")?, bound_arg(ev, b, "solve")?);
+            if !(a.is_finite() && b.is_finite()) {
+                return Err(
+                    "solve() searches a finite interval; leave the bounds out to search widely"
+                        .to_string(),
+                );
+            }
+            let (a, b) = (a.min(b), a.max(b));
+            (roots::grid(a, b), a, b)
+        }
+        _ => {
+            return Err(
+                "solve() expects an equation, a variable and optionally two bounds to search between"
+                    .to_string(),
+            );
+        }
+    };
+    let none = || {
+        Err(format!(
+            "solve() found no solution for {} between {} and {}",
+            var, a, b
+        ))
+    };
+
+    // A straight line crosses zero once, at -offset / slope
+    if let Ok(slope) = formula.derivative(&var, angle)
+        && !slope.depends_on(&var)
+    {
+        let slope = formula_at(ev, &slope.to_tokens(), &var, 0.0, "solve")?;
+        let offset = formula_at(ev, &tokens, &var, 0.0, "solve")?;
+        if slope == 0.0 && offset == 0.0 {
+            return Err(format!("Every value of {} is a solution", var));
+        }
+        let root = -offset / slope;
+        return if slope != 0.0 && (a..=b).contains(&root) {
+            Ok(Value::Number(root))
+        } else {
+            none()
+        };
+    }
+
+    let found = scan_roots(ev, &tokens, &var, &points, "solve")?;
+    match found.as_slice() {
+        [] => none(),
+        [root] => Ok(Value::Number(*root)),
+        _ => Ok(Value::Matrix(Matrix::column(found))),
+    }
+}
+
+/// Every root of a formula in `var` found among `points`. Points where the
+/// formula is undefined are skipped, unless that is all of them.
+fn scan_roots(
+    ev: &mut Evaluator,
+    tokens: &[Token],
+    var: &str,
+    points: &[f64],
+    name: &str,
+) -> Result<Vec<f64>, String> {
+    let mut failure = None;
+    let mut defined = false;
+    let found = roots::all_roots(
+        |x| match formula_at(ev, tokens, var, x, name) {
+            Ok(y) => {
+                defined = true;
+                y
+            }
+            Err(e) => {
+                failure.get_or_insert(e);
+                f64::NAN
+            }
+        },
+        points,
+    );
+    match failure {
+        Some(e) if !defined => Err(e),
+        _ => Ok(found),
+    }
+}
+
+fn truth(value: bool) -> Value {
+    Value::Number(if value { 1.0 } else { 0.0 })
+}
+
+/// Applies a rounding function, which is flat wherever it is differentiable.
+fn round_with(
+    ev: &Evaluator,
+    arg: &Value,
+    round: impl Fn(f64) -> f64 + Copy,
+) -> Result<Value, String> {
+    ev.apply_unary(arg, round, |_| 0.0, |i| Ok(i.increasing_exact(round)))
+}
+
+/// Number of decimals to round to; negative counts round to tens, hundreds, ….
+fn decimal_count(value: &Value, name: &str) -> Result<i32, String> {
+    match integer_arg(value, name)? {
+        n @ -308..=308 => Ok(n as i32),
+        _ => Err(format!(
+            "{}() rounds to at most 308 decimals either side",
+            name
+        )),
+    }
+}
+
+/// An explicit rounding mode such as "half-even", or the session's.
+fn rounding_mode(ev: &Evaluator, mode: Option<&Value>, name: &str) -> Result<RoundingMode, String> {
+    match mode {
+        None => Ok(ev.settings().format.rounding),
+        Some(Value::Text(text)) => RoundingMode::parse(text).ok_or_else(|| {
+            format!(
+                "{}() mode must be \"half-up\", \"half-even\", \"half-down\", \"toward-zero\" or \"away-from-zero\"",
+                name
+            )
+        }),
+        Some(_) => Err(format!("{}() expects the rounding mode as text, e.g. \"half-even\"", name)),
+    }
+}
+
+/// Plain numbers, as many as the built-in takes.
+fn numbers<const N: usize>(args: &[Value], name: &str) -> Result<[f64; N], String> {
+    numbers_or(args, name, [0.0; N])
+}
+
+/// Like `numbers`, with trailing arguments that were left out taken from `defaults`.
+fn numbers_or<const N: usize>(
+    args: &[Value],
+    name: &str,
+    defaults: [f64; N],
+) -> Result<[f64; N], String> {
+    let mut numbers = defaults;
+    for (number, arg) in numbers.iter_mut().zip(args) {
+        *number = match arg {
+            Value::Number(n) => *n,
+            _ => return Err(format!("{}() expects plain numbers", name)),
+        };
+    }
+    Ok(numbers)
+}
+
+/// Calls `f` with plain numbers as `numbers_or` does; uncertain arguments are
+/// passed by their nominal value and propagated to first order through the
+/// partial derivatives `df`, keeping correlated sources together.
+fn propagate<const N: usize>(
+    args: &[Value],
+    name: &str,
+    defaults: [f64; N],
+    f: impl Fn([f64; N]) -> Result<f64, String>,
+    df: impl Fn([f64; N]) -> [f64; N],
+) -> Result<Value, String> {
+    if !args.iter().any(|arg| matches!(arg, Value::Uncertain(_))) {
+        return f(numbers_or(args, name, defaults)?).map(Value::Number);
+    }
+    let mut inputs = defaults.map(Uncertain::exact);
+    for (input, arg) in inputs.iter_mut().zip(args) {
+        *input = match arg {
+            Value::Number(n) => Uncertain::exact(*n),
+            Value::Uncertain(u) => u.clone(),
+            _ => return Err(format!("{}() expects plain or uncertain numbers", name)),
+        };
+    }
+    let nominal = inputs.each_ref().map(|u| u.value);
+    let value = f(nominal)?;
+    Ok(with_slopes(&inputs, value, &df(nominal)))
+}
+
+/// `value` with the uncertainty of each input scaled by its partial derivative.
+fn with_slopes(inputs: &[Uncertain], value: f64, slopes: &[f64]) -> Value {
+    let result = inputs
+        .iter()
+        .zip(slopes)
+        .fold(Uncertain::exact(value), |sum, (input, &slope)| {
+            sum.combine(input, value, 1.0, slope)
+        });
+    Value::Uncertain(result).normalize()
+}
+
+/// Like `propagate`, for built-ins over any mix of numbers and lists, as
+/// `number_list` reads them; list entries are exact.
+fn propagate_list(
+    args: &[Value],
+    name: &str,
+    f: impl Fn(&[f64]) -> f64,
+    df: impl Fn(&[f64]) -> Vec<f64>,
+) -> Result<Value, String> {
+    if !args.iter().any(|arg| matches!(arg, Value::Uncertain(_))) {
+        return Ok(Value::Number(f(&number_list(args, name)?)));
+    }
+    let mut inputs = Vec::new();
+    for arg in args {
+        match arg {
+            Value::Uncertain(u) => inputs.push(u.clone()),
+            other => {
+                let values = number_list(std::slice::from_ref(other), name)?;
+                inputs.extend(values.into_iter().map(Uncertain::exact));
+            }
+        }
+    }
+    let nominal: Vec<f64> = inputs.iter().map(|u| u.value).collect();
+    Ok(with_slopes(&inputs, f(&nominal), &df(&nominal)))
+}
+
+/// A special function's value, or `pole` in IEEE mode where it has none.
+fn or_pole(ev: &Evaluator, result: Result<f64, String>, pole: f64) -> Result<f64, String> {
+    match result {
+        Err(_) if ev.settings().ieee => Ok(pole),
+        result => result,
+    }
+}
+
+/// A special function's value, refused once it overflows unless in IEEE mode.
+fn finite(ev: &Evaluator, name: &str, value: f64) -> Result<f64, String> {
+    ev.special_result(name, value)?;
+    Ok(value)
+}
+
+/// `n` and `k` of `comb`/`perm`: integers with 0 <= k <= n.
+fn counts(args: &[Value], name: &str) -> Result<[u128; 2], String> {
+    let [n, k] = numbers(args, name)?;
+    if n < 0.0 || k < 0.0 || n.fract() != 0.0 || k.fract() != 0.0 || k > n {
+        return Err(format!("{}(n, k) expects 0 <= k <= n, both integers", name));
+    }
+    Ok([n as u128, k as u128])
+}
+
+/// `min`/`max` over numbers and lists, or over intervals bound by bound.
+/// The Euclidean norm, scaled by the largest magnitude so the squares cannot overflow.
+fn hypot(values: &[f64]) -> f64 {
+    let scale = values.iter().fold(0.0, |m: f64, x| m.max(x.abs()));
+    if scale == 0.0 || scale.is_infinite() {
+        return scale;
+    }
+    let sum: f64 = values.iter().map(|x| (x / scale).powi(2)).sum();
+    scale * sum.sqrt()
+}
+
+fn extreme(args: &[Value], name: &str, pick: fn(f64, f64) -> f64) -> Result<Value, String> {
+    if args.iter().any(|arg| matches!(arg, Value::Interval(_))) {
+        let bounds = args
+            .iter()
+            .map(Value::to_interval)
+            .collect::<Result<Vec<Interval>, String>>()?;
+        let lo = bounds.iter().map(|i| i.lo).reduce(pick).unwrap();
+        let hi = bounds.iter().map(|i| i.hi).reduce(pick).unwrap();
+        return Ok(Value::Interval(Interval::new(lo, hi)?).normalize());
+    }
+    // The uncertainty is that of whichever value is picked
+    let reduce = |values: &[f64]| values.iter().copied().reduce(pick).unwrap();
+    propagate_list(args, name, reduce, |values| {
+        let chosen = reduce(values);
+        let index = values.iter().position(|&x| x == chosen);
+        (0..values.len())
+            .map(|i| if Some(i) == index { 1.0 } else { 0.0 })
+            .collect()
+    })
+}
+
+/// Flattens numbers and vectors given to a statistics function into one sample.
+pub fn number_list(args: &[Value], name: &str) -> Result<Vec<f64>, String> {
+    let mut data = Vec::new();
+    for arg in args {
+        match arg {
+            Value::Number(n) => data.push(*n),
+            Value::Matrix(m) => data.extend_from_slice(m.as_vector()?),
+            _ => {
+                return Err(format!(
+                    "{}() expects numbers or lists of numbers, e.g. [1, 2, 3]",
+                    name
+                ));
+            }
+        }
+    }
+    if data.is_empty() {
+        return Err(format!("{}() expects at least one value", name));
+    }
+    Ok(data)
+}
+
+fn paired_lists(args: &[Value], name: &str) -> Result<[Vec<f64>; 2], String> {
+    Ok([
+        number_list(&args[..1], name)?,
+        number_list(&args[1..], name)?,
+    ])
+}
+
+fn matrix_arg<'a>(value: &'a Value, name: &str) -> Result<&'a Matrix, String> {
+    match value {
+        Value::Matrix(m) => Ok(m),
+        _ => Err(format!(
+            "{}() expects a matrix, e.g. [[1, 2], [3, 4]]",
+            name
+        )),
+    }
+}
+
+/// An integer argument, refused once it is too large for `f64` to hold exactly.
+fn integer(x: f64, name: &str) -> Result<i64, String> {
+    if x.fract() != 0.0 || !x.is_finite() {
+        return Err(format!("{}() expects integers, got {}", name, x));
+    }
+    // 2^53 itself is refused too, since 2^53 + 1 rounds onto it
+    if x.abs() >= number_theory::MAX_EXACT as f64 {
+        return Err(format!("{}() only handles integers below 2^53", name));
+    }
+    Ok(x as i64)
+}
+
+fn integer_arg(value: &Value, name: &str) -> Result<i64, String> {
+    match value {
+        Value::Number(n) => integer(*n, name),
+        _ => Err(format!("{}() expects an integer", name)),
+    }
+}
+
+fn positive_integer_arg(value: &Value, name: &str) -> Result<u64, String> {
+    match integer_arg(value, name)? {
+        n if n >= 1 => Ok(n as u64),
+        _ => Err(format!("{}() expects a positive integer", name)),
+    }
+}
+
+/// Absolute values of integer arguments, as `gcd` and `lcm` take them.
+fn magnitudes(args: &[Value], name: &str) -> Result<Vec<u64>, String> {
+    number_list(args, name)?
+        .into_iter()
+        .map(|x| integer(x, name).map(i64::unsigned_abs))
+        .collect()
+}
+
+fn exact_integer(n: u64) -> Result<Value, String> {
+    if n > number_theory::MAX_EXACT {
+        return Err(format!("{} is too large to represent exactly", n));
+    }
+    Ok(Value::Number(n as f64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry() {
+        // Every name and alias is unique and lower case
+        let mut names: Vec<&str> = BUILTINS
+            .iter()
+            .flat_map(|b| std::iter::once(b.name).chain(b.aliases.iter().copied()))
+            .collect();
+        assert!(names.iter().all(|n| *n == n.to_lowercase()));
+        let count = names.len();
+        names.sort_unstable();
+        names.dedup();
+        assert_eq!(names.len(), count);
+
+        assert_eq!(lookup("ASIN").unwrap().name, "arcsin");
+        assert_eq!(lookup("ln").unwrap().name, "log");
+        assert!(lookup("nosuch").is_none());
+
+        assert!(Exact(1).check("sqrt", 1).is_ok());
+        assert_eq!(
+            Exact(1).check("asin", 2).unwrap_err(),
+            "asin() expects 1 argument, got 2"
+        );
+        assert_eq!(
+            Between(1, 2).check("log", 3).unwrap_err(),
+            "log() expects 1 to 2 arguments, got 3"
+        );
+    }
+}
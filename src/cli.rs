@@ -2,6 +2,9 @@ use crate::evaluator::evaluate;
 use crate::functions;
 use crate::history::{HistoryEntry, HistoryManager};
 use crate::parser::Lexer;
+use crate::settings::AngleMode;
+use crate::symbolic::{self, Expr};
+use crate::value::Value;
 use regex::Regex;
 use std::io::{self, Write};
 
@@ -10,7 +13,7 @@ pub async fn run(history_manager: &HistoryManager) -> Result<(), String> {
     println!("Supported operators: +, -, *, /, ( ), %, ^");
     println!("Type 'help' for help, 'exit' to exit the program");
     loop {
-        print!("> ");
+        print!("{}> ", crate::settings::current().angle);
         io::stdout().flush().map_err(|e| e.to_string())?;
 
         let mut input = String::new();
@@ -35,6 +38,11 @@ pub async fn run(history_manager: &HistoryManager) -> Result<(), String> {
             continue;
         }
 
+        if input.eq_ignore_ascii_case("constants") {
+            show_constants();
+            continue;
+        }
+
         if input.eq_ignore_ascii_case("history") {
             show_history(history_manager).await;
             continue;
@@ -49,10 +57,46 @@ pub async fn run(history_manager: &HistoryManager) -> Result<(), String> {
             continue;
         }
 
+        if let Some(rest) = input.strip_prefix(":set") {
+            let mut parts = rest.split_whitespace();
+            match (parts.next(), parts.next()) {
+                (None, _) => println!("{}", crate::settings::current()),
+                (Some(key), Some(value)) => match crate::settings::set(key, value) {
+                    Ok(_) => println!("{} = {}", key, value),
+                    Err(e) => println!("Error: {}", e),
+                },
+                (Some(key), None) => println!("Error: Missing value for setting {}", key),
+            }
+            continue;
+        }
+
+        if let Some(rest) = input.strip_prefix(":seed") {
+            match rest.trim() {
+                "" => println!("seed = {}", crate::random::seed()),
+                seed => match seed.parse::<u64>() {
+                    Ok(seed) => {
+                        crate::random::set_seed(seed);
+                        println!("seed = {}", seed);
+                    }
+                    Err(_) => println!("Error: Expected a whole number seed, got '{}'", seed),
+                },
+            }
+            continue;
+        }
+
+        if input.eq_ignore_ascii_case(":save") {
+            match crate::settings::save_settings_async().await {
+                Ok(_) => println!("Settings saved"),
+                Err(e) => println!("Failed to save settings: {}", e),
+            }
+            continue;
+        }
+
         if input.starts_with("define ") {
             let def = input.strip_prefix("define ").unwrap();
             match define_function_async(def).await {
-                Ok(_) => println!("Function defined successfully"),
+                Ok(None) => println!("Function defined successfully"),
+                Ok(Some(simplified)) => println!("Function defined successfully: {}", simplified),
                 Err(e) => println!("Function definition failed: {}", e),
             }
             continue;
@@ -60,13 +104,9 @@ pub async fn run(history_manager: &HistoryManager) -> Result<(), String> {
 
         match calculate(input) {
             Ok(result) => {
-                println!(" = {}", result);
+                println!(" = {}", render(&result));
 
-                let entry = HistoryEntry {
-                    expression: input.to_string(),
-                    result,
-                    timestamp: crate::history::current_timestamp(),
-                };
+                let entry = HistoryEntry::new(input, &result);
 
                 let manager_clone = history_manager.clone_manager();
                 tokio::spawn(async move {
@@ -94,31 +134,145 @@ pub async fn run(history_manager: &HistoryManager) -> Result<(), String> {
     }
 }
 
-pub fn calculate(input: &str) -> Result<f64, String> {
+pub fn calculate(input: &str) -> Result<Value, String> {
     let mut lexer = Lexer::new(input);
     let tokens = lexer.tokenize()?;
     evaluate(&tokens)
 }
 
+/// Formats a result with the session's number format.
+pub fn render(value: &Value) -> String {
+    crate::settings::current().format.format_value(value)
+}
+
 fn show_help() {
     println!("\nUsage:");
     println!("  Enter a mathematical expression to calculate, e.g., 3+5*2");
-    println!("  Decimals are supported: 3.14, 0.5");
+    println!("  Decimals are supported: 3.14, 0.5, and exponents: 1e-9, 2.5E3");
+    println!("  SI suffixes are supported: 4.7k, 220n, 3.3M, 10µ (a trailing m means metres)");
+    println!(
+        "  In function bodies and the formulas of diff, integrate, solve and the like, 3k is 3 times k"
+    );
     println!("  Spaces are supported: 10 + 5 * 2");
     println!("  Parentheses are supported: (3+5)*2");
     println!("  Minus are supported: -5 + 3");
     println!("  Function calls are supported: f(2), g(1, 2)");
+    println!("  Uncertainties are supported: (2.0 ± 0.1) * 3, or 2.0 +- 0.1");
+    println!("  +- means ± only with a space on each side; 2+-4 adds a negative");
+    println!("  Intervals are supported in interval mode: [1, 2] * [-3, 4]");
+    println!(
+        "  Functions: sqrt, cbrt, abs, sign, floor, ceil, round, min, max, hypot, exp, log (ln), log10, log2"
+    );
+    println!(
+        "  log(x, base), sin, cos, tan, arcsin (asin), arccos (acos), arctan (atan), atan2(y, x)"
+    );
+    println!("  sinh, cosh, tanh, asinh, acosh, atanh, fact, comb(n, k), perm(n, k)");
+    println!("  Angles can carry a unit: sin(30deg), cos(1.2rad), tan(50grad)");
+    println!("  Angles convert with deg(x) (from radians) and rad(x) (from degrees)");
+    println!("  Results can be formatted as text: format(1/3, \"sci digits=3\")");
+    println!("  Matrices and vectors: [[1, 2], [3, 4]] * [1, 1], det(A), inv(A), solve(A, b)");
+    println!("  Linear algebra: transpose, rank, trace, eig, dot(u, v), cross(u, v)");
+    println!("  Dates: 2026-10-16 + 30 days, date(2026, 12, 25) - today(), weekday(d)");
+    println!(
+        "  Business days: businessdays(start, end), workday(d, n); durations: duration(\"P1DT2H\")"
+    );
+    println!("  Units: 5 km + 300 m, 3 kg * 9.81 m/s^2 to N, 60 mph in m/s, 20 degC to degF");
+    println!(
+        "  Percentages: 200 + 15%, 15% of 80, 50 of 200 as % (7 % 3 with an operand is modulo)"
+    );
+    println!(
+        "  Statistics: mean, median, mode, variance/pvariance, stdev/pstdev, skewness, kurtosis, quartiles"
+    );
+    println!(
+        "  take numbers or lists: mean(1, 2, 3), median([4, 8, 15]), percentile([1, 5, 9], 90)"
+    );
+    println!("  correlation(x, y) and covariance(x, y) take two lists of the same length");
+    println!(
+        "  Number theory: gcd, lcm, isprime, nextprime, factor(120) = 2^3 * 3 * 5, divisors, totient"
+    );
+    println!("  modpow(b, e, m), modinv(a, m), crt([2, 3], [3, 5]); exact for integers below 2^53");
+    println!("  Special functions: gamma, lgamma, digamma, beta(a, b), erf, erfc, erfinv, j0, j1");
+    println!("  gammainc(a, x) and gammaincc(a, x) are the regularized incomplete gamma functions");
+    println!(
+        "  Distributions: normpdf/normcdf(x[, mu, sigma]), invnorm(p[, mu, sigma]), tcdf(x, df), chi2cdf(x, df)"
+    );
+    println!("  binompmf(k, n, p), poissonpmf(k, lambda), expcdf(x[, rate]), unifcdf(x[, a, b]);");
+    println!("  each family has pdf (pmf), cdf and inv forms, e.g. invt(p, df), invbinom(q, n, p)");
+    println!(
+        "  Rounding: round(x[, decimals[, mode]]), trunc(x[, decimals]), roundsig(x, n), mround(x, m)"
+    );
+    println!(
+        "  e.g. round(2.665, 2, \"half-even\") = 2.66; the default mode is the rounding setting"
+    );
+    println!(
+        "  Finance: pmt(rate, nper, pv[, fv, type]), pv, fv, nper, rate(nper, pmt, pv), npv(rate, flows)"
+    );
+    println!("  irr([flows][, guess]), xirr([flows], date1, date2, ...); rates are per period");
+    println!("  amortize(principal, rate, periods) prints a repayment schedule");
+    println!(
+        "  Random: rand(), randint(a, b), randn([mu, sigma]), choice([1, 2, 3]) or choice(\"a\", \"b\")"
+    );
+    println!("  Factorials: 5!, fact(5); non-integers use gamma: 0.5! = gamma(1.5)");
+    println!(
+        "  Calculus: diff(x^2 sin(x), x) gives the derivative as a formula, diff(expr, x, at) its value"
+    );
+    println!("  simplify(expr), expand((x + 1)^3) and factor(x^2 - 1) rewrite formulas");
+    println!("  integrate(expr, x, a, b) integrates numerically; a and b may be -inf or inf");
+    println!(
+        "  solve(x^2 = 2, x[, a, b]) lists the solutions (between a and b), root(f, x, a, b) lists the roots of f between a and b"
+    );
     println!("\nCommands:");
     println!("  help         - Displays help information");
     println!("  clear        - Clear the screen");
     println!("  history      - Display history");
+    println!("  constants    - List built-in constants; use const.name when a variable hides one");
     println!("  clearhistory - Clear history");
+    println!("  :set         - Show settings");
+    println!("  :set key val - Change a setting, e.g. :set angle deg");
+    println!("  :save        - Save the current settings as defaults");
+    println!("  :seed [n]    - Show the random seed, or restart rand() and friends from seed n");
+    println!("  solve 3x + 5 = 20 for x - Solve an equation for one variable");
+    println!("\nSettings:");
+    println!("  interval     - on/off, evaluate with guaranteed interval bounds");
+    println!("  angle        - rad, deg or grad, the unit of trigonometric functions");
+    println!(
+        "  ieee         - on/off, 1/0 = inf and 0/0 = nan instead of errors; isnan, isinf, isfinite"
+    );
+    println!("  notation     - auto, fixed, sci, eng or si (4.7µ)");
+    println!("  digits       - significant digits, or off");
+    println!("  decimals     - digits after the decimal point, or off");
+    println!("  separators   - on/off, group thousands: 1,234,567");
+    println!("  trim         - on/off, drop trailing zeros");
+    println!("  fraction     - off, improper (3/8), mixed (1 3/8) or cf ([0; 2, 1, 2])");
+    println!("  maxden       - largest denominator shown as a fraction");
+    println!("  fractol      - relative error allowed for a fraction, e.g. 1e-9");
+    println!(
+        "  rounding     - half-up, half-even (banker's), half-down, toward-zero or away-from-zero"
+    );
     println!("  exit         - Exit the program");
     println!("\nNotes:");
     println!("  * The divisor cannot be 0 in a division operation");
     println!("  * Function customization is supported");
     println!("  * A negative base with a fractional exponent will lead to an error");
     println!("  * Power operations support right associativity (2^3^2 = 2^(3^2) = 512)");
+    println!("  * Uncertainties propagate to first order and stay correlated when reused");
+    println!("  * Units must match when adding; `to`/`in` conversions go at the end");
+}
+
+fn show_constants() {
+    for constant in crate::constants::CONSTANTS {
+        let value = format!(
+            "{} {}",
+            render(&Value::Number(constant.value)),
+            constant.unit
+        );
+        println!(
+            "  {:6} = {:28} {}",
+            constant.name,
+            value.trim_end(),
+            constant.description
+        );
+    }
 }
 
 fn clear_screen() {
@@ -135,11 +289,22 @@ pub async fn show_history(manager: &HistoryManager) {
 
             println!("History:");
             for (i, entry) in history.iter().rev().enumerate() {
+                let mut notes = match entry.angle {
+                    AngleMode::Radians => String::new(),
+                    other => format!(" ({})", other),
+                };
+                if let Some(seed) = entry.seed {
+                    notes.push_str(&format!(" (seed {})", seed));
+                }
                 println!(
-                    "{:2}. {} = {} [{}]",
+                    "{:2}. {}{} = {} [{}]",
                     i + 1,
                     entry.expression,
-                    entry.result,
+                    notes,
+                    entry
+                        .display
+                        .clone()
+                        .unwrap_or_else(|| render(&Value::Number(entry.result))),
                     entry.timestamp
                 );
             }
@@ -150,7 +315,9 @@ pub async fn show_history(manager: &HistoryManager) {
     }
 }
 
-pub async fn define_function_async(definition: &str) -> Result<(), String> {
+/// Defines a custom function. The definition is returned with its body
+/// simplified when that reads differently from what was typed.
+pub async fn define_function_async(definition: &str) -> Result<Option<String>, String> {
     let re = Regex::new(r"^\s*([a-zA-Z_][a-zA-Z0-9_]*)\s*\((.*?)\)\s*=\s*(.+)\s*$").unwrap();
     let caps = re
         .captures(definition)
@@ -172,5 +339,16 @@ pub async fn define_function_async(definition: &str) -> Result<(), String> {
         return Err("Parameter names must be unique".to_string());
     }
 
-    crate::functions::register_custom_function_async(name, parameters, expression).await
+    let simplified = simplified_body(expression)
+        .map(|body| format!("{}({}) = {}", name, parameters.join(", "), body));
+    crate::functions::register_custom_function_async(name, parameters, expression).await?;
+    Ok(simplified)
+}
+
+/// The body of a definition after simplification, if that changes more than spacing.
+fn simplified_body(expression: &str) -> Option<String> {
+    let tokens = Lexer::formula(expression).tokenize().ok()?;
+    let body = symbolic::simplify(&Expr::parse(&tokens).ok()?).to_string();
+    let spaceless = |s: &str| s.split_whitespace().collect::<String>();
+    (spaceless(&body) != spaceless(expression)).then_some(body)
 }
@@ -0,0 +1,166 @@
+use crate::units::{Quantity, Unit};
+use crate::value::Value;
+use std::f64::consts;
+
+/// A named constant. Physical constants carry a unit and use CODATA 2018 values.
+pub struct Constant {
+    pub name: &'static str,
+    pub value: f64,
+    /// Unit expression, empty for plain numbers.
+    pub unit: &'static str,
+    pub description: &'static str,
+}
+
+const fn constant(
+    name: &'static str,
+    value: f64,
+    unit: &'static str,
+    description: &'static str,
+) -> Constant {
+    Constant {
+        name,
+        value,
+        unit,
+        description,
+    }
+}
+
+/// Constants reachable by bare name, unless a variable of the same name hides
+/// them, and always as `const.<name>`.
+pub const CONSTANTS: &[Constant] = &[
+    constant(
+        "pi",
+        consts::PI,
+        "",
+        "ratio of a circle's circumference to its diameter",
+    ),
+    constant("tau", consts::TAU, "", "2π, one full turn in radians"),
+    constant(
+        "e",
+        consts::E,
+        "",
+        "Euler's number, base of the natural logarithm",
+    ),
+    constant("phi", 1.618033988749895, "", "golden ratio"),
+    constant("gamma", 0.5772156649015329, "", "Euler–Mascheroni constant"),
+    constant("sqrt2", consts::SQRT_2, "", "square root of 2"),
+    constant("ln2", consts::LN_2, "", "natural logarithm of 2"),
+    constant("ln10", consts::LN_10, "", "natural logarithm of 10"),
+    constant("c", 299792458.0, "m/s", "speed of light in vacuum (exact)"),
+    constant(
+        "G",
+        6.67430e-11,
+        "m^3/kg/s^2",
+        "Newtonian constant of gravitation",
+    ),
+    constant(
+        "h",
+        6.62607015e-34,
+        "J*s",
+        "Planck constant (exact), bare as planck",
+    ),
+    constant("hbar", 1.054571817e-34, "J*s", "reduced Planck constant"),
+    constant("k_B", 1.380649e-23, "J/K", "Boltzmann constant (exact)"),
+    constant("N_A", 6.02214076e23, "mol^-1", "Avogadro constant (exact)"),
+    constant("R", 8.314462618, "J/mol/K", "molar gas constant"),
+    constant("q_e", 1.602176634e-19, "C", "elementary charge (exact)"),
+    constant("m_e", 9.1093837015e-31, "kg", "electron mass"),
+    constant("m_p", 1.67262192369e-27, "kg", "proton mass"),
+    constant("m_n", 1.67492749804e-27, "kg", "neutron mass"),
+    constant("m_u", 1.66053906660e-27, "kg", "atomic mass constant"),
+    constant(
+        "eps0",
+        8.8541878128e-12,
+        "F/m",
+        "vacuum electric permittivity",
+    ),
+    constant(
+        "mu0",
+        1.25663706212e-6,
+        "H/m",
+        "vacuum magnetic permeability",
+    ),
+    constant(
+        "sigma",
+        5.670374419e-8,
+        "W/m^2/K^4",
+        "Stefan–Boltzmann constant",
+    ),
+    constant("alpha", 7.2973525693e-3, "", "fine-structure constant"),
+    constant("a0", 5.29177210903e-11, "m", "Bohr radius"),
+    constant(
+        "g0",
+        9.80665,
+        "m/s^2",
+        "standard acceleration of gravity (exact)",
+    ),
+];
+
+/// Other names of constants, as alias and name.
+const ALIASES: &[(&str, &str)] = &[("planck", "h")];
+
+/// Constants only reached as `const.<name>`, since their bare name is a unit.
+const QUALIFIED: &[&str] = &["h"];
+
+/// Finds a constant by exact name or alias; unitless constants also match
+/// case-insensitively (`PI`).
+pub fn lookup(name: &str) -> Option<&'static Constant> {
+    let name = ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == name)
+        .map_or(name, |(_, target)| target);
+    CONSTANTS.iter().find(|c| c.name == name).or_else(|| {
+        CONSTANTS
+            .iter()
+            .find(|c| c.unit.is_empty() && c.name.eq_ignore_ascii_case(name))
+    })
+}
+
+/// Finds a constant written without `const.`, where `h` is still the hour.
+pub fn lookup_bare(name: &str) -> Option<&'static Constant> {
+    lookup(name).filter(|c| c.name != name || !QUALIFIED.contains(&name))
+}
+
+impl Constant {
+    pub fn to_value(&self) -> Value {
+        match Unit::parse(self.unit) {
+            Ok(unit) => Value::Quantity(Quantity::new(self.value, unit)),
+            Err(_) => Value::Number(self.value),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_units_are_consistent() {
+        for constant in CONSTANTS.iter().filter(|c| !c.unit.is_empty()) {
+            assert!(Unit::parse(constant.unit).is_ok(), "{}", constant.name);
+        }
+        // A constant named like a unit hides it; hbar only shadows the hectobar,
+        // and h is left to the hour unless written const.h
+        for constant in CONSTANTS.iter().filter(|c| c.name != "hbar") {
+            let bare = lookup_bare(constant.name).is_some();
+            assert!(
+                !bare || Unit::lookup(constant.name).is_none(),
+                "{}",
+                constant.name
+            );
+        }
+        assert!(lookup_bare("h").is_none());
+        assert_eq!(lookup_bare("planck").unwrap().name, "h");
+        // h = 2π·ħ and R = N_A·k_B, within their published precision
+        let (h, hbar) = (lookup("h").unwrap(), lookup("hbar").unwrap());
+        assert!((h.value / (consts::TAU * hbar.value) - 1.0).abs() < 1e-9);
+        let (r, n_a, k_b) = (
+            lookup("R").unwrap(),
+            lookup("N_A").unwrap(),
+            lookup("k_B").unwrap(),
+        );
+        assert!((r.value / (n_a.value * k_b.value) - 1.0).abs() < 1e-9);
+        assert_eq!(lookup("PI").unwrap().value, consts::PI);
+        assert!(lookup("C").is_none());
+    }
+}
@@ -0,0 +1,476 @@
+use chrono::{Datelike, Duration, Months, NaiveDate, NaiveDateTime, Timelike, Weekday};
+use std::fmt;
+
+/// A length of time: whole calendar months plus an exact duration.
+///
+/// Months and years are kept apart because their length depends on the date they
+/// are added to; `2026-01-31 + 1 month` is `2026-02-28`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Span {
+    pub months: i32,
+    pub time: Duration,
+}
+
+impl Span {
+    pub fn months(months: i32) -> Self {
+        Span {
+            months,
+            time: Duration::zero(),
+        }
+    }
+
+    pub fn seconds(seconds: f64) -> Result<Self, String> {
+        let millis = (seconds * 1000.0).round();
+        if !millis.is_finite() || millis.abs() > i64::MAX as f64 {
+            return Err("Duration is out of range".to_string());
+        }
+        Ok(Span {
+            months: 0,
+            time: Duration::milliseconds(millis as i64),
+        })
+    }
+
+    /// `amount` of a unit such as `days` or `weeks`, or `None` for an unknown unit.
+    pub fn from_unit(amount: f64, unit: &str) -> Option<Result<Self, String>> {
+        let seconds = match unit.to_lowercase().as_str() {
+            "second" | "seconds" | "sec" | "secs" => 1.0,
+            "minute" | "minutes" => 60.0,
+            "hour" | "hours" => 3600.0,
+            "day" | "days" => 86400.0,
+            "week" | "weeks" => 604800.0,
+            "month" | "months" => return Some(Self::whole_months(amount, 1)),
+            "year" | "years" => return Some(Self::whole_months(amount, 12)),
+            _ => return None,
+        };
+        Some(Self::seconds(amount * seconds))
+    }
+
+    fn whole_months(amount: f64, per_unit: i32) -> Result<Self, String> {
+        let months = amount * per_unit as f64;
+        if months.fract() != 0.0 || months.abs() > i32::MAX as f64 {
+            return Err("Months and years must be whole numbers".to_string());
+        }
+        Ok(Span::months(months as i32))
+    }
+
+    pub fn total_seconds(&self) -> f64 {
+        self.time.num_milliseconds() as f64 / 1000.0
+    }
+
+    pub fn neg(&self) -> Self {
+        Span {
+            months: -self.months,
+            time: -self.time,
+        }
+    }
+
+    pub fn add(&self, other: &Span) -> Result<Self, String> {
+        match (
+            self.months.checked_add(other.months),
+            self.time.checked_add(&other.time),
+        ) {
+            (Some(months), Some(time)) => Ok(Span { months, time }),
+            _ => Err("Duration is out of range".to_string()),
+        }
+    }
+
+    pub fn scale(&self, factor: f64) -> Result<Self, String> {
+        let months = self.months as f64 * factor;
+        if months.fract() != 0.0 {
+            return Err("Months and years can only be scaled to whole numbers".to_string());
+        }
+        if months.abs() > i32::MAX as f64 {
+            return Err("Duration is out of range".to_string());
+        }
+        Ok(Span {
+            months: months as i32,
+            time: Self::seconds(self.total_seconds() * factor)?.time,
+        })
+    }
+
+    /// How many times `other` fits into `self`; both must be exact or both whole months.
+    pub fn ratio(&self, other: &Span) -> Result<f64, String> {
+        match (self.months, other.months) {
+            (0, 0) if !other.time.is_zero() => Ok(self.total_seconds() / other.total_seconds()),
+            (a, b) if b != 0 && self.time.is_zero() && other.time.is_zero() => {
+                Ok(a as f64 / b as f64)
+            }
+            _ if other.months == 0 && other.time.is_zero() => {
+                Err("Division by a zero duration".to_string())
+            }
+            _ => Err("Cannot divide calendar months by an exact duration".to_string()),
+        }
+    }
+
+    /// Parses an ISO-8601 duration such as `P1Y2M10DT2H30M` or `P3W`.
+    pub fn parse_iso(text: &str) -> Result<Self, String> {
+        let invalid = || format!("Invalid ISO-8601 duration: {}", text);
+        let (negative, rest) = match text.trim().strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, text.trim()),
+        };
+        let rest = rest
+            .strip_prefix('P')
+            .or_else(|| rest.strip_prefix('p'))
+            .ok_or_else(invalid)?;
+        let (date_part, time_part) = match rest.split_once(['T', 't']) {
+            Some((date, time)) if !time.is_empty() => (date, Some(time)),
+            Some(_) => return Err(invalid()),
+            None => (rest, None),
+        };
+        if date_part.is_empty() && time_part.is_none() {
+            return Err(invalid());
+        }
+
+        let mut span = Span::months(0);
+        for (part, units) in [(date_part, "YMWD"), (time_part.unwrap_or(""), "HMS")] {
+            let mut number = String::new();
+            let mut last = 0;
+            for c in part.chars() {
+                if c.is_ascii_digit() || c == '.' || c == ',' {
+                    number.push(if c == ',' { '.' } else { c });
+                    continue;
+                }
+                let c = c.to_ascii_uppercase();
+                // Designators must appear once each, in order
+                let position = units.find(c).filter(|&p| p >= last).ok_or_else(invalid)?;
+                last = position + 1;
+                let amount: f64 = number.parse().map_err(|_| invalid())?;
+                number.clear();
+                let unit = match (units, c) {
+                    ("YMWD", 'Y') => "years",
+                    ("YMWD", 'M') => "months",
+                    ("YMWD", 'W') => "weeks",
+                    ("YMWD", 'D') => "days",
+                    (_, 'H') => "hours",
+                    (_, 'M') => "minutes",
+                    _ => "seconds",
+                };
+                span = span.add(&Span::from_unit(amount, unit).unwrap()?)?;
+            }
+            if !number.is_empty() {
+                return Err(invalid());
+            }
+        }
+        Ok(if negative { span.neg() } else { span })
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let negative = self.months < 0 || (self.months == 0 && self.time < Duration::zero());
+        let span = if negative { self.neg() } else { *self };
+        let millis = span.time.num_milliseconds();
+        let parts = [
+            (span.months as i64 / 12, "year"),
+            (span.months as i64 % 12, "month"),
+            (millis / 86_400_000, "day"),
+            (millis / 3_600_000 % 24, "hour"),
+            (millis / 60_000 % 60, "minute"),
+        ];
+        let mut words: Vec<String> = parts
+            .iter()
+            .filter(|(n, _)| *n != 0)
+            .map(|(n, unit)| format!("{} {}{}", n, unit, if *n == 1 { "" } else { "s" }))
+            .collect();
+        let seconds = (millis % 60_000) as f64 / 1000.0;
+        if seconds != 0.0 {
+            let plural = if seconds == 1.0 { "" } else { "s" };
+            words.push(format!("{} second{}", seconds, plural));
+        }
+        if words.is_empty() {
+            return write!(f, "0 days");
+        }
+        write!(f, "{}{}", if negative { "-" } else { "" }, words.join(" "))
+    }
+}
+
+pub fn add(date: NaiveDateTime, span: &Span) -> Result<NaiveDateTime, String> {
+    let shifted = if span.months >= 0 {
+        date.checked_add_months(Months::new(span.months as u32))
+    } else {
+        date.checked_sub_months(Months::new(span.months.unsigned_abs()))
+    };
+    shifted
+        .and_then(|d| d.checked_add_signed(span.time))
+        .ok_or_else(|| "Date is out of range".to_string())
+}
+
+pub fn difference(a: NaiveDateTime, b: NaiveDateTime) -> Span {
+    Span {
+        months: 0,
+        time: a - b,
+    }
+}
+
+/// Parses `2026-10-16`, `2026-10-16T09:30` or `2026-10-16T09:30:15`.
+pub fn parse_date(text: &str) -> Result<NaiveDateTime, String> {
+    let text = text.trim();
+    for format in [
+        "%Y-%m-%dT%H:%M:%S",
+        "%Y-%m-%dT%H:%M",
+        "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%d %H:%M",
+    ] {
+        if let Ok(date) = NaiveDateTime::parse_from_str(text, format) {
+            return Ok(date);
+        }
+    }
+    NaiveDate::parse_from_str(text, "%Y-%m-%d")
+        .map(|d| d.and_hms_opt(0, 0, 0).unwrap())
+        .map_err(|_| format!("Invalid date: {}", text))
+}
+
+pub fn from_parts(parts: &[f64]) -> Result<NaiveDateTime, String> {
+    if parts.iter().any(|p| p.fract() != 0.0) {
+        return Err("date() expects whole numbers".to_string());
+    }
+    let get = |i: usize| parts.get(i).copied().unwrap_or(0.0);
+    NaiveDate::from_ymd_opt(get(0) as i32, get(1) as u32, get(2) as u32)
+        .and_then(|d| d.and_hms_opt(get(3) as u32, get(4) as u32, get(5) as u32))
+        .ok_or_else(|| "Invalid date".to_string())
+}
+
+pub fn weekday_name(date: NaiveDateTime) -> &'static str {
+    match date.weekday() {
+        Weekday::Mon => "Monday",
+        Weekday::Tue => "Tuesday",
+        Weekday::Wed => "Wednesday",
+        Weekday::Thu => "Thursday",
+        Weekday::Fri => "Friday",
+        Weekday::Sat => "Saturday",
+        Weekday::Sun => "Sunday",
+    }
+}
+
+fn is_business_day(date: NaiveDate) -> bool {
+    !matches!(date.weekday(), Weekday::Sat | Weekday::Sun)
+}
+
+/// Monday-to-Friday days from `start` up to but not including `end`; negative if
+/// `end` comes first.
+pub fn business_days(start: NaiveDateTime, end: NaiveDateTime) -> i64 {
+    let (start, end) = (start.date(), end.date());
+    if end < start {
+        return -business_days(end.into(), start.into());
+    }
+    let days = (end - start).num_days();
+    let (weeks, rest) = (days / 7, days % 7);
+    let extra = (0..rest)
+        .filter(|&i| is_business_day(start + Duration::days(i)))
+        .count() as i64;
+    weeks * 5 + extra
+}
+
+/// The date `n` business days after `date` (before, for negative `n`). A weekend
+/// start counts from the business day just before it in the direction of travel.
+pub fn add_business_days(date: NaiveDateTime, n: i64) -> Result<NaiveDateTime, String> {
+    let step = Span::seconds(if n < 0 { -86400.0 } else { 86400.0 })?;
+    let mut day = date;
+    while !is_business_day(day.date()) {
+        day = add(day, &step.neg())?;
+    }
+    day = add(day, &Span::seconds((n / 5) as f64 * 604800.0)?)?;
+    let mut remaining = (n % 5).abs();
+    while remaining > 0 {
+        day = add(day, &step)?;
+        if is_business_day(day.date()) {
+            remaining -= 1;
+        }
+    }
+    Ok(day)
+}
+
+pub fn format_date(date: &NaiveDateTime) -> String {
+    match (date.hour(), date.minute(), date.second()) {
+        (0, 0, 0) => date.format("%Y-%m-%d").to_string(),
+        (_, _, 0) => date.format("%Y-%m-%d %H:%M").to_string(),
+        _ => date.format("%Y-%m-%d %H:%M:%S").to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(text: &str) -> NaiveDateTime {
+        parse_date(text).unwrap()
+    }
+
+    #[test]
+    fn test_calendar_arithmetic() {
+        let month = Span::months(1);
+        assert_eq!(add(date("2026-01-31"), &month).unwrap(), date("2026-02-28"));
+        let days = Span::from_unit(30.0, "days").unwrap().unwrap();
+        assert_eq!(add(date("2026-10-16"), &days).unwrap(), date("2026-11-15"));
+        assert_eq!(
+            difference(date("2026-12-25"), date("2026-10-16")).to_string(),
+            "70 days"
+        );
+        assert_eq!(
+            Span::seconds(-93784.5).unwrap().to_string(),
+            "-1 day 2 hours 3 minutes 4.5 seconds"
+        );
+    }
+
+    #[test]
+    fn test_month_ends_and_leap_days() {
+        let year = Span::months(12);
+        assert_eq!(add(date("2024-02-29"), &year).unwrap(), date("2025-02-28"));
+        assert_eq!(
+            add(date("2024-02-29"), &year.scale(4.0).unwrap()).unwrap(),
+            date("2028-02-29")
+        );
+        assert_eq!(
+            add(date("2026-03-31"), &Span::months(-1)).unwrap(),
+            date("2026-02-28")
+        );
+        // Months first, then the exact part: Jan 31 + 1 month is Feb 28, plus a day
+        let span = Span::months(1)
+            .add(&Span::from_unit(1.0, "day").unwrap().unwrap())
+            .unwrap();
+        assert_eq!(add(date("2026-01-31"), &span).unwrap(), date("2026-03-01"));
+        assert_eq!(span.to_string(), "1 month 1 day");
+        // A clock time survives the month shift
+        assert_eq!(
+            add(date("2026-01-31T23:30"), &Span::months(1)).unwrap(),
+            date("2026-02-28T23:30")
+        );
+        assert!(add(date("2026-01-01"), &Span::months(i32::MAX)).is_err());
+    }
+
+    #[test]
+    fn test_span_arithmetic_errors() {
+        assert!(Span::from_unit(1.5, "months").unwrap().is_err());
+        assert_eq!(
+            Span::from_unit(0.5, "years").unwrap().unwrap(),
+            Span::months(6)
+        );
+        assert!(Span::from_unit(1.0, "fortnights").is_none());
+        assert!(Span::seconds(f64::INFINITY).is_err());
+        assert!(Span::seconds(1e300).is_err());
+        assert!(Span::months(1).scale(0.5).is_err());
+        // Too many months for the count, rather than silently saturating
+        assert!(Span::months(12).scale(3e9).is_err());
+        assert!(Span::months(i32::MAX).add(&Span::months(1)).is_err());
+
+        let day = Span::seconds(86400.0).unwrap();
+        let week = Span::from_unit(1.0, "week").unwrap().unwrap();
+        assert_eq!(week.ratio(&day).unwrap(), 7.0);
+        assert_eq!(Span::months(18).ratio(&Span::months(12)).unwrap(), 1.5);
+        assert!(day.ratio(&Span::months(0)).is_err());
+        assert!(Span::months(1).ratio(&day).is_err());
+        assert!(day.ratio(&Span::months(1)).is_err());
+    }
+
+    #[test]
+    fn test_iso_durations() {
+        let span = Span::parse_iso("P1Y2M10DT2H30M").unwrap();
+        assert_eq!(span.months, 14);
+        assert_eq!(span.time, Duration::days(10) + Duration::minutes(150));
+        assert_eq!(Span::parse_iso("P3W").unwrap().time, Duration::weeks(3));
+        assert_eq!(
+            Span::parse_iso("PT1.5S").unwrap().time,
+            Duration::milliseconds(1500)
+        );
+        // M is months before the T and minutes after it
+        assert_eq!(Span::parse_iso("P1M").unwrap(), Span::months(1));
+        assert_eq!(Span::parse_iso("PT1M").unwrap().time, Duration::minutes(1));
+        assert_eq!(
+            Span::parse_iso("p1dt12h").unwrap().time,
+            Duration::hours(36)
+        );
+        assert_eq!(
+            Span::parse_iso("PT0,5H").unwrap().time,
+            Duration::minutes(30)
+        );
+        assert_eq!(
+            Span::parse_iso("-P1M2D").unwrap().to_string(),
+            "-1 month 2 days"
+        );
+        assert!(Span::parse_iso("P").is_err());
+        assert!(Span::parse_iso("PT").is_err());
+        assert!(Span::parse_iso("P1DT").is_err());
+        assert!(Span::parse_iso("P1D2Y").is_err());
+        assert!(Span::parse_iso("P1D1D").is_err());
+        assert!(Span::parse_iso("P1").is_err());
+        assert!(Span::parse_iso("P1.5M").is_err());
+        assert!(Span::parse_iso("1D").is_err());
+    }
+
+    #[test]
+    fn test_dates() {
+        assert_eq!(format_date(&date("2026-10-16")), "2026-10-16");
+        assert_eq!(format_date(&date("2026-10-16 09:30")), "2026-10-16 09:30");
+        assert_eq!(
+            format_date(&date(" 2026-10-16T09:30:15 ")),
+            "2026-10-16 09:30:15"
+        );
+        assert!(parse_date("2026-02-30").is_err());
+        assert!(parse_date("16/10/2026").is_err());
+        assert!(parse_date("2026-10-16T25:00").is_err());
+
+        assert_eq!(
+            from_parts(&[2024.0, 2.0, 29.0]).unwrap(),
+            date("2024-02-29")
+        );
+        assert_eq!(
+            from_parts(&[2026.0, 10.0, 16.0, 9.0, 30.0]).unwrap(),
+            date("2026-10-16T09:30")
+        );
+        assert!(from_parts(&[2026.0, 2.0, 29.0]).is_err());
+        assert!(from_parts(&[2026.0, 13.0, 1.0]).is_err());
+        assert!(from_parts(&[2026.0, 10.5, 1.0]).is_err());
+
+        let earlier = difference(date("2026-10-16"), date("2026-10-16T12:00"));
+        assert_eq!(earlier.to_string(), "-12 hours");
+        assert_eq!(
+            difference(date("2026-10-16"), date("2026-10-16")).to_string(),
+            "0 days"
+        );
+    }
+
+    #[test]
+    fn test_business_days() {
+        // 2026-10-16 is a Friday
+        assert_eq!(weekday_name(date("2026-10-16")), "Friday");
+        assert_eq!(business_days(date("2026-10-16"), date("2026-10-23")), 5);
+        assert_eq!(business_days(date("2026-10-17"), date("2026-10-19")), 0);
+        assert_eq!(business_days(date("2026-10-23"), date("2026-10-16")), -5);
+        // The end day is excluded and the time of day ignored
+        assert_eq!(
+            business_days(date("2026-10-16T18:00"), date("2026-10-19T08:00")),
+            1
+        );
+        assert_eq!(
+            business_days(date("2026-10-16T08:00"), date("2026-10-16T18:00")),
+            0
+        );
+        assert_eq!(
+            add_business_days(date("2026-10-16"), 1).unwrap(),
+            date("2026-10-19")
+        );
+        assert_eq!(
+            add_business_days(date("2026-10-19"), -1).unwrap(),
+            date("2026-10-16")
+        );
+        assert_eq!(
+            add_business_days(date("2026-10-16"), 10).unwrap(),
+            date("2026-10-30")
+        );
+        // From a weekend, count from the business day behind it in the direction of travel
+        assert_eq!(
+            add_business_days(date("2026-10-17"), 5).unwrap(),
+            date("2026-10-23")
+        );
+        assert_eq!(
+            add_business_days(date("2026-10-18"), -1).unwrap(),
+            date("2026-10-16")
+        );
+        assert_eq!(
+            add_business_days(date("2026-10-17"), 0).unwrap(),
+            date("2026-10-16")
+        );
+        assert!(add_business_days(date("2026-10-16"), i64::MAX).is_err());
+    }
+}
@@ -0,0 +1,457 @@
+//! Probability distributions: densities, cumulative probabilities and their inverses.
+//!
+//! Parameters are checked here. An inverse returns the smallest value whose
+//! cumulative probability reaches `p`, which is infinite at the ends of an
+//! unbounded support.
+
+use crate::special;
+use std::f64::consts::{PI, SQRT_2};
+
+fn check_probability(p: f64) -> Result<(), String> {
+    if (0.0..=1.0).contains(&p) {
+        Ok(())
+    } else {
+        Err(format!("Expected a probability from 0 to 1, got {}", p))
+    }
+}
+
+fn check_positive(value: f64, what: &str) -> Result<(), String> {
+    if value > 0.0 && value.is_finite() {
+        Ok(())
+    } else {
+        Err(format!("The {} must be positive, got {}", what, value))
+    }
+}
+
+fn check_count(k: f64, what: &str) -> Result<(), String> {
+    if k.fract() == 0.0 && k >= 0.0 && k.is_finite() {
+        Ok(())
+    } else {
+        Err(format!("The {} must be a whole number, got {}", what, k))
+    }
+}
+
+/// Inverts a continuous `cdf` by Newton steps on its density, falling back to
+/// bisection (or to widening the bracket) when a step would leave the bracket.
+fn invert(
+    p: f64,
+    guess: f64,
+    support: (f64, f64),
+    cdf: impl Fn(f64) -> Result<f64, String>,
+    pdf: impl Fn(f64) -> f64,
+) -> Result<f64, String> {
+    let (mut lo, mut hi) = support;
+    let mut x = guess;
+    for _ in 0..500 {
+        let error = cdf(x)? - p;
+        if error == 0.0 {
+            return Ok(x);
+        }
+        if error < 0.0 {
+            lo = x;
+        } else {
+            hi = x;
+        }
+        let mut next = x - error / pdf(x);
+        if !(next > lo && next < hi) {
+            next = match (lo.is_finite(), hi.is_finite()) {
+                (true, true) => lo + (hi - lo) / 2.0,
+                (true, false) => x + 2.0 * (x.abs() + 1.0),
+                _ => x - 2.0 * (x.abs() + 1.0),
+            };
+        }
+        if (next - x).abs() <= 1e-15 * next.abs() || next == lo || next == hi {
+            return Ok(next);
+        }
+        x = next;
+    }
+    Err(format!("The inverse did not converge for p = {}", p))
+}
+
+/// Smallest whole `k ≥ 0` with `cdf(k) ≥ p`, searching from `guess`.
+fn invert_discrete(
+    p: f64,
+    guess: f64,
+    cdf: impl Fn(f64) -> Result<f64, String>,
+) -> Result<f64, String> {
+    let mut k = guess.round().max(0.0);
+    while k > 0.0 && cdf(k - 1.0)? >= p {
+        k -= 1.0;
+    }
+    while cdf(k)? < p {
+        k += 1.0;
+    }
+    Ok(k)
+}
+
+pub fn normal_pdf(x: f64, mu: f64, sigma: f64) -> Result<f64, String> {
+    check_positive(sigma, "standard deviation")?;
+    let z = (x - mu) / sigma;
+    Ok((-0.5 * z * z).exp() / (sigma * (2.0 * PI).sqrt()))
+}
+
+pub fn normal_cdf(x: f64, mu: f64, sigma: f64) -> Result<f64, String> {
+    check_positive(sigma, "standard deviation")?;
+    Ok(0.5 * special::erfc(-(x - mu) / (sigma * SQRT_2)))
+}
+
+/// Standard normal quantile: the Abramowitz–Stegun 26.2.23 approximation,
+/// refined by Halley steps on the lower tail.
+fn standard_normal_inv(p: f64) -> f64 {
+    if p == 0.0 {
+        return f64::NEG_INFINITY;
+    }
+    if p > 0.5 {
+        return -standard_normal_inv(1.0 - p);
+    }
+    let t = (-2.0 * p.ln()).sqrt();
+    let mut x = -(t
+        - (2.515517 + 0.802853 * t + 0.010328 * t * t)
+            / (1.0 + 1.432788 * t + 0.189269 * t * t + 0.001308 * t * t * t));
+    for _ in 0..4 {
+        let u = (0.5 * special::erfc(-x / SQRT_2) - p) * (2.0 * PI).sqrt() * (0.5 * x * x).exp();
+        if !u.is_finite() {
+            break;
+        }
+        x -= u / (1.0 + 0.5 * x * u);
+    }
+    x
+}
+
+pub fn normal_inv(p: f64, mu: f64, sigma: f64) -> Result<f64, String> {
+    check_probability(p)?;
+    check_positive(sigma, "standard deviation")?;
+    Ok(mu + sigma * standard_normal_inv(p))
+}
+
+/// ln Γ(z + 1/2) - ln Γ(z). For large z the two logarithms would cancel most
+/// of their digits, so the asymptotic series is used there instead.
+fn lgamma_half_step(z: f64) -> Result<f64, String> {
+    if z < 20.0 {
+        return Ok(special::lgamma(z + 0.5)? - special::lgamma(z)?);
+    }
+    let w = 1.0 / (z * z);
+    let series = 1.0 / 8.0 - w * (1.0 / 192.0 - w * (1.0 / 640.0 - w * 17.0 / 14336.0));
+    Ok(0.5 * z.ln() - series / z)
+}
+
+pub fn t_pdf(x: f64, df: f64) -> Result<f64, String> {
+    check_positive(df, "degrees of freedom")?;
+    let log_norm = lgamma_half_step(df / 2.0)? - 0.5 * (df * PI).ln();
+    Ok((log_norm - (df + 1.0) / 2.0 * (x * x / df).ln_1p()).exp())
+}
+
+pub fn t_cdf(x: f64, df: f64) -> Result<f64, String> {
+    check_positive(df, "degrees of freedom")?;
+    if x.is_infinite() {
+        return Ok(if x > 0.0 { 1.0 } else { 0.0 });
+    }
+    // P(T > |x|) = I_{df/(df + x²)}(df/2, 1/2) / 2
+    let tail = 0.5 * special::incomplete_beta(df / 2.0, 0.5, df / (df + x * x), false)?;
+    Ok(if x > 0.0 { 1.0 - tail } else { tail })
+}
+
+pub fn t_inv(p: f64, df: f64) -> Result<f64, String> {
+    check_probability(p)?;
+    check_positive(df, "degrees of freedom")?;
+    match p {
+        0.0 => Ok(f64::NEG_INFINITY),
+        1.0 => Ok(f64::INFINITY),
+        0.5 => Ok(0.0),
+        _ => invert(
+            p,
+            standard_normal_inv(p),
+            (f64::NEG_INFINITY, f64::INFINITY),
+            |x| t_cdf(x, df),
+            |x| t_pdf(x, df).unwrap_or(0.0),
+        ),
+    }
+}
+
+pub fn chi2_pdf(x: f64, df: f64) -> Result<f64, String> {
+    check_positive(df, "degrees of freedom")?;
+    let k = df / 2.0;
+    Ok(match x {
+        x if x < 0.0 => 0.0,
+        0.0 if df < 2.0 => f64::INFINITY,
+        0.0 if df > 2.0 => 0.0,
+        0.0 => 0.5,
+        x => ((k - 1.0) * x.ln() - x / 2.0 - k * 2f64.ln() - special::lgamma(k)?).exp(),
+    })
+}
+
+pub fn chi2_cdf(x: f64, df: f64) -> Result<f64, String> {
+    check_positive(df, "degrees of freedom")?;
+    if x <= 0.0 {
+        return Ok(0.0);
+    }
+    special::incomplete_gamma(df / 2.0, x / 2.0, false)
+}
+
+pub fn chi2_inv(p: f64, df: f64) -> Result<f64, String> {
+    check_probability(p)?;
+    check_positive(df, "degrees of freedom")?;
+    match p {
+        0.0 => Ok(0.0),
+        1.0 => Ok(f64::INFINITY),
+        _ => {
+            // Wilson–Hilferty: (X/df)^(1/3) is close to normal
+            let v = 2.0 / (9.0 * df);
+            let guess = df * (1.0 - v + standard_normal_inv(p) * v.sqrt()).powi(3);
+            invert(
+                p,
+                guess.max(f64::MIN_POSITIVE),
+                (0.0, f64::INFINITY),
+                |x| chi2_cdf(x, df),
+                |x| chi2_pdf(x, df).unwrap_or(0.0),
+            )
+        }
+    }
+}
+
+fn check_binomial(n: f64, p: f64) -> Result<(), String> {
+    check_count(n, "number of trials")?;
+    check_probability(p)
+}
+
+pub fn binomial_pmf(k: f64, n: f64, p: f64) -> Result<f64, String> {
+    check_binomial(n, p)?;
+    check_count(k, "number of successes")?;
+    if k > n {
+        return Ok(0.0);
+    }
+    // Written out so 0⁰ = 1 when p is 0 or 1
+    let successes = if k == 0.0 { 0.0 } else { k * p.ln() };
+    let failures = if k == n { 0.0 } else { (n - k) * (-p).ln_1p() };
+    let log_comb =
+        special::lgamma(n + 1.0)? - special::lgamma(k + 1.0)? - special::lgamma(n - k + 1.0)?;
+    Ok((log_comb + successes + failures).exp())
+}
+
+pub fn binomial_cdf(k: f64, n: f64, p: f64) -> Result<f64, String> {
+    check_binomial(n, p)?;
+    check_count(k, "number of successes")?;
+    match p {
+        _ if k >= n => Ok(1.0),
+        0.0 => Ok(1.0),
+        1.0 => Ok(0.0),
+        // P(X ≤ k) = I_{1-p}(n - k, k + 1) = 1 - I_p(k + 1, n - k)
+        _ => special::incomplete_beta(k + 1.0, n - k, p, true),
+    }
+}
+
+pub fn binomial_inv(q: f64, n: f64, p: f64) -> Result<f64, String> {
+    check_probability(q)?;
+    check_binomial(n, p)?;
+    if q == 1.0 {
+        return Ok(if p == 0.0 { 0.0 } else { n });
+    }
+    let guess = n * p + (n * p * (1.0 - p)).sqrt() * standard_normal_inv(q);
+    invert_discrete(q, guess.min(n), |k| binomial_cdf(k, n, p))
+}
+
+pub fn poisson_pmf(k: f64, lambda: f64) -> Result<f64, String> {
+    check_positive(lambda, "mean")?;
+    check_count(k, "count")?;
+    Ok((k * lambda.ln() - lambda - special::lgamma(k + 1.0)?).exp())
+}
+
+pub fn poisson_cdf(k: f64, lambda: f64) -> Result<f64, String> {
+    check_positive(lambda, "mean")?;
+    check_count(k, "count")?;
+    // P(X ≤ k) = Q(k + 1, λ)
+    special::incomplete_gamma(k + 1.0, lambda, true)
+}
+
+pub fn poisson_inv(p: f64, lambda: f64) -> Result<f64, String> {
+    check_probability(p)?;
+    check_positive(lambda, "mean")?;
+    if p == 1.0 {
+        return Ok(f64::INFINITY);
+    }
+    let guess = lambda + lambda.sqrt() * standard_normal_inv(p);
+    invert_discrete(p, guess, |k| poisson_cdf(k, lambda))
+}
+
+pub fn exponential_pdf(x: f64, rate: f64) -> Result<f64, String> {
+    check_positive(rate, "rate")?;
+    Ok(if x < 0.0 {
+        0.0
+    } else {
+        rate * (-rate * x).exp()
+    })
+}
+
+pub fn exponential_cdf(x: f64, rate: f64) -> Result<f64, String> {
+    check_positive(rate, "rate")?;
+    Ok(if x < 0.0 { 0.0 } else { -(-rate * x).exp_m1() })
+}
+
+pub fn exponential_inv(p: f64, rate: f64) -> Result<f64, String> {
+    check_probability(p)?;
+    check_positive(rate, "rate")?;
+    Ok(-(-p).ln_1p() / rate)
+}
+
+fn check_bounds(a: f64, b: f64) -> Result<(), String> {
+    if a < b {
+        Ok(())
+    } else {
+        Err(format!(
+            "The lower bound must be below the upper bound, got {} and {}",
+            a, b
+        ))
+    }
+}
+
+pub fn uniform_pdf(x: f64, a: f64, b: f64) -> Result<f64, String> {
+    check_bounds(a, b)?;
+    Ok(if (a..=b).contains(&x) {
+        1.0 / (b - a)
+    } else {
+        0.0
+    })
+}
+
+pub fn uniform_cdf(x: f64, a: f64, b: f64) -> Result<f64, String> {
+    check_bounds(a, b)?;
+    Ok(((x - a) / (b - a)).clamp(0.0, 1.0))
+}
+
+pub fn uniform_inv(p: f64, a: f64, b: f64) -> Result<f64, String> {
+    check_probability(p)?;
+    check_bounds(a, b)?;
+    Ok(a + p * (b - a))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reference values below are from mpmath at 30 digits.
+    fn assert_rel(actual: f64, expected: f64, tolerance: f64) {
+        let error = if expected == 0.0 {
+            actual.abs()
+        } else {
+            ((actual - expected) / expected).abs()
+        };
+        assert!(
+            error <= tolerance,
+            "{} != {} (relative error {:e})",
+            actual,
+            expected,
+            error
+        );
+    }
+
+    #[test]
+    fn test_normal() {
+        assert_rel(
+            normal_pdf(1.5, 1.0, 2.0).unwrap(),
+            0.1933340584014246,
+            1e-15,
+        );
+        assert_rel(
+            normal_cdf(-1.96, 0.0, 1.0).unwrap(),
+            0.024997895148220436,
+            1e-15,
+        );
+        assert_rel(
+            normal_cdf(-10.0, 0.0, 1.0).unwrap(),
+            7.619853024160526e-24,
+            1e-14,
+        );
+        assert_rel(
+            normal_cdf(3.0, 1.0, 2.0).unwrap(),
+            0.8413447460685429,
+            1e-15,
+        );
+        assert_rel(
+            normal_inv(0.975, 0.0, 1.0).unwrap(),
+            1.959963984540054,
+            1e-15,
+        );
+        assert_rel(
+            normal_inv(0.3, 0.0, 1.0).unwrap(),
+            -0.5244005127080408,
+            1e-15,
+        );
+        assert_rel(
+            normal_inv(1e-10, 0.0, 1.0).unwrap(),
+            -6.361340902404056,
+            1e-15,
+        );
+        assert_eq!(normal_inv(0.5, 3.0, 2.0).unwrap(), 3.0);
+        assert_eq!(normal_inv(0.0, 0.0, 1.0).unwrap(), f64::NEG_INFINITY);
+        assert!(normal_cdf(0.0, 0.0, -1.0).is_err());
+        assert!(normal_inv(1.5, 0.0, 1.0).is_err());
+    }
+
+    #[test]
+    fn test_student_t_and_chi_square() {
+        assert_rel(t_pdf(1.0, 5.0).unwrap(), 0.21967979735098057, 1e-14);
+        assert_rel(t_pdf(0.5, 3.0).unwrap(), 0.3131809110088286, 1e-14);
+        // Many degrees of freedom approach the normal density without losing digits
+        assert_rel(t_pdf(1.5, 50.0).unwrap(), 0.12920321834422048, 1e-14);
+        assert_rel(t_pdf(2.0, 1e6).unwrap(), 0.053991060997102184, 1e-14);
+        assert_rel(t_pdf(0.0, 1e10).unwrap(), 0.3989422803914591, 1e-14);
+        assert_rel(t_cdf(2.5, 10.0).unwrap(), 0.9842765778816956, 1e-15);
+        assert_rel(t_cdf(-3.0, 2.5).unwrap(), 0.03628804777451592, 1e-14);
+        assert_eq!(t_cdf(0.0, 4.0).unwrap(), 0.5);
+        assert_rel(t_inv(0.975, 10.0).unwrap(), 2.228138851986274, 1e-14);
+        assert_rel(t_inv(0.9, 30.0).unwrap(), 1.3104150253913957, 1e-14);
+        // Cauchy, whose quantile is tan(π(p - 1/2))
+        assert_rel(t_inv(1e-6, 1.0).unwrap(), -318309.8861827435, 1e-12);
+
+        assert_rel(chi2_pdf(3.0, 4.0).unwrap(), 0.16734762011132237, 1e-15);
+        assert_rel(chi2_cdf(3.84, 1.0).unwrap(), 0.9499564787512949, 1e-15);
+        assert_rel(chi2_cdf(20.0, 10.0).unwrap(), 0.970747311923039, 1e-15);
+        assert_rel(chi2_inv(0.95, 1.0).unwrap(), 3.8414588206941245, 1e-14);
+        assert_rel(chi2_inv(0.05, 3.0).unwrap(), 0.3518463177492714, 1e-14);
+        assert_rel(chi2_inv(0.99, 100.0).unwrap(), 135.80672317102677, 1e-14);
+        assert!(t_cdf(1.0, 0.0).is_err());
+    }
+
+    #[test]
+    fn test_discrete() {
+        assert_rel(binomial_pmf(3.0, 10.0, 0.3).unwrap(), 0.266827932, 1e-14);
+        assert_rel(binomial_cdf(3.0, 10.0, 0.3).unwrap(), 0.6496107184, 1e-15);
+        assert_rel(
+            binomial_cdf(450.0, 1000.0, 0.5).unwrap(),
+            0.0008652680424881588,
+            1e-12,
+        );
+        assert_eq!(binomial_pmf(0.0, 5.0, 0.0).unwrap(), 1.0);
+        assert_eq!(binomial_pmf(6.0, 5.0, 0.5).unwrap(), 0.0);
+        assert_eq!(binomial_inv(0.6496107184, 10.0, 0.3).unwrap(), 3.0);
+        assert_eq!(binomial_inv(0.65, 10.0, 0.3).unwrap(), 4.0);
+        assert_eq!(binomial_inv(1.0, 10.0, 0.3).unwrap(), 10.0);
+        assert!(binomial_pmf(1.5, 10.0, 0.3).is_err());
+
+        assert_rel(poisson_pmf(2.0, 3.0).unwrap(), 0.22404180765538775, 1e-15);
+        assert_rel(poisson_cdf(10.0, 4.5).unwrap(), 0.993331327912818, 1e-15);
+        assert_rel(
+            poisson_cdf(5.0, 100.0).unwrap(),
+            3.26145636672047e-36,
+            1e-12,
+        );
+        assert_eq!(poisson_inv(0.99, 4.5).unwrap(), 10.0);
+        assert_eq!(poisson_inv(0.0, 4.5).unwrap(), 0.0);
+    }
+
+    #[test]
+    fn test_exponential_and_uniform() {
+        assert_rel(
+            exponential_pdf(2.0, 0.5).unwrap(),
+            0.5 * (-1f64).exp(),
+            1e-15,
+        );
+        assert_rel(exponential_cdf(1e-20, 1.0).unwrap(), 1e-20, 1e-15);
+        assert_rel(exponential_inv(0.5, 2.0).unwrap(), 2f64.ln() / 2.0, 1e-15);
+        assert_eq!(exponential_cdf(-1.0, 1.0).unwrap(), 0.0);
+        assert_eq!(uniform_pdf(3.0, 2.0, 6.0).unwrap(), 0.25);
+        assert_eq!(uniform_cdf(7.0, 2.0, 6.0).unwrap(), 1.0);
+        assert_eq!(uniform_inv(0.25, 2.0, 6.0).unwrap(), 3.0);
+        assert!(uniform_pdf(0.0, 1.0, 1.0).is_err());
+    }
+}
@@ -1,23 +1,138 @@
-use crate::parser::Token;
+use crate::builtins::{self, Handler};
+use crate::constants::{self, Constant};
+use crate::datetime::{self, Span};
+use crate::interval::{self, Interval};
+use crate::matrix::Matrix;
+use crate::parser::{Lexer, Token};
+use crate::settings::{AngleMode, Settings};
+use crate::special;
+use crate::uncertainty::Uncertain;
+use crate::units::{Quantity, Unit};
+use crate::value::Value;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
 
-pub struct Evaluator;
+/// Maximum nesting of custom function calls, guarding against runaway recursion.
+pub const MAX_CALL_DEPTH: usize = 20;
+
+pub struct Evaluator {
+    settings: Settings,
+    variables: HashMap<String, Value>,
+    depth: usize,
+    /// Whether everything evaluated so far depends only on its inputs, so results
+    /// can be cached; cleared once an impure built-in such as `rand()` runs, or a
+    /// `±` creates a new source of uncertainty.
+    pure: bool,
+    memo: Memo,
+}
+
+/// What one evaluation remembers about custom functions.
+#[derive(Default)]
+struct Memo {
+    /// Results of pure calls.
+    calls: HashMap<CallKey, Value>,
+    /// Bodies, tokenized and with their constant calls folded.
+    bodies: HashMap<String, Vec<Token>>,
+}
+
+/// A custom function call. The hash covers the name and the plain numbers among
+/// the arguments; equality compares the arguments in full.
+#[derive(PartialEq)]
+struct CallKey {
+    name: String,
+    args: Vec<Value>,
+}
+
+// NaN arguments never compare equal, which only costs a cache miss
+impl Eq for CallKey {}
+
+impl Hash for CallKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.name.hash(state);
+        for arg in &self.args {
+            std::mem::discriminant(arg).hash(state);
+            if let Value::Number(n) = arg {
+                // -0.0 == 0.0, so both hash as 0.0
+                (n + 0.0).to_bits().hash(state);
+            }
+        }
+    }
+}
 
 impl Evaluator {
     pub fn new() -> Self {
-        Evaluator
+        Evaluator {
+            settings: crate::settings::current(),
+            variables: HashMap::new(),
+            depth: 0,
+            pure: true,
+            memo: Memo::default(),
+        }
     }
 
-    pub fn evaluate(&mut self, tokens: &[Token]) -> Result<f64, String> {
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    pub fn evaluate(&mut self, tokens: &[Token]) -> Result<Value, String> {
         if tokens.is_empty() {
             return Err("Empty expression".to_string());
         }
+        if let [rest @ .., Token::Convert(unit)] = tokens {
+            let value = self.evaluate(rest)?;
+            return Self::convert_units(value, unit);
+        }
+        if let [rest @ .., Token::AsPercent] = tokens {
+            return match self.evaluate(rest)? {
+                Value::Number(n) => Ok(Value::Percent(n * 100.0)),
+                percent @ Value::Percent(_) => Ok(percent),
+                other => Err(format!("Cannot show {} as a percentage", other)),
+            };
+        }
 
-        let mut values: Vec<f64> = Vec::new();
+        let mut values: Vec<Value> = Vec::new();
         let mut ops: Vec<Token> = Vec::new();
 
         for token in tokens {
             match token {
-                Token::Number(n) => values.push(*n),
+                Token::Number(n) => values.push(self.literal(*n, false)),
+                Token::Rounded(n) => values.push(self.literal(*n, true)),
+                Token::Angle(n, unit) => {
+                    let rounded = !interval::is_exact_decimal(*n);
+                    let value =
+                        self.convert_angle(self.literal(*n, rounded), *unit, self.settings.angle)?;
+                    values.push(value);
+                }
+                Token::Text(text) => values.push(Value::Text(text.clone())),
+                Token::Date(date) => values.push(Value::Date(*date)),
+                Token::Duration(span) => values.push(Value::Duration(*span)),
+                Token::Quantity(n, unit) => {
+                    values.push(Value::Quantity(Quantity::new(*n, unit.clone())))
+                }
+                Token::Bracket(items) => {
+                    let value = self.evaluate_bracket(items)?;
+                    values.push(value);
+                }
+                Token::Variable(name) => {
+                    // Variables hide constants, which hide units
+                    let value = match self.variables.get(name) {
+                        Some(value) => value.clone(),
+                        None => match (self.special_value(name), constants::lookup_bare(name)) {
+                            (Some(value), _) => value?,
+                            (None, Some(constant)) => self.constant(constant),
+                            // A bare unit such as the `s` in `5 N * s`
+                            (None, None) => Unit::lookup(name)
+                                .map(|unit| Value::Quantity(Quantity::new(1.0, unit)))
+                                .ok_or_else(|| format!("Unexpected identifier: {}", name))?,
+                        },
+                    };
+                    values.push(value);
+                }
+                Token::Constant(name) => {
+                    let constant = constants::lookup(name)
+                        .ok_or_else(|| format!("Unknown constant: {}", name))?;
+                    values.push(self.constant(constant));
+                }
                 Token::FunctionCall(name, args) => {
                     let result = self.evaluate_function_call(name, args)?;
                     values.push(result);
@@ -40,6 +155,15 @@ impl Evaluator {
                 Token::UnaryMinus => {
                     ops.push(token.clone());
                 }
+                Token::Factorial => match values.pop() {
+                    Some(value) => values.push(self.factorial(&value)?),
+                    None => return Err("Missing operand for !".to_string()),
+                },
+                Token::Percent => match values.pop() {
+                    Some(Value::Number(n)) => values.push(Value::Percent(n)),
+                    Some(_) => return Err("% expects a plain number".to_string()),
+                    None => return Err("Missing operand for %".to_string()),
+                },
                 Token::Add | Token::Subtract => {
                     while let Some(op) = ops.last() {
                         if matches!(
@@ -48,7 +172,9 @@ impl Evaluator {
                                 | Token::Multiply
                                 | Token::Divide
                                 | Token::Modulo
+                                | Token::Of
                                 | Token::Power
+                                | Token::PlusMinus
                                 | Token::Add
                                 | Token::Subtract
                         ) {
@@ -59,11 +185,16 @@ impl Evaluator {
                     }
                     ops.push(token.clone());
                 }
-                Token::Multiply | Token::Divide | Token::Modulo => {
+                Token::Multiply | Token::Divide | Token::Modulo | Token::Of => {
                     while let Some(op) = ops.last() {
                         if matches!(
                             op,
-                            Token::Multiply | Token::Divide | Token::Modulo | Token::Power
+                            Token::Multiply
+                                | Token::Divide
+                                | Token::Modulo
+                                | Token::Of
+                                | Token::Power
+                                | Token::PlusMinus
                         ) {
                             self.perform_operation(&mut values, &mut ops)?;
                         } else {
@@ -72,9 +203,26 @@ impl Evaluator {
                     }
                     ops.push(token.clone());
                 }
+                Token::PlusMinus => {
+                    // Each ± is a new source, so evaluating it twice gives unrelated values
+                    if !self.settings.interval {
+                        self.pure = false;
+                    }
+                    while let Some(op) = ops.last() {
+                        if matches!(op, Token::UnaryMinus | Token::Power | Token::PlusMinus) {
+                            self.perform_operation(&mut values, &mut ops)?;
+                        } else {
+                            break;
+                        }
+                    }
+                    ops.push(token.clone());
+                }
                 Token::Power => {
                     ops.push(token.clone());
                 }
+                Token::Equals => {
+                    return Err("= only appears in equations, as in solve(x^2 = 2, x)".to_string());
+                }
                 #[allow(unreachable_patterns)]
                 _ => return Err(format!("Unexpected token: {:?}", token)),
             }
@@ -86,7 +234,7 @@ impl Evaluator {
                         return Err("Missing operand for unary minus".to_string());
                     }
                     let value = values.pop().unwrap();
-                    values.push(-value);
+                    values.push(Self::negate(value)?);
                 }
                 _ => {
                     if values.len() < 2 {
@@ -101,180 +249,586 @@ impl Evaluator {
         }
 
         match values.len() {
-            1 => Ok(values[0]),
+            1 => Ok(values.pop().unwrap()),
             0 => Err("No result produced".to_string()),
             _ => Err(format!("Too many values in the stack: {:?}", values)),
         }
     }
 
-    fn zero_if_tiny(val: f64) -> f64 {
-        if val.abs() < 1e-8 { 0.0 } else { val }
+    /// Applies a built-in of one variable, propagating uncertainty through its derivative
+    /// and intervals through `bounds`.
+    pub fn apply_unary(
+        &self,
+        arg: &Value,
+        f: impl Fn(f64) -> f64,
+        df: impl Fn(f64) -> f64,
+        bounds: impl Fn(&Interval) -> Result<Interval, String>,
+    ) -> Result<Value, String> {
+        if self.settings.interval {
+            return Ok(Value::Interval(bounds(&arg.to_interval()?)?).normalize());
+        }
+        match arg {
+            Value::Number(n) => Ok(Value::Number(f(*n))),
+            Value::Percent(p) => Ok(Value::Number(f(p / 100.0))),
+            Value::Uncertain(u) => {
+                Ok(Value::Uncertain(u.apply(f(u.value), df(u.value))).normalize())
+            }
+            Value::Interval(i) => Ok(Value::Interval(bounds(i)?).normalize()),
+            Value::Text(_) => Err("Expected a number, got text".to_string()),
+            Value::Matrix(_) => Err("Expected a number, got a matrix".to_string()),
+            Value::Date(_) | Value::Duration(_) => {
+                Err("Expected a number, got a date or duration".to_string())
+            }
+            Value::Quantity(q) => Err(format!(
+                "Expected a plain number, got a quantity in {}",
+                q.unit
+            )),
+        }
     }
 
-    fn evaluate_function_call(&mut self, name: &str, args: &[Token]) -> Result<f64, String> {
-        match name.to_lowercase().as_str() {
-            "sin" => {
-                if args.len() != 1 {
-                    return Err("sin() expects 1 argument".to_string());
-                }
-                return match args[0] {
-                    Token::Number(n) => Ok(Self::zero_if_tiny(n.sin())),
-                    _ => Err("sin() expects a number".to_string()),
+    /// Whether `name` is a variable here, such as a parameter of a custom function.
+    pub fn binds(&self, name: &str) -> bool {
+        self.variables.contains_key(name)
+    }
+
+    /// Evaluates `tokens` with `name` bound to `value`, as when a formula in one
+    /// variable is evaluated at a point.
+    pub fn evaluate_at(
+        &mut self,
+        tokens: &[Token],
+        name: &str,
+        value: Value,
+    ) -> Result<Value, String> {
+        let hidden = self.variables.insert(name.to_string(), value);
+        let result = self.evaluate(tokens);
+        match hidden {
+            Some(value) => self.variables.insert(name.to_string(), value),
+            None => self.variables.remove(name),
+        };
+        result
+    }
+
+    /// Converts an interval angle between the session's angle mode and radians.
+    pub fn interval_angle(
+        &self,
+        i: &Interval,
+        from: AngleMode,
+        to: AngleMode,
+    ) -> Result<Interval, String> {
+        self.convert_angle(Value::Interval(*i), from, to)?
+            .to_interval()
+    }
+
+    fn negate(value: Value) -> Result<Value, String> {
+        match value {
+            Value::Number(n) => Ok(Value::Number(-n)),
+            Value::Uncertain(u) => Ok(Value::Uncertain(u.neg())),
+            Value::Interval(i) => Ok(Value::Interval(i.neg())),
+            Value::Text(_) => Err("Cannot negate text".to_string()),
+            Value::Matrix(m) => Ok(Value::Matrix(m.map(|x| -x))),
+            Value::Duration(span) => Ok(Value::Duration(span.neg())),
+            Value::Date(_) => Err("Cannot negate a date".to_string()),
+            Value::Quantity(q) => Ok(Value::Quantity(q.scale(-1.0))),
+            Value::Percent(p) => Ok(Value::Percent(-p)),
+        }
+    }
+
+    /// Applies a trailing `to <unit>` conversion.
+    fn convert_units(value: Value, unit: &Unit) -> Result<Value, String> {
+        let quantity = match value {
+            Value::Quantity(q) => q,
+            Value::Duration(span) if span.months == 0 => Quantity::seconds(span.total_seconds()),
+            Value::Number(n) if unit.is_dimensionless() => Quantity::new(n, Unit::none()),
+            other => return Err(format!("Cannot convert {} to {}", other, unit)),
+        };
+        quantity.convert(unit).map(Value::Quantity)
+    }
+
+    fn constant(&self, constant: &Constant) -> Value {
+        match constant.to_value() {
+            // Table values are written in their shortest decimal form
+            Value::Number(n) => self.literal(n, !interval::is_exact_decimal(n)),
+            quantity => quantity,
+        }
+    }
+
+    /// `inf` and `nan`, which are only values in IEEE mode.
+    fn special_value(&self, name: &str) -> Option<Result<Value, String>> {
+        let value = match name.to_lowercase().as_str() {
+            "inf" | "infinity" => f64::INFINITY,
+            "nan" => f64::NAN,
+            _ => return None,
+        };
+        Some(if self.settings.ieee {
+            Ok(Value::Number(value))
+        } else {
+            Err(format!(
+                "{} is only a value in IEEE mode (:set ieee on)",
+                name
+            ))
+        })
+    }
+
+    fn literal(&self, n: f64, rounded: bool) -> Value {
+        if self.settings.interval {
+            Value::Interval(Interval::from_literal(n, rounded)).normalize()
+        } else {
+            Value::Number(n)
+        }
+    }
+
+    /// Size of a full turn in `unit`, enclosing 2π rigorously in interval mode.
+    fn turn(&self, unit: AngleMode) -> Value {
+        match unit {
+            AngleMode::Radians if self.settings.interval => {
+                let pi = Interval {
+                    lo: std::f64::consts::PI,
+                    hi: std::f64::consts::PI.next_up(),
                 };
+                Value::Interval(pi.add(&pi))
             }
-            "cos" => {
-                if args.len() != 1 {
-                    return Err("cos() expects 1 argument".to_string());
-                }
-                return match args[0] {
-                    Token::Number(n) => Ok(Self::zero_if_tiny(n.cos())),
-                    _ => Err("cos() expects a number".to_string()),
-                };
+            _ => Value::Number(unit.per_turn()),
+        }
+    }
+
+    pub fn convert_angle(
+        &self,
+        value: Value,
+        from: AngleMode,
+        to: AngleMode,
+    ) -> Result<Value, String> {
+        if from == to {
+            return Ok(value);
+        }
+        let scaled = self.apply_operator(&Token::Multiply, value, self.turn(to))?;
+        self.apply_operator(&Token::Divide, scaled, self.turn(from))
+    }
+
+    /// `[a, b]` is an interval literal in interval mode. Otherwise brackets build
+    /// matrices: `[1, 2, 3]` is a column vector and `[[1, 2], [3, 4]]` lists rows.
+    fn evaluate_bracket(&mut self, items: &[Vec<Token>]) -> Result<Value, String> {
+        if self.settings.interval {
+            if items.len() != 2 {
+                return Err("An interval literal needs exactly two bounds: [lo, hi]".to_string());
             }
-            "tan" => {
-                if args.len() != 1 {
-                    return Err("tan() expects 1 argument".to_string());
+            let lo = self.evaluate(&items[0])?.to_interval()?;
+            let hi = self.evaluate(&items[1])?.to_interval()?;
+            return Ok(Value::Interval(Interval::new(lo.lo, hi.hi)?).normalize());
+        }
+        if items.is_empty() {
+            return Err("A matrix needs at least one element".to_string());
+        }
+        let items = items
+            .iter()
+            .map(|item| self.evaluate(item))
+            .collect::<Result<Vec<Value>, String>>()?;
+        if items.iter().all(|item| matches!(item, Value::Matrix(_))) {
+            let rows = items
+                .iter()
+                .map(|item| match item {
+                    Value::Matrix(row) if row.cols == 1 => Ok(row.as_vector()?.to_vec()),
+                    _ => Err("Matrix rows must be flat lists of numbers".to_string()),
+                })
+                .collect::<Result<Vec<Vec<f64>>, String>>()?;
+            return Ok(Value::Matrix(Matrix::from_rows(rows)?));
+        }
+        let values = items
+            .iter()
+            .map(|item| match item {
+                Value::Number(n) => Ok(*n),
+                _ => Err("Matrix elements must be plain numbers".to_string()),
+            })
+            .collect::<Result<Vec<f64>, String>>()?;
+        Ok(Value::Matrix(Matrix::column(values)))
+    }
+
+    fn evaluate_function_call(&mut self, name: &str, args: &[Vec<Token>]) -> Result<Value, String> {
+        let builtin = builtins::lookup(name);
+        if let Some(builtin) = builtin
+            && let Handler::Tokens(call) = builtin.call
+        {
+            builtin.arity.check(name, args.len())?;
+            self.pure &= builtin.pure;
+            return call(self, args);
+        }
+
+        // Functions see a percentage as its fraction: sin(50%) is sin(0.5)
+        let args = args
+            .iter()
+            .map(|arg| match self.evaluate(arg)? {
+                Value::Percent(p) => Ok(Value::Number(p / 100.0)),
+                other => Ok(other),
+            })
+            .collect::<Result<Vec<Value>, String>>()?;
+
+        if let Some(builtin) = builtin
+            && let Handler::Values(call) = builtin.call
+        {
+            builtin.arity.check(name, args.len())?;
+            self.pure &= builtin.pure;
+            return call(self, &args);
+        }
+
+        self.call_custom_function(name, args)
+    }
+
+    /// n! of a number, or of an uncertain number through d/dn n! = n!·ψ(n + 1).
+    pub fn factorial(&self, arg: &Value) -> Result<Value, String> {
+        match arg {
+            Value::Number(n) => self.whole_factorial(*n),
+            Value::Uncertain(u) => match self.whole_factorial(u.value)? {
+                Value::Number(f) => {
+                    let slope = f * special::digamma(u.value + 1.0)?;
+                    Ok(Value::Uncertain(u.apply(f, slope)).normalize())
                 }
-                return match args[0] {
-                    Token::Number(n) => Ok(Self::zero_if_tiny(n.tan())),
-                    _ => Err("tan() expects a number".to_string()),
-                };
+                other => Ok(other),
+            },
+            other => Err(format!("fact() expects a number, got {}", other)),
+        }
+    }
+
+    /// n!, exact while it fits in a `u128` and Γ(n + 1) beyond that or for non-integers.
+    fn whole_factorial(&self, n: f64) -> Result<Value, String> {
+        if n.fract() == 0.0 {
+            if n < 0.0 {
+                return Err("fact() is undefined for negative integers".to_string());
             }
-            "log" => {
-                if args.len() != 1 {
-                    return Err("log() expects 1 argument".to_string());
-                }
-                return match args[0] {
-                    Token::Number(n) => {
-                        if n <= 0.0 {
-                            Err("log() argument must be positive".to_string())
-                        } else {
-                            Ok(Self::zero_if_tiny(n.ln()))
-                        }
-                    }
-                    _ => Err("log() expects a number".to_string()),
-                };
+            let exact = (1..=n as u128).try_fold(1u128, |product, i| product.checked_mul(i));
+            if let Some(product) = exact {
+                return Ok(Value::Number(product as f64));
             }
-            "exp" => {
-                if args.len() != 1 {
-                    return Err("exp() expects 1 argument".to_string());
-                }
-                return match args[0] {
-                    Token::Number(n) => Ok(Self::zero_if_tiny(n.exp())),
-                    _ => Err("exp() expects a number".to_string()),
-                };
+        }
+        self.special_result("fact", special::gamma(n + 1.0)?)
+    }
+
+    /// Rejects an infinite or NaN result from a special function unless in IEEE mode.
+    pub fn special_result(&self, name: &str, value: f64) -> Result<Value, String> {
+        if !value.is_finite() && !self.settings.ieee {
+            return Err(format!("{}() result is too large to represent", name));
+        }
+        Ok(Value::Number(value))
+    }
+
+    /// Evaluates a user-defined function with its parameters bound to the argument values,
+    /// so an argument used several times in the body stays the same value. Calls whose
+    /// body is pure are cached, so `f(2) + f(2)` evaluates `f` once.
+    fn call_custom_function(&mut self, name: &str, args: Vec<Value>) -> Result<Value, String> {
+        let key = CallKey {
+            name: name.to_string(),
+            args,
+        };
+        if let Some(value) = self.memo.calls.get(&key) {
+            return Ok(value.clone());
+        }
+        let CallKey { name, args } = &key;
+        let func = crate::functions::get_custom_function(name)
+            .ok_or_else(|| format!("Function '{}' is not defined", name))?;
+        if args.len() != func.parameters.len() {
+            return Err("Custom function argument count mismatch".to_string());
+        }
+        if self.depth >= MAX_CALL_DEPTH {
+            return Err(format!(
+                "Too many nested calls of custom function '{}'",
+                name
+            ));
+        }
+
+        let tokens = match self.memo.bodies.get(name) {
+            Some(tokens) => tokens.clone(),
+            None => {
+                // A body is a formula in its parameters: 2n is 2 times n, not 2 nano
+                let tokens = Lexer::formula(&func.expression).tokenize()?;
+                let tokens = self.fold_constants(tokens);
+                self.memo.bodies.insert(name.clone(), tokens.clone());
+                tokens
             }
-            "arcsin" => {
-                if args.len() != 1 {
-                    return Err("asin() expects 1 argument".to_string());
+        };
+        let mut evaluator = Evaluator {
+            settings: self.settings.clone(),
+            variables: func.parameters.into_iter().zip(args.clone()).collect(),
+            depth: self.depth + 1,
+            pure: true,
+            memo: std::mem::take(&mut self.memo),
+        };
+        let result = evaluator.evaluate(&tokens);
+        self.memo = evaluator.memo;
+        self.pure &= evaluator.pure;
+        if evaluator.pure
+            && let Ok(value) = &result
+        {
+            self.memo.calls.insert(key, value.clone());
+        }
+        result
+    }
+
+    /// Replaces calls of pure built-ins on plain numbers by their values, so a body
+    /// such as `x * sqrt(2)` computes `sqrt(2)` once however often it is called.
+    /// Interval mode keeps the calls, since a folded value would lose its enclosure.
+    fn fold_constants(&mut self, tokens: Vec<Token>) -> Vec<Token> {
+        if self.settings.interval {
+            return tokens;
+        }
+        tokens
+            .into_iter()
+            .map(|token| match token {
+                Token::FunctionCall(name, args) => {
+                    let args: Vec<Vec<Token>> = args
+                        .into_iter()
+                        .map(|arg| self.fold_constants(arg))
+                        .collect();
+                    let constant = builtins::lookup(&name).is_some_and(|builtin| {
+                        builtin.pure && matches!(builtin.call, Handler::Values(_))
+                    }) && args.iter().all(|arg| {
+                        matches!(arg.as_slice(), [Token::Number(_) | Token::Rounded(_)])
+                    });
+                    // Errors are left for the call to raise when it runs
+                    if constant
+                        && let Ok(Value::Number(n)) = self.evaluate_function_call(&name, &args)
+                    {
+                        return Token::Number(n);
+                    }
+                    Token::FunctionCall(name, args)
                 }
-                return match args[0] {
-                    Token::Number(n) => Ok(Self::zero_if_tiny(n.asin())),
-                    _ => Err("asin() expects a number".to_string()),
-                };
-            }
-            "arccos" => {
-                if args.len() != 1 {
-                    return Err("acos() expects 1 argument".to_string());
+                Token::Bracket(items) => Token::Bracket(
+                    items
+                        .into_iter()
+                        .map(|item| self.fold_constants(item))
+                        .collect(),
+                ),
+                other => other,
+            })
+            .collect()
+    }
+
+    pub fn apply_operator(&self, op: &Token, a: Value, b: Value) -> Result<Value, String> {
+        if *op == Token::Of || matches!(a, Value::Percent(_)) || matches!(b, Value::Percent(_)) {
+            return self.apply_percent_operator(op, a, b);
+        }
+        if *op == Token::PlusMinus {
+            if self.settings.interval {
+                let (a, b) = (a.to_interval()?, b.to_interval()?);
+                if b.lo < 0.0 {
+                    return Err("Uncertainty must be non-negative".to_string());
                 }
-                return match args[0] {
-                    Token::Number(n) => Ok(Self::zero_if_tiny(n.acos())),
-                    _ => Err("acos() expects a number".to_string()),
-                };
+                return Ok(Value::Interval(Interval::new(a.sub(&b).lo, a.add(&b).hi)?).normalize());
             }
-            "arctan" => {
-                if args.len() != 1 {
-                    return Err("atan() expects 1 argument".to_string());
+            return match (a, b) {
+                (Value::Number(a), Value::Number(b)) => {
+                    if b < 0.0 {
+                        return Err("Uncertainty must be non-negative".to_string());
+                    }
+                    Ok(Value::Uncertain(Uncertain::new(a, b)).normalize())
                 }
-                return match args[0] {
-                    Token::Number(n) => Ok(Self::zero_if_tiny(n.atan())),
-                    _ => Err("atan() expects a number".to_string()),
-                };
+                _ => Err("± expects plain numbers on both sides".to_string()),
+            };
+        }
+        let (a, b) = Self::unify_time(a, b)?;
+        match (a, b) {
+            (a @ (Value::Date(_) | Value::Duration(_)), b)
+            | (a, b @ (Value::Date(_) | Value::Duration(_))) => {
+                Self::apply_date_operator(op, &a, &b)
             }
-            "fact" | "factorial" => {
-                if args.len() != 1 {
-                    return Err("fact() expects 1 argument".to_string());
-                }
-                return match args[0] {
-                    Token::Number(n) => {
-                        if n < 0.0 || n.fract() != 0.0 {
-                            Err("fact() expects a non-negative integer".to_string())
-                        } else {
-                            let mut res = 1u128;
-                            let mut i = 1u128;
-                            let n = n as u128;
-                            while i <= n {
-                                res = res.checked_mul(i).ok_or("fact() overflow")?;
-                                i += 1;
-                            }
-                            Ok(res as f64)
-                        }
-                    }
-                    _ => Err("fact() expects a number".to_string()),
+            (Value::Quantity(a), b) => self.apply_quantity_operator(op, &a, &b, false),
+            (a, Value::Quantity(b)) => self.apply_quantity_operator(op, &b, &a, true),
+            (Value::Matrix(a), b) => self.apply_matrix_operator(op, &a, &b, false),
+            (a, Value::Matrix(b)) => self.apply_matrix_operator(op, &b, &a, true),
+            (Value::Number(a), Value::Number(b))
+                if !self.settings.interval || *op == Token::Modulo =>
+            {
+                self.apply_number_operator(op, a, b).map(Value::Number)
+            }
+            (a, b)
+                if self.settings.interval
+                    || matches!((&a, &b), (Value::Interval(_), _) | (_, Value::Interval(_))) =>
+            {
+                self.apply_interval_operator(op, &a.to_interval()?, &b.to_interval()?)
+                    .map(|i| Value::Interval(i).normalize())
+            }
+            (a, b) => self
+                .apply_uncertain_operator(op, &a.to_uncertain()?, &b.to_uncertain()?)
+                .map(|u| Value::Uncertain(u).normalize()),
+        }
+    }
+
+    /// Desk-calculator percentages: `200 + 15%` adds 15% of 200, `15% of 80` takes
+    /// 15% of 80 and `50 of 200` is the ratio 0.25. Elsewhere a percentage is its
+    /// fraction, so `200 * 15%` is 30.
+    fn apply_percent_operator(&self, op: &Token, a: Value, b: Value) -> Result<Value, String> {
+        match (op, a, b) {
+            (Token::Of, Value::Percent(p), b) => {
+                self.apply_operator(&Token::Multiply, Value::Number(p / 100.0), b)
+            }
+            (Token::Of, Value::Number(part), Value::Number(whole)) => {
+                self.apply_operator(&Token::Divide, Value::Number(part), Value::Number(whole))
+            }
+            (Token::Of, _, _) => {
+                Err("of expects a percentage or a number on the left, e.g. 15% of 80".to_string())
+            }
+            (Token::Add | Token::Subtract, Value::Percent(p), Value::Percent(q)) => {
+                let sign = if *op == Token::Add { 1.0 } else { -1.0 };
+                Ok(Value::Percent(p + sign * q))
+            }
+            (Token::Add | Token::Subtract, a, Value::Percent(p)) => {
+                let share =
+                    self.apply_operator(&Token::Multiply, a.clone(), Value::Number(p / 100.0))?;
+                self.apply_operator(op, a, share)
+            }
+            (op, a, b) => {
+                let fraction = |value: Value| match value {
+                    Value::Percent(p) => Value::Number(p / 100.0),
+                    other => other,
                 };
+                self.apply_operator(op, fraction(a), fraction(b))
             }
-            "comb" => {
-                if args.len() != 2 {
-                    return Err("comb() expects 2 arguments".to_string());
+        }
+    }
+
+    /// Applies `op` to a matrix and another operand; `swapped` means the matrix
+    /// is the right-hand side.
+    fn apply_matrix_operator(
+        &self,
+        op: &Token,
+        m: &Matrix,
+        other: &Value,
+        swapped: bool,
+    ) -> Result<Value, String> {
+        let result = match other {
+            Value::Matrix(other) => {
+                let (a, b) = if swapped { (other, m) } else { (m, other) };
+                match op {
+                    Token::Add => a.add(b)?,
+                    Token::Subtract => a.sub(b)?,
+                    Token::Multiply => a.mul(b)?,
+                    Token::Divide => a.mul(&b.inv()?)?,
+                    _ => return Err(format!("Unsupported matrix operator: {:?}", op)),
                 }
-                return match (&args[0], &args[1]) {
-                    (Token::Number(n), Token::Number(k)) => {
-                        if *n < 0.0 || *k < 0.0 || n.fract() != 0.0 || k.fract() != 0.0 || k > n {
-                            Err("comb(n, k) expects 0 <= k <= n, both integers".to_string())
-                        } else {
-                            let n = *n as u128;
-                            let k = *k as u128;
-                            let mut res = 1u128;
-                            for i in 0..k {
-                                res = res.checked_mul(n - i).ok_or("comb() overflow")?;
-                                res = res.checked_div(i + 1).ok_or("comb() division by zero")?;
-                            }
-                            Ok(res as f64)
-                        }
+            }
+            Value::Number(x) => match (op, swapped) {
+                (Token::Multiply, _) => m.map(|v| v * x),
+                (Token::Divide, false) => {
+                    if *x == 0.0 {
+                        return Err("Division by zero".to_string());
                     }
-                    _ => Err("comb() expects two numbers".to_string()),
-                };
+                    m.map(|v| v / x)
+                }
+                (Token::Divide, true) => m.inv()?.map(|v| v * x),
+                (Token::Power, false) => m.pow(*x)?,
+                (Token::Add | Token::Subtract, _) => {
+                    return Err(
+                        "Cannot add a number to a matrix; use a matrix of the same size"
+                            .to_string(),
+                    );
+                }
+                _ => return Err(format!("Unsupported matrix operator: {:?}", op)),
+            },
+            _ => return Err("Matrices can only be combined with plain numbers".to_string()),
+        };
+        Ok(Value::Matrix(result))
+    }
+
+    /// Lets time quantities and durations mix: next to a date a quantity becomes a
+    /// duration, otherwise a duration becomes a quantity in seconds.
+    fn unify_time(a: Value, b: Value) -> Result<(Value, Value), String> {
+        let has_date = matches!(a, Value::Date(_)) || matches!(b, Value::Date(_));
+        let has_quantity = matches!(a, Value::Quantity(_)) || matches!(b, Value::Quantity(_));
+        let convert = |value: Value| -> Result<Value, String> {
+            match value {
+                Value::Quantity(q) if has_date => {
+                    let seconds = q.convert(&Unit::lookup("s").unwrap())?.value;
+                    Ok(Value::Duration(Span::seconds(seconds)?))
+                }
+                Value::Duration(span) if has_quantity && !has_date && span.months == 0 => {
+                    Ok(Value::Quantity(Quantity::seconds(span.total_seconds())))
+                }
+                other => Ok(other),
+            }
+        };
+        Ok((convert(a)?, convert(b)?))
+    }
+
+    /// Applies `op` to a quantity and another operand; `swapped` means the
+    /// quantity is the right-hand side.
+    fn apply_quantity_operator(
+        &self,
+        op: &Token,
+        q: &Quantity,
+        other: &Value,
+        swapped: bool,
+    ) -> Result<Value, String> {
+        let other = match other {
+            Value::Quantity(other) => other.clone(),
+            Value::Number(n) => Quantity::new(*n, Unit::none()),
+            _ => {
+                return Err(
+                    "Quantities with units can only be combined with plain numbers".to_string(),
+                );
             }
-            "perm" => {
-                if args.len() != 2 {
-                    return Err("perm() expects 2 arguments".to_string());
+        };
+        let (a, b) = if swapped { (&other, q) } else { (q, &other) };
+        let result = match op {
+            Token::Add => a.add(b, 1.0)?,
+            Token::Subtract => a.add(b, -1.0)?,
+            Token::Multiply => a.mul(b),
+            Token::Divide => a.div(b)?,
+            Token::Power => {
+                if !b.unit.is_dimensionless() {
+                    return Err("An exponent cannot have a unit".to_string());
                 }
-                return match (&args[0], &args[1]) {
-                    (Token::Number(n), Token::Number(k)) => {
-                        if *n < 0.0 || *k < 0.0 || n.fract() != 0.0 || k.fract() != 0.0 || k > n {
-                            Err("perm(n, k) expects 0 <= k <= n, both integers".to_string())
-                        } else {
-                            let n = *n as u128;
-                            let k = *k as u128;
-                            let mut res = 1u128;
-                            for i in 0..k {
-                                res = res.checked_mul(n - i).ok_or("perm() overflow")?;
-                            }
-                            Ok(res as f64)
-                        }
-                    }
-                    _ => Err("perm() expects two numbers".to_string()),
-                };
+                a.powf(b.si_value())?
             }
-            _ => {}
-        }
+            _ => return Err(format!("Unsupported operator for quantities: {:?}", op)),
+        };
+        Ok(Value::Quantity(result).normalize())
+    }
 
-        if !crate::functions::is_function_defined(name) {
-            return Err(format!("Function '{}' is not defined", name));
+    fn apply_date_operator(op: &Token, a: &Value, b: &Value) -> Result<Value, String> {
+        match (op, a, b) {
+            (Token::Add, Value::Date(d), Value::Duration(s))
+            | (Token::Add, Value::Duration(s), Value::Date(d)) => {
+                datetime::add(*d, s).map(Value::Date)
+            }
+            (Token::Subtract, Value::Date(d), Value::Duration(s)) => {
+                datetime::add(*d, &s.neg()).map(Value::Date)
+            }
+            (Token::Subtract, Value::Date(a), Value::Date(b)) => {
+                Ok(Value::Duration(datetime::difference(*a, *b)))
+            }
+            (Token::Add, Value::Duration(a), Value::Duration(b)) => a.add(b).map(Value::Duration),
+            (Token::Subtract, Value::Duration(a), Value::Duration(b)) => {
+                a.add(&b.neg()).map(Value::Duration)
+            }
+            (Token::Multiply, Value::Duration(s), Value::Number(n))
+            | (Token::Multiply, Value::Number(n), Value::Duration(s)) => {
+                s.scale(*n).map(Value::Duration)
+            }
+            (Token::Divide, Value::Duration(s), Value::Number(n)) => {
+                if *n == 0.0 {
+                    return Err("Division by zero".to_string());
+                }
+                s.scale(1.0 / n).map(Value::Duration)
+            }
+            (Token::Divide, Value::Duration(a), Value::Duration(b)) => {
+                a.ratio(b).map(Value::Number)
+            }
+            (_, Value::Date(_), Value::Number(_)) | (_, Value::Number(_), Value::Date(_)) => {
+                Err("Dates can only be shifted by durations, e.g. 2026-10-16 + 30 days".to_string())
+            }
+            _ => Err(format!("Unsupported operation on dates: {:?}", op)),
         }
-        let arg_strs: Vec<String> = args
-            .iter()
-            .map(|arg| match arg {
-                Token::Number(n) => Ok(n.to_string()),
-                _ => Err("Function arguments must be numbers".to_string()),
-            })
-            .collect::<Result<Vec<String>, String>>()?;
-
-        let expr = format!("{}({})", name, arg_strs.join(","));
-        crate::functions::calculate_with_custom(&expr)
     }
 
-    fn apply_operator(&self, op: &Token, a: f64, b: f64) -> Result<f64, String> {
+    fn apply_number_operator(&self, op: &Token, a: f64, b: f64) -> Result<f64, String> {
+        if self.settings.ieee {
+            return match op {
+                Token::Add => Ok(a + b),
+                Token::Subtract => Ok(a - b),
+                Token::Multiply => Ok(a * b),
+                Token::Divide => Ok(a / b),
+                Token::Modulo => Ok(a % b),
+                Token::Power => Ok(a.powf(b)),
+                _ => Err(format!("Unexpected operator: {:?}", op)),
+            };
+        }
         match op {
             Token::Add => Ok(a + b),
             Token::Subtract => Ok(a - b),
@@ -311,14 +865,68 @@ impl Evaluator {
         }
     }
 
-    fn perform_operation(&self, values: &mut Vec<f64>, ops: &mut Vec<Token>) -> Result<(), String> {
+    fn apply_uncertain_operator(
+        &self,
+        op: &Token,
+        a: &Uncertain,
+        b: &Uncertain,
+    ) -> Result<Uncertain, String> {
+        match op {
+            Token::Add => Ok(a.add(b)),
+            Token::Subtract => Ok(a.sub(b)),
+            Token::Multiply => Ok(a.mul(b)),
+            Token::Divide => {
+                if b.value == 0.0 && !self.settings.ieee {
+                    return Err("Division by zero".to_string());
+                }
+                Ok(a.div(b))
+            }
+            // a - b·trunc(a / b), with the nominal values under the integer rules
+            Token::Modulo => {
+                let value = self.apply_number_operator(op, a.value, b.value)?;
+                Ok(a.combine(b, value, 1.0, -(a.value / b.value).trunc()))
+            }
+            Token::Power => {
+                if b.std_dev() != 0.0 && a.value <= 0.0 {
+                    return Err("An uncertain exponent requires a positive base".to_string());
+                }
+                // The nominal value follows the same rules as plain numbers.
+                self.apply_number_operator(op, a.value, b.value)?;
+                Ok(a.powf(b))
+            }
+            _ => Err(format!("Unexpected operator: {:?}", op)),
+        }
+    }
+
+    fn apply_interval_operator(
+        &self,
+        op: &Token,
+        a: &Interval,
+        b: &Interval,
+    ) -> Result<Interval, String> {
+        match op {
+            Token::Add => Ok(a.add(b)),
+            Token::Subtract => Ok(a.sub(b)),
+            Token::Multiply => Ok(a.mul(b)),
+            Token::Divide => a.div(b),
+            Token::Modulo => Err("Modulo operation requires exact integer operands".to_string()),
+            Token::Power => a.pow(b),
+            _ => Err(format!("Unexpected operator: {:?}", op)),
+        }
+    }
+
+    fn perform_operation(
+        &self,
+        values: &mut Vec<Value>,
+        ops: &mut Vec<Token>,
+    ) -> Result<(), String> {
         let op = ops.pop().ok_or("Missing operator".to_string())?;
         if op == Token::UnaryMinus {
             if values.is_empty() {
                 return Err("Missing operand for unary minus".to_string());
             }
             let value = values.pop().unwrap();
-            values.push(-value);
+            values.push(Self::negate(value)?);
             return Ok(());
         }
         if values.len() < 2 {
@@ -333,7 +941,7 @@ impl Evaluator {
     }
 }
 
-pub fn evaluate(tokens: &[Token]) -> Result<f64, String> {
+pub fn evaluate(tokens: &[Token]) -> Result<Value, String> {
     Evaluator::new().evaluate(tokens)
 }
 
@@ -342,7 +950,7 @@ mod tests {
     use super::*;
     use crate::parser::Lexer;
 
-    fn eval_expr(expr: &str) -> Result<f64, String> {
+    fn eval_expr(expr: &str) -> Result<Value, String> {
         let mut lexer = Lexer::new(expr);
         let tokens = lexer.tokenize()?;
         evaluate(&tokens)
@@ -502,6 +1110,546 @@ mod tests {
         assert_eq!(eval_expr("(-8) ^ -1").unwrap(), -0.125);
     }
 
+    #[test]
+    fn test_percentages() {
+        assert_eq!(eval_expr("200 + 15%").unwrap(), 230.0);
+        assert_eq!(eval_expr("200 + 15% -5").unwrap(), 225.0);
+        assert_eq!(eval_expr("200 - 10%").unwrap(), 180.0);
+        assert_eq!(eval_expr("15% of 80").unwrap(), 12.0);
+        assert_eq!(eval_expr("200 * 15%").unwrap(), 30.0);
+        assert_eq!(eval_expr("(100 + 20%) - 50%").unwrap(), 60.0);
+        assert_eq!(eval_expr("50 of 200 as %").unwrap(), Value::Percent(25.0));
+        assert_eq!(eval_expr("10% + 5%").unwrap(), Value::Percent(15.0));
+        assert_eq!(eval_expr("-15%").unwrap(), Value::Percent(-15.0));
+        assert_eq!(eval_expr("exp(0%)").unwrap(), 1.0);
+        let Value::Quantity(q) = eval_expr("80 kg + 10%").unwrap() else {
+            panic!("expected a quantity");
+        };
+        assert_eq!(q.value, 88.0);
+
+        // With an operand after it, `%` is still modulo
+        assert_eq!(eval_expr("7 % 3").unwrap(), 1.0);
+        assert_eq!(eval_expr("17 % (2 + 3)").unwrap(), 2.0);
+        assert_eq!(eval_expr("10 % -3").unwrap(), 1.0);
+        assert_eq!(eval_expr("17%5").unwrap(), 2.0);
+        // Glued on both sides, a signed operand could be either reading
+        assert!(eval_expr("200 + 15%-5").is_err());
+        assert_eq!(eval_expr("200 + 15% - 5").unwrap(), 225.0);
+        assert_eq!(eval_expr("200 + 15 % -5").unwrap(), 200.0);
+    }
+
+    #[test]
+    fn test_statistics() {
+        // Numbers and lists mix freely
+        assert_eq!(eval_expr("mean(1, 2, 3, 4)").unwrap(), 2.5);
+        assert_eq!(eval_expr("mean([1, 2], 3, [4])").unwrap(), 2.5);
+        // Uncertain values contribute a third of their uncertainty each
+        let Value::Uncertain(average) = eval_expr("mean(1 ± 0.3, 2 ± 0.4, [3])").unwrap() else {
+            panic!("expected an uncertain value");
+        };
+        assert_eq!(average.value, 2.0);
+        assert!((average.std_dev() - 0.5 / 3.0).abs() < 1e-15);
+        assert_eq!(eval_expr("median([5, 1, 3])").unwrap(), 3.0);
+        assert_eq!(eval_expr("mode(1, 2, 2, 3)").unwrap(), 2.0);
+        assert_eq!(eval_expr("pvariance(2, 4, 4, 4, 5, 5, 7, 9)").unwrap(), 4.0);
+        assert_eq!(eval_expr("pstdev([2, 4, 4, 4, 5, 5, 7, 9])").unwrap(), 2.0);
+        assert_eq!(eval_expr("stdev(1, 3)").unwrap(), 2f64.sqrt());
+        assert_eq!(eval_expr("percentile([1, 2, 3, 4, 5], 90)").unwrap(), 4.6);
+        assert_matrix(
+            eval_expr("quartiles(1, 2, 3, 4, 5)").unwrap(),
+            &[2.0, 3.0, 4.0],
+        );
+        assert_eq!(
+            eval_expr("correlation([1, 2, 3], [3, 2, 1])").unwrap(),
+            -1.0
+        );
+        assert_eq!(eval_expr("covariance([1, 2, 3], [2, 4, 6])").unwrap(), 2.0);
+
+        assert!(eval_expr("mean()").is_err());
+        assert!(eval_expr("variance(5)").is_err());
+        assert!(eval_expr("percentile([1, 2], 101)").is_err());
+        assert!(eval_expr("correlation([1, 2, 3], [1, 2])").is_err());
+        assert!(eval_expr("mean([[1, 2], [3, 4]])").is_err());
+    }
+
+    #[test]
+    fn test_number_theory() {
+        assert_eq!(eval_expr("gcd(12, 18, [30])").unwrap(), 6.0);
+        assert_eq!(eval_expr("lcm(4, 6, 10)").unwrap(), 60.0);
+        assert_eq!(eval_expr("isprime(97)").unwrap(), 1.0);
+        assert_eq!(eval_expr("isprime(91)").unwrap(), 0.0);
+        assert_eq!(
+            eval_expr("nextprime(2^53 - 112)").unwrap(),
+            9007199254740881.0
+        );
+        assert_eq!(
+            eval_expr("factor(120)").unwrap(),
+            Value::Text("2^3 * 3 * 5".to_string())
+        );
+        assert_eq!(
+            eval_expr("factor(-9)").unwrap(),
+            Value::Text("-1 * 3^2".to_string())
+        );
+        assert_matrix(
+            eval_expr("divisors(12)").unwrap(),
+            &[1.0, 2.0, 3.0, 4.0, 6.0, 12.0],
+        );
+        assert_eq!(eval_expr("totient(10)").unwrap(), 4.0);
+        // phi is the golden ratio, not an alias of totient
+        assert!((eval_expr("phi").unwrap().nominal() - 1.618033988749895).abs() < 1e-12);
+        assert!(eval_expr("phi(10)").is_err());
+        assert_eq!(
+            eval_expr("modpow(2, 100, 1000000007)").unwrap(),
+            976371285.0
+        );
+        assert_eq!(eval_expr("modinv(3, 11)").unwrap(), 4.0);
+        assert_eq!(eval_expr("crt([2, 3, 2], [3, 5, 7])").unwrap(), 23.0);
+
+        assert!(eval_expr("gcd(1.5, 3)").is_err());
+        assert!(eval_expr("isprime(2^60)").is_err());
+        // 2^53 + 1 rounds onto 2^53, so the boundary itself is refused
+        assert!(eval_expr("gcd(9007199254740993, 3)").is_err());
+        assert!(eval_expr("factor(2^53)").is_err());
+        assert_eq!(
+            eval_expr("factor(2^53 - 1)").unwrap(),
+            Value::Text("6361 * 69431 * 20394401".to_string())
+        );
+        assert!(eval_expr("totient(0)").is_err());
+        assert!(eval_expr("modinv(2, 4)").is_err());
+    }
+
+    #[test]
+    fn test_special_functions() {
+        assert_eq!(eval_expr("5!").unwrap(), 120.0);
+        assert_eq!(eval_expr("2 ^ 3!").unwrap(), 64.0);
+        assert_eq!(eval_expr("-3! + 1").unwrap(), -5.0);
+        assert_eq!(eval_expr("(1 + 2)!").unwrap(), 6.0);
+        // Non-integers fall back to Γ(x + 1)
+        assert_close(
+            eval_expr("0.5!").unwrap(),
+            std::f64::consts::PI.sqrt() / 2.0,
+        );
+        assert_close(
+            eval_expr("fact(-0.5)").unwrap(),
+            std::f64::consts::PI.sqrt(),
+        );
+        assert_eq!(eval_expr("fact(40) / fact(39)").unwrap(), 40.0);
+        assert!(eval_expr("fact(-2)").is_err());
+        assert!(eval_expr("200!").is_err());
+
+        assert_eq!(eval_expr("gamma(6)").unwrap(), 120.0);
+        assert_close(eval_expr("beta(2, 3)").unwrap(), 1.0 / 12.0);
+        assert_close(eval_expr("erf(1) + erfc(1)").unwrap(), 1.0);
+        assert_close(eval_expr("erfinv(erf(0.7))").unwrap(), 0.7);
+        assert_eq!(eval_expr("j0(0) + j1(0)").unwrap(), 1.0);
+        assert_close(eval_expr("gammainc(2, 3) + gammaincc(2, 3)").unwrap(), 1.0);
+        assert!(eval_expr("gamma(0)").is_err());
+        assert!(eval_expr("erfinv(1)").is_err());
+        assert_eq!(eval_ieee("erfinv(1)").unwrap(), f64::INFINITY);
+
+        // Uncertainty propagates through the derivatives
+        let spread = |input: &str| {
+            let u = eval_expr(input).unwrap().to_uncertain().unwrap();
+            (u.value, u.std_dev())
+        };
+        let (value, sigma) = spread("gamma(3 ± 0.1)");
+        assert_eq!(value, 2.0);
+        assert_close(Value::Number(sigma), 0.2 * (1.5 - 0.5772156649015329));
+        let (_, sigma) = spread("erf(0.5 ± 0.01)");
+        assert_close(
+            Value::Number(sigma),
+            0.02 / std::f64::consts::PI.sqrt() * (-0.25f64).exp(),
+        );
+        let (_, sigma) = spread("j0(1 ± 0.1)");
+        assert_close(Value::Number(sigma), 0.1 * special::bessel_j1(1.0));
+        assert_eq!(spread("j1(0 ± 0.1)"), (0.0, 0.05));
+        let (_, sigma) = spread("beta(2 ± 0.1, 3)");
+        assert_close(Value::Number(sigma), 0.1 * 13.0 / 144.0);
+        // erf + erfc of the same value is exactly 1
+        crate::functions::insert_custom_function("erf_sum", vec!["x"], "erf(x) + erfc(x)").unwrap();
+        assert_eq!(spread("erf_sum(0.3 ± 0.1)").1, 0.0);
+    }
+
+    #[test]
+    fn test_elementary_functions() {
+        assert_eq!(eval_expr("sqrt(16)").unwrap(), 4.0);
+        assert_eq!(eval_expr("cbrt(-27)").unwrap(), -3.0);
+        assert_eq!(eval_expr("abs(-2.5) + sign(-3) + sign(0)").unwrap(), 1.5);
+        assert_eq!(
+            eval_expr("floor(-1.5) + ceil(1.2) + round(2.5)").unwrap(),
+            3.0
+        );
+        assert_eq!(eval_expr("min(3, [1, 5], 2) + max(3, 7)").unwrap(), 8.0);
+        assert_eq!(eval_expr("log10(1000) + log2(8)").unwrap(), 6.0);
+        assert_close(eval_expr("log(81, 3)").unwrap(), 4.0);
+        assert_eq!(eval_expr("ln(1)").unwrap(), 0.0);
+        assert_eq!(eval_expr("hypot(3, 4)").unwrap(), 5.0);
+        // Scaled, so the squares do not overflow
+        assert_close(
+            eval_expr("hypot(3 * 10^200, 4 * 10^200) / 10^200").unwrap(),
+            5.0,
+        );
+        assert_close(
+            eval_expr("atan2(1, -1)").unwrap(),
+            3.0 * std::f64::consts::FRAC_PI_4,
+        );
+        assert_eq!(
+            eval_with_angle("atan2(1, 1)", AngleMode::Degrees).unwrap(),
+            45.0
+        );
+        assert_close(eval_expr("cosh(1)^2 - sinh(1)^2").unwrap(), 1.0);
+        assert_close(
+            eval_expr("tanh(atanh(0.5)) + asinh(sinh(2)) + acosh(cosh(3))").unwrap(),
+            5.5,
+        );
+
+        // Aliases
+        assert_eq!(
+            eval_with_angle("asin(1)", AngleMode::Degrees).unwrap(),
+            90.0
+        );
+        assert_eq!(
+            eval_with_angle("acos(1) + atan(1)", AngleMode::Degrees).unwrap(),
+            45.0
+        );
+        assert_eq!(eval_expr("SQRT(4)").unwrap(), 2.0);
+
+        // Errors name the function as it was called
+        assert_eq!(
+            eval_expr("asin(1, 2)").unwrap_err(),
+            "asin() expects 1 argument, got 2"
+        );
+        assert_eq!(
+            eval_expr("arcsin(2)").unwrap_err(),
+            "arcsin() argument must be within [-1, 1]"
+        );
+        assert!(eval_expr("sqrt(-1)").is_err());
+        assert!(eval_expr("log(8, 1)").is_err());
+        assert!(eval_ieee("sqrt(-1)").unwrap().nominal().is_nan());
+
+        // Uncertainty and intervals propagate through the new functions
+        let Value::Uncertain(root) = eval_expr("sqrt(4 ± 0.4)").unwrap() else {
+            panic!("expected an uncertain value");
+        };
+        assert_eq!((root.value, root.std_dev()), (2.0, 0.1));
+        let Value::Uncertain(length) = eval_expr("hypot(3 ± 0.1, 4)").unwrap() else {
+            panic!("expected an uncertain value");
+        };
+        assert_eq!(length.value, 5.0);
+        assert!((length.std_dev() - 0.06).abs() < 1e-15);
+        let Value::Uncertain(angle) = eval_expr("atan2(1 ± 0.1, 1)").unwrap() else {
+            panic!("expected an uncertain value");
+        };
+        assert!((angle.std_dev() - 0.05).abs() < 1e-15);
+        // min and max carry the uncertainty of the value they pick
+        let Value::Uncertain(largest) = eval_expr("max(1 ± 0.1, 3 ± 0.2, [2])").unwrap() else {
+            panic!("expected an uncertain value");
+        };
+        assert_eq!((largest.value, largest.std_dev()), (3.0, 0.2));
+        assert_eq!(eval_expr("min(1 ± 0.1, 3)").unwrap().nominal(), 1.0);
+        let Value::Interval(floor) = eval_interval("floor([-1.5, 2.5])").unwrap() else {
+            panic!("expected an interval");
+        };
+        assert_eq!((floor.lo, floor.hi), (-2.0, 2.0));
+        let Value::Interval(abs) = eval_interval("abs([-3, 2])").unwrap() else {
+            panic!("expected an interval");
+        };
+        assert_eq!((abs.lo, abs.hi), (0.0, 3.0));
+    }
+
+    #[test]
+    fn test_rounding() {
+        assert_eq!(eval_expr("round(2.675, 2)").unwrap(), 2.68);
+        assert_eq!(eval_expr("round(2.665, 2, \"half-even\")").unwrap(), 2.66);
+        assert_eq!(eval_expr("round(-2.5, 0, \"half-down\")").unwrap(), -2.0);
+        assert_eq!(eval_expr("round(1234, -2)").unwrap(), 1200.0);
+        assert_eq!(eval_expr("trunc(-2.79)").unwrap(), -2.0);
+        assert_eq!(eval_expr("trunc(2.79, 1)").unwrap(), 2.7);
+        assert_eq!(eval_expr("roundsig(123456, 3)").unwrap(), 123000.0);
+        assert_eq!(eval_expr("mround(0.15, 0.05)").unwrap(), 0.15);
+        assert_eq!(
+            eval_expr("mround(7.3, 0.5, \"away-from-zero\")").unwrap(),
+            7.5
+        );
+        assert!(eval_expr("round(1, 2, \"sideways\")").is_err());
+        assert!(eval_expr("round(1, 0.5)").is_err());
+
+        // Without a mode, round() follows the session's rounding setting
+        let mut settings = Settings::default();
+        settings.set("rounding", "half-even").unwrap();
+        let tokens = Lexer::new("round(2.5) + round(0.125, 2)")
+            .tokenize()
+            .unwrap();
+        let mut evaluator = Evaluator {
+            settings,
+            ..Evaluator::new()
+        };
+        assert_eq!(evaluator.evaluate(&tokens).unwrap(), 2.12);
+
+        let Value::Interval(rounded) = eval_interval("round([2.31, 2.37], 1)").unwrap() else {
+            panic!("expected an interval");
+        };
+        assert_eq!((rounded.lo, rounded.hi), (2.3, 2.4));
+
+        // mround() rounds an uncertain value like the rest of the family
+        assert_eq!(eval_expr("mround(7.3 ± 0.2, 0.5)").unwrap().nominal(), 7.5);
+        let Value::Interval(multiple) = eval_interval("mround([7.1, 7.9], 0.5)").unwrap() else {
+            panic!("expected an interval");
+        };
+        assert_eq!((multiple.lo, multiple.hi), (7.0, 8.0));
+        assert!(eval_expr("mround(7.3, 0.5 ± 0.1)").is_err());
+    }
+
+    #[test]
+    fn test_random() {
+        for _ in 0..100 {
+            let x = eval_expr("rand()").unwrap().nominal();
+            assert!((0.0..1.0).contains(&x));
+            let k = eval_expr("randint(-3, 3)").unwrap().nominal();
+            assert!(k.fract() == 0.0 && (-3.0..=3.0).contains(&k));
+            let pick = eval_expr("choice([2, 4, 6])").unwrap().nominal();
+            assert!([2.0, 4.0, 6.0].contains(&pick));
+        }
+        let Value::Text(side) = eval_expr("choice(\"heads\", \"tails\")").unwrap() else {
+            panic!("expected text");
+        };
+        assert!(side == "heads" || side == "tails");
+        assert_eq!(eval_expr("randn(5, 0)").unwrap(), 5.0);
+        assert!(eval_expr("randint(3, 1)").is_err());
+        assert!(eval_expr("randint(1.5, 3)").is_err());
+
+        // Purity is tracked through the whole expression
+        let purity = |expr: &str| {
+            let tokens = Lexer::new(expr).tokenize().unwrap();
+            let mut evaluator = Evaluator::new();
+            evaluator.evaluate(&tokens).unwrap();
+            evaluator.pure
+        };
+        assert!(purity("sin(1) + max(2, 3)"));
+        assert!(!purity("1 + 2 * rand()"));
+        assert!(!purity("sqrt(randint(1, 4))"));
+    }
+
+    #[test]
+    fn test_derivatives() {
+        assert_eq!(
+            eval_expr("diff(x^3 - 2x, x)").unwrap(),
+            Value::Text("3x^2 - 2".to_string())
+        );
+        assert_eq!(eval_expr("diff(x^3 - 2x, x, 2)").unwrap(), 10.0);
+        assert_close(
+            eval_expr("diff(sin(x) * exp(x), x, 1)").unwrap(),
+            3.7560492270947274,
+        );
+        assert_eq!(eval_expr("diff(diff(x^4, x), x, -1)").unwrap(), 12.0);
+        // Letters that are SI prefixes elsewhere are variables in a formula
+        assert_eq!(
+            eval_expr("diff(3u^2, u)").unwrap(),
+            Value::Text("6 * u".to_string())
+        );
+        assert_eq!(eval_expr("diff(2M^2, M, 3)").unwrap(), 12.0);
+        let degrees = eval_with_angle("diff(sin(x), x, 0)", AngleMode::Degrees).unwrap();
+        assert_close(degrees, std::f64::consts::PI / 180.0);
+        assert!(eval_expr("diff(x^2, 2)").is_err());
+        assert!(eval_expr("diff(x^2)").is_err());
+    }
+
+    #[test]
+    fn test_integrals() {
+        assert_close(eval_expr("integrate(x^2, x, 0, 3)").unwrap(), 9.0);
+        assert_close(eval_expr("integrate(3t^2 - 1, t, 2, 0)").unwrap(), -6.0);
+        assert_close(
+            eval_expr("integrate(exp(-x^2 / 2), x, -inf, inf)").unwrap(),
+            (2.0 * std::f64::consts::PI).sqrt(),
+        );
+        assert_close(eval_expr("integrate(diff(x^4, x), x, 0, 1)").unwrap(), 1.0);
+        assert_close(eval_expr("integrate(2k, k, 0, 1)").unwrap(), 1.0);
+        let degrees = eval_with_angle("integrate(cos(x), x, 0, 90)", AngleMode::Degrees).unwrap();
+        assert_close(degrees, 180.0 / std::f64::consts::PI);
+        let error = eval_expr("integrate(1/x, x, 0, 1)").unwrap_err();
+        assert!(
+            error.starts_with("integrate() did not converge"),
+            "{}",
+            error
+        );
+        assert!(eval_expr("integrate(log(x), x, -1, 1)").is_err());
+        assert!(eval_expr("integrate(x, x, 0, [1, 2])").is_err());
+    }
+
+    #[test]
+    fn test_equations() {
+        assert_eq!(eval_expr("solve(3x + 5 = 20, x)").unwrap(), 5.0);
+        assert_eq!(
+            eval_expr("solve(x^3 - 6x^2 + 11x = 6, x)").unwrap(),
+            Value::Matrix(Matrix::column(vec![1.0, 2.0, 3.0]))
+        );
+        assert_close(
+            eval_expr("solve(x^2 = 2, x, 0, 5)").unwrap(),
+            std::f64::consts::SQRT_2,
+        );
+        assert_eq!(eval_expr("solve(sqrt(x) = 3, x)").unwrap(), 9.0);
+        assert_eq!(eval_expr("solve(2n = 4, n)").unwrap(), 2.0);
+        assert_eq!(eval_expr("solve 3k + 5 = 20 for k").unwrap(), 5.0);
+        assert_close(
+            eval_expr("root(cos(x) = x, x, 0, 1)").unwrap(),
+            0.7390851332151607,
+        );
+        // root() lists every root in its interval, as solve() does
+        assert_eq!(
+            eval_expr("root(x^3 - x, x, -2, 2)").unwrap(),
+            Value::Matrix(Matrix::column(vec![-1.0, 0.0, 1.0]))
+        );
+        assert_eq!(eval_expr("root(x^3 - x, x, 2, 0.5)").unwrap(), 1.0);
+        // Matrices still solve linear systems
+        assert_eq!(
+            eval_expr("solve([[2, 0], [0, 4]], [2, 2])").unwrap(),
+            Value::Matrix(Matrix::column(vec![1.0, 0.5]))
+        );
+        assert!(eval_expr("solve(x^2 + 1 = 0, x)").is_err());
+        assert!(eval_expr("solve(x = x + 1, x)").is_err());
+        assert!(eval_expr("root(x^2 + 1, x, -1, 1)").is_err());
+        assert!(eval_expr("1 + 2 = 3").is_err());
+    }
+
+    #[test]
+    fn test_formulas() {
+        let text = |s: &str| Value::Text(s.to_string());
+        assert_eq!(eval_expr("simplify(x * x + x)").unwrap(), text("x^2 + x"));
+        // `k` is a variable here, not the SI prefix
+        assert_eq!(eval_expr("simplify(2k + k)").unwrap(), text("3 * k"));
+        assert_eq!(
+            eval_expr("expand((n + 1)^2)").unwrap(),
+            text("n^2 + 2 * n + 1")
+        );
+        assert_eq!(
+            eval_expr("factor(4p^2 - 1)").unwrap(),
+            text("(2 * p - 1) * (2 * p + 1)")
+        );
+        assert_eq!(
+            eval_expr("expand((x + 1)^3)").unwrap(),
+            text("x^3 + 3x^2 + 3x + 1")
+        );
+        assert_eq!(
+            eval_expr("factor(x^3 - x)").unwrap(),
+            text("x * (x - 1) * (x + 1)")
+        );
+        assert_eq!(
+            eval_expr("factor(diff(x^3, x) - 12)").unwrap(),
+            text("3 * (x - 2) * (x + 2)")
+        );
+        // Without free variables factor() still works on integers
+        assert_eq!(eval_expr("factor(120)").unwrap(), text("2^3 * 3 * 5"));
+        assert_eq!(eval_expr("factor(2 * pi^0 * 6)").unwrap(), text("2^2 * 3"));
+        assert!(eval_expr("factor(x * y + 1)").is_err());
+    }
+
+    #[test]
+    fn test_amortize() {
+        // A built-in, so every front end prints the schedule
+        let Value::Text(table) = eval_expr("amortize(1000, 1%, 12)").unwrap() else {
+            panic!("expected a table");
+        };
+        assert_eq!(table.lines().count(), 15);
+        assert!(
+            table
+                .lines()
+                .last()
+                .unwrap()
+                .trim_start()
+                .starts_with("Total")
+        );
+        assert!(eval_expr("amortize(1000, 1%)").is_err());
+        assert!(eval_expr("amortize(1000, 1%, 0)").is_err());
+    }
+
+    #[test]
+    fn test_distributions() {
+        assert_close(eval_expr("normcdf(1.96)").unwrap(), 0.9750021048517795);
+        assert_close(
+            eval_expr("normcdf(130, 100, 15)").unwrap(),
+            0.9772498680518208,
+        );
+        assert_close(eval_expr("invnorm(0.975)").unwrap(), 1.959963984540054);
+        assert_close(eval_expr("normcdf(norminv(0.2, 5, 2), 5, 2)").unwrap(), 0.2);
+        assert_close(eval_expr("binompmf(3, 10, 0.3)").unwrap(), 0.266827932);
+        assert_close(eval_expr("1 - tcdf(2.1, 15)").unwrap(), 0.02652762807602136);
+        assert_close(eval_expr("chi2cdf(invchi2(0.95, 4), 4)").unwrap(), 0.95);
+        assert_eq!(eval_expr("invbinom(0.5, 10, 0.5)").unwrap(), 5.0);
+        assert_eq!(eval_expr("unifcdf(0.25)").unwrap(), 0.25);
+        assert_close(eval_expr("expcdf(invexp(0.9, 3), 3)").unwrap(), 0.9);
+        assert!(eval_expr("invnorm(1)").is_err());
+        assert_eq!(eval_ieee("invnorm(1)").unwrap(), f64::INFINITY);
+        assert!(eval_expr("normpdf(0, 0, 0)").is_err());
+
+        // Uncertainty in x, mu or sigma propagates through the density
+        let density = crate::distributions::normal_pdf(1.0, 0.0, 1.0).unwrap();
+        for input in [
+            "normcdf(1 ± 0.1)",
+            "normcdf(1, 0 ± 0.1, 1)",
+            "normcdf(1, 0, 1 ± 0.1)",
+        ] {
+            let u = eval_expr(input).unwrap().to_uncertain().unwrap();
+            assert!((u.std_dev() - 0.1 * density).abs() < 1e-15, "{}", input);
+        }
+        let u = eval_expr("normpdf(1 ± 0.1)")
+            .unwrap()
+            .to_uncertain()
+            .unwrap();
+        assert!((u.std_dev() - 0.1 * density).abs() < 1e-15);
+        // The same source in x and mu cancels
+        crate::functions::insert_custom_function("shifted_cdf", vec!["x"], "normcdf(x + 1, x, 1)")
+            .unwrap();
+        let u = eval_expr("shifted_cdf(0 ± 0.1)").unwrap();
+        assert_close(u.clone(), 0.841344746068543);
+        assert_eq!(u.to_uncertain().unwrap().std_dev(), 0.0);
+    }
+
+    fn eval_ieee(expr: &str) -> Result<Value, String> {
+        let tokens = Lexer::new(expr).tokenize()?;
+        let mut evaluator = Evaluator {
+            settings: Settings {
+                ieee: true,
+                ..Settings::default()
+            },
+            ..Evaluator::new()
+        };
+        evaluator.evaluate(&tokens)
+    }
+
+    #[test]
+    fn test_ieee_mode() {
+        assert_eq!(eval_ieee("1 / 0").unwrap(), f64::INFINITY);
+        assert_eq!(eval_ieee("-1 / 0").unwrap(), f64::NEG_INFINITY);
+        assert!(eval_ieee("0 / 0").unwrap().nominal().is_nan());
+        assert!(eval_ieee("(-2) ^ 0.5").unwrap().nominal().is_nan());
+        assert_eq!(eval_ieee("0 ^ 0").unwrap(), 1.0);
+        assert_eq!(eval_ieee("log(0)").unwrap(), f64::NEG_INFINITY);
+
+        assert_eq!(eval_ieee("inf - 10 ^ 308").unwrap(), f64::INFINITY);
+        assert!(eval_ieee("inf - inf").unwrap().nominal().is_nan());
+        assert_eq!(eval_ieee("1 / inf").unwrap(), 0.0);
+        assert_eq!(eval_ieee("isnan(nan)").unwrap(), 1.0);
+        assert_eq!(eval_ieee("isnan(inf)").unwrap(), 0.0);
+        assert_eq!(eval_ieee("isinf(-1 / 0)").unwrap(), 1.0);
+        assert_eq!(eval_ieee("isfinite(1 / 0)").unwrap(), 0.0);
+        assert_eq!(eval_ieee("isfinite(10 ^ 308)").unwrap(), 1.0);
+        // Poles of built-ins and division of uncertain values follow suit
+        assert_eq!(eval_ieee("gamma(0)").unwrap(), f64::INFINITY);
+        assert!(eval_ieee("gamma(-2)").unwrap().nominal().is_nan());
+        assert_eq!(eval_ieee("lgamma(-1)").unwrap(), f64::INFINITY);
+        assert!(eval_ieee("tan(pi / 2)").unwrap().nominal().is_nan());
+        assert_eq!(eval_ieee("1 / (0 ± 1)").unwrap().nominal(), f64::INFINITY);
+        assert!(eval_ieee("0 / (0 ± 1)").unwrap().nominal().is_nan());
+
+        // Strict mode, the default, keeps rejecting them
+        assert!(eval_expr("1 / 0").is_err());
+        assert!(eval_expr("inf").is_err());
+        assert!(eval_expr("gamma(0)").is_err());
+        assert!(eval_expr("tan(pi / 2)").is_err());
+        assert!(eval_expr("1 / (0 ± 1)").is_err());
+        assert_eq!(eval_expr("isfinite(2)").unwrap(), 1.0);
+    }
+
     #[test]
     fn test_power_mixed_operations() {
         // 混合运算
@@ -521,4 +1669,396 @@ mod tests {
         assert_eq!(eval_expr("4 ^ 3 ^ 2").unwrap(), 262144.0); // 4^(3^2)=4^9=262144
         assert_eq!(eval_expr("2 ^ 3 ^ 4").unwrap(), 2417851639229258349412352.0); // 2^(3^4)=2^81
     }
+
+    #[test]
+    fn test_uncertainty_propagation() {
+        let sum = eval_expr("(3 ± 0.3) + (4 +- 0.4)").unwrap();
+        assert_eq!(sum.nominal(), 7.0);
+        assert!((sum.to_uncertain().unwrap().std_dev() - 0.5).abs() < 1e-12);
+
+        // ± binds tighter than multiplication
+        let scaled = eval_expr("2 ± 0.1 * 3").unwrap();
+        assert_eq!(scaled.nominal(), 6.0);
+        assert!((scaled.to_uncertain().unwrap().std_dev() - 0.3).abs() < 1e-12);
+
+        let sine = eval_expr("sin(0.5 ± 0.01)").unwrap();
+        assert!((sine.nominal() - 0.5f64.sin()).abs() < 1e-12);
+        assert!((sine.to_uncertain().unwrap().std_dev() - 0.01 * 0.5f64.cos()).abs() < 1e-12);
+
+        assert_eq!(eval_expr("5 ± 0").unwrap(), 5.0);
+        assert!(eval_expr("5 ± -1").is_err());
+
+        // Only the spaced form is an uncertainty; glued, 5+-0.1 adds -0.1
+        let spaced = eval_expr("5 +- 0.1").unwrap().to_uncertain().unwrap();
+        assert_eq!((spaced.value, spaced.std_dev()), (5.0, 0.1));
+        assert_eq!(eval_expr("5+-0.1").unwrap(), 4.9);
+
+        // Modulo of whole nominal values, a - b·trunc(a / b)
+        let remainder = eval_expr("(17 ± 0.3) % (5 ± 0.1)").unwrap();
+        assert_eq!(remainder.nominal(), 2.0);
+        let expected = (0.3f64.powi(2) + (3.0 * 0.1f64).powi(2)).sqrt();
+        assert!((remainder.to_uncertain().unwrap().std_dev() - expected).abs() < 1e-12);
+        assert!(eval_expr("(5.5 ± 1) % 2").is_err());
+
+        // d/dn n! = n!·ψ(n + 1)
+        let factorial = eval_expr("(4 ± 0.1)!").unwrap();
+        assert_eq!(factorial.nominal(), 24.0);
+        let slope = 24.0 * special::digamma(5.0).unwrap();
+        assert!((factorial.to_uncertain().unwrap().std_dev() - 0.1 * slope).abs() < 1e-9);
+        let called = eval_expr("fact(4 ± 0.1)").unwrap().to_uncertain().unwrap();
+        assert_eq!(
+            called.std_dev(),
+            factorial.to_uncertain().unwrap().std_dev()
+        );
+        assert!(eval_expr("fact(-1 ± 0.1)").is_err());
+    }
+
+    #[test]
+    fn test_custom_function_memo() {
+        let define = |name: &str, expression: &str| {
+            crate::functions::insert_custom_function(name, vec!["x"], expression).unwrap();
+        };
+        // Each call of a body with ± draws a new, independent source
+        define("memo_noisy", "x ± 0.5");
+        let difference = eval_expr("memo_noisy(1) - memo_noisy(1)").unwrap();
+        assert_eq!(difference.nominal(), 0.0);
+        let spread = difference.to_uncertain().unwrap().std_dev();
+        assert!((spread - 0.5f64.hypot(0.5)).abs() < 1e-12);
+
+        // Constant calls in a body are folded once
+        define("memo_scaled", "x * sqrt(4) + max(1, 2) - sqrt(-1)");
+        let tokens = Lexer::new("memo_scaled(3)").tokenize().unwrap();
+        let mut evaluator = Evaluator::new();
+        assert!(evaluator.evaluate(&tokens).is_err());
+        let body = &evaluator.memo.bodies["memo_scaled"];
+        assert_eq!(body[2], Token::Number(2.0));
+        assert_eq!(body[4], Token::Number(2.0));
+        // A failing call is left to fail when it runs
+        assert!(matches!(&body[6], Token::FunctionCall(name, _) if name == "sqrt"));
+
+        // Built-in names are taken, aliases and other cases included
+        for taken in ["sqrt", "Sqrt", "ln", "diff"] {
+            assert!(crate::functions::insert_custom_function(taken, vec!["x"], "x").is_err());
+        }
+
+        // Pure calls are cached, -0 and 0 being the same argument
+        define("memo_square", "x^2 + 1");
+        let tokens = Lexer::new("memo_square(-0) + memo_square(0)")
+            .tokenize()
+            .unwrap();
+        let mut evaluator = Evaluator::new();
+        assert_eq!(evaluator.evaluate(&tokens).unwrap(), 2.0);
+        assert_eq!(evaluator.memo.calls.len(), 1);
+    }
+
+    #[test]
+    fn test_custom_function_bodies_are_formulas() {
+        // 2n is 2 times n, which needs an explicit `*` outside the symbolic
+        // functions; it is never read as 2 nano
+        crate::functions::insert_custom_function("si_odd", vec!["n"], "2n + 1").unwrap();
+        crate::functions::insert_custom_function("si_triple", vec!["k"], "3k").unwrap();
+        crate::functions::insert_custom_function("si_double", vec!["k"], "2*k").unwrap();
+        assert!(eval_expr("si_odd(5)").is_err());
+        assert!(eval_expr("si_triple(2)").is_err());
+        assert_eq!(eval_expr("diff(si_odd(n), n)").unwrap().to_string(), "2");
+        assert_eq!(eval_expr("diff(si_triple(k), k)").unwrap().to_string(), "3");
+        assert_eq!(eval_expr("si_double(2)").unwrap(), 4.0);
+        // Outside a body the suffix still applies
+        assert_eq!(eval_expr("si_double(2k)").unwrap(), 4000.0);
+    }
+
+    #[test]
+    fn test_uncertainty_correlation() {
+        let tokens = Lexer::new("x - x + x * x").tokenize().unwrap();
+        let mut evaluator = Evaluator {
+            settings: Settings::default(),
+            variables: HashMap::from([(
+                "x".to_string(),
+                Value::Uncertain(Uncertain::new(2.0, 0.1)),
+            )]),
+            depth: 0,
+            pure: true,
+            memo: Memo::default(),
+        };
+        let result = evaluator.evaluate(&tokens).unwrap();
+        assert_eq!(result.nominal(), 4.0);
+        // d(x*x)/dx = 2x = 4
+        assert!((result.to_uncertain().unwrap().std_dev() - 0.4).abs() < 1e-12);
+    }
+
+    fn eval_interval(expr: &str) -> Result<Value, String> {
+        let tokens = Lexer::new(expr).tokenize()?;
+        let mut evaluator = Evaluator {
+            settings: Settings {
+                interval: true,
+                ..Settings::default()
+            },
+            ..Evaluator::new()
+        };
+        evaluator.evaluate(&tokens)
+    }
+
+    #[test]
+    fn test_interval_mode() {
+        let Value::Interval(sum) = eval_interval("0.1 + 0.2").unwrap() else {
+            panic!("expected an interval");
+        };
+        assert!(sum.lo <= 0.3 && 0.3 <= sum.hi);
+        assert!(sum.hi - sum.lo < 1e-15);
+
+        // Exact integer arithmetic stays exact
+        assert_eq!(eval_interval("2 + 3 * 4").unwrap(), 14.0);
+        assert_eq!(eval_interval("9007199254740992").unwrap(), 2f64.powi(53));
+        // 2^53 + 1 has no double, so it has to be enclosed
+        let Value::Interval(big) = eval_interval("9007199254740993").unwrap() else {
+            panic!("expected an interval");
+        };
+        assert!(big.lo < 2f64.powi(53) && big.hi > 2f64.powi(53));
+        assert_eq!(eval_interval("fact(2 + 3)").unwrap(), 120.0);
+
+        let Value::Interval(product) = eval_interval("[1, 2] * [-3, 4]").unwrap() else {
+            panic!("expected an interval");
+        };
+        assert_eq!((product.lo, product.hi), (-6.0, 8.0));
+
+        let Value::Interval(sine) = eval_interval("sin([0, 3])").unwrap() else {
+            panic!("expected an interval");
+        };
+        assert_eq!(sine.hi, 1.0);
+        assert!(sine.lo <= 0.0);
+
+        let Value::Interval(tolerance) = eval_interval("10 ± 0.5").unwrap() else {
+            panic!("expected an interval");
+        };
+        assert_eq!((tolerance.lo, tolerance.hi), (9.5, 10.5));
+
+        assert!(eval_interval("1 / [-1, 1]").is_err());
+        assert!(eval_interval("log([-1, 1])").is_err());
+        // Outside interval mode brackets are vectors
+        assert!(matches!(eval_expr("[1, 2]").unwrap(), Value::Matrix(_)));
+    }
+
+    #[test]
+    fn test_format_function() {
+        assert_eq!(
+            eval_expr("format(0.1 + 0.2, \"digits=3 trim\")").unwrap(),
+            Value::Text("0.3".to_string())
+        );
+        assert_eq!(
+            eval_expr("format(1234567, \"separators\")").unwrap(),
+            Value::Text("1,234,567".to_string())
+        );
+        assert_eq!(
+            eval_expr("format(2 ^ 81, \"sci, digits=4\")").unwrap(),
+            Value::Text("2.418e24".to_string())
+        );
+        assert!(eval_expr("format(1, 2)").is_err());
+        assert!(eval_expr("format(1, \"bogus\")").is_err());
+        assert!(eval_expr("format(1, \"sci\") + 1").is_err());
+    }
+
+    fn eval_with_angle(expr: &str, angle: AngleMode) -> Result<Value, String> {
+        let tokens = Lexer::new(expr).tokenize()?;
+        let mut evaluator = Evaluator {
+            settings: Settings {
+                angle,
+                ..Settings::default()
+            },
+            ..Evaluator::new()
+        };
+        evaluator.evaluate(&tokens)
+    }
+
+    fn assert_close(value: Value, expected: f64) {
+        assert!(
+            (value.nominal() - expected).abs() < 1e-12,
+            "{} != {}",
+            value,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_angle_modes() {
+        assert_close(eval_with_angle("sin(30)", AngleMode::Degrees).unwrap(), 0.5);
+        assert_close(
+            eval_with_angle("cos(100)", AngleMode::Gradians).unwrap(),
+            0.0,
+        );
+        assert_close(
+            eval_with_angle("arctan(1)", AngleMode::Degrees).unwrap(),
+            45.0,
+        );
+        assert_close(
+            eval_with_angle("arcsin(1)", AngleMode::Gradians).unwrap(),
+            100.0,
+        );
+
+        // Suffixed angles are converted to the current mode
+        assert_close(
+            eval_with_angle("sin(30deg)", AngleMode::Radians).unwrap(),
+            0.5,
+        );
+        assert_close(
+            eval_with_angle("90deg", AngleMode::Gradians).unwrap(),
+            100.0,
+        );
+        assert_eq!(eval_with_angle("30deg", AngleMode::Degrees).unwrap(), 30.0);
+        assert_close(
+            eval_with_angle("sin(1.2rad)", AngleMode::Degrees).unwrap(),
+            1.2f64.sin(),
+        );
+
+        assert_close(eval_expr("deg(pi)").unwrap(), 180.0);
+        assert_close(eval_expr("rad(90)").unwrap(), std::f64::consts::FRAC_PI_2);
+    }
+
+    #[test]
+    fn test_exact_special_angles() {
+        assert_eq!(eval_expr("sin(pi)").unwrap(), 0.0);
+        assert_eq!(eval_expr("cos(pi/3)").unwrap(), 0.5);
+        assert_eq!(eval_expr("sin(pi/6) + cos(pi/2)").unwrap(), 0.5);
+        assert_eq!(eval_expr("tan(pi/4)").unwrap(), 1.0);
+        assert!(eval_expr("tan(pi/2)").is_err());
+        assert_eq!(eval_with_angle("sin(30)", AngleMode::Degrees).unwrap(), 0.5);
+        assert_eq!(
+            eval_with_angle("arcsin(0.5)", AngleMode::Degrees).unwrap(),
+            30.0
+        );
+    }
+
+    #[test]
+    fn test_tiny_results_are_kept() {
+        assert_eq!(eval_expr("exp(-20)").unwrap(), (-20f64).exp());
+        assert_eq!(eval_expr("sin(1e-9)").unwrap(), 1e-9f64.sin());
+        assert_eq!(eval_expr("log(1.000000001)").unwrap(), 1.000000001f64.ln());
+        assert!(eval_expr("arctan(1e-9)").unwrap().nominal() > 0.0);
+        assert_eq!(eval_expr("sqrt(1e-300)").unwrap(), 1e-150);
+    }
+
+    fn assert_matrix(value: Value, expected: &[f64]) {
+        let Value::Matrix(m) = value else {
+            panic!("expected a matrix, got {}", value);
+        };
+        let data: Vec<f64> = (0..m.rows)
+            .flat_map(|i| (0..m.cols).map(move |j| (i, j)))
+            .map(|(i, j)| m[(i, j)])
+            .collect();
+        assert_eq!(data.len(), expected.len(), "{}", m);
+        for (x, y) in data.iter().zip(expected) {
+            assert!((x - y).abs() < 1e-12, "{} != {:?}", m, expected);
+        }
+    }
+
+    #[test]
+    fn test_matrix_arithmetic() {
+        assert_matrix(
+            eval_expr("[[1, 2], [3, 4]] * [[5, 6], [7, 8]]").unwrap(),
+            &[19.0, 22.0, 43.0, 50.0],
+        );
+        assert_matrix(eval_expr("[[1, 2], [3, 4]] * [1, 1]").unwrap(), &[3.0, 7.0]);
+        assert_matrix(
+            eval_expr("2 * [[1, 2], [3, 4]] - [[1, 1], [1, 1]]").unwrap(),
+            &[1.0, 3.0, 5.0, 7.0],
+        );
+        assert_matrix(
+            eval_expr("[[1, 1], [0, 1]] ^ 3").unwrap(),
+            &[1.0, 3.0, 0.0, 1.0],
+        );
+        assert_matrix(
+            eval_expr("-transpose([[1, 2], [3, 4]])").unwrap(),
+            &[-1.0, -3.0, -2.0, -4.0],
+        );
+        assert!(eval_expr("[[1, 2], [3, 4]] + 1").is_err());
+        assert!(eval_expr("[1, 2] * [1, 2]").is_err());
+        assert!(eval_expr("[[1, 2], [3]]").is_err());
+        assert!(eval_expr("sin([1, 2])").is_err());
+    }
+
+    #[test]
+    fn test_linear_algebra_functions() {
+        assert_close(eval_expr("det([[4, 7], [2, 6]])").unwrap(), 10.0);
+        assert_matrix(
+            eval_expr("inv([[4, 7], [2, 6]])").unwrap(),
+            &[0.6, -0.7, -0.2, 0.4],
+        );
+        assert_eq!(eval_expr("rank([[1, 2], [2, 4]])").unwrap(), 1.0);
+        assert_eq!(eval_expr("trace([[1, 2], [3, 4]])").unwrap(), 5.0);
+        assert_matrix(
+            eval_expr("solve([[2, 1], [1, 3]], [3, 5])").unwrap(),
+            &[0.8, 1.4],
+        );
+        assert_eq!(eval_expr("dot([1, 2, 3], [4, 5, 6])").unwrap(), 32.0);
+        assert_matrix(
+            eval_expr("cross([1, 0, 0], [0, 1, 0])").unwrap(),
+            &[0.0, 0.0, 1.0],
+        );
+        assert_matrix(eval_expr("eig([[2, 0], [0, 3]])").unwrap(), &[3.0, 2.0]);
+        assert!(eval_expr("inv([[1, 2], [2, 4]])").is_err());
+        assert!(eval_expr("det([[1, 2, 3], [4, 5, 6]])").is_err());
+    }
+
+    #[test]
+    fn test_date_arithmetic() {
+        let render = |expr: &str| eval_expr(expr).unwrap().to_string();
+        assert_eq!(render("2026-10-16 + 30 days"), "2026-11-15");
+        assert_eq!(render("date(2026, 12, 25) - 2026-10-16"), "70 days");
+        assert_eq!(render("2026-01-31 + 1 month"), "2026-02-28");
+        assert_eq!(render("2026-10-16T09:30 + 36 hours"), "2026-10-17 21:30");
+        assert_eq!(render("2026-10-16 - duration(\"P1W\")"), "2026-10-09");
+        assert_eq!(render("(2 weeks + 12 hours) * 2"), "29 days");
+        assert_eq!(render("weekday(date(\"2026-12-25\"))"), "Friday");
+        assert_eq!(
+            eval_expr("businessdays(2026-10-16, 2026-10-30)").unwrap(),
+            10.0
+        );
+        assert_eq!(
+            eval_expr("(2027-10-16 - 2026-10-16) / 1 day").unwrap(),
+            365.0
+        );
+        assert!(eval_expr("2026-10-16 + 30").is_err());
+        assert!(eval_expr("2026-10-16 + 2026-10-16").is_err());
+        assert!(eval_expr("2026-02-30").is_err());
+    }
+
+    #[test]
+    fn test_units() {
+        let render = |expr: &str| eval_expr(expr).unwrap().to_string();
+        assert_eq!(render("5 km + 300 m"), "5.3 km");
+        assert_eq!(render("3 kg * 9.81 m/s^2 to N"), "29.43 N");
+        assert_close(eval_expr("60 mph to m/s").unwrap(), 26.8224);
+        assert_close(eval_expr("20 degC in degF").unwrap(), 68.0);
+        assert_close(eval_expr("100 km / 2 h to m/s").unwrap(), 13.88888888888889);
+        assert_eq!(render("2 m * 3 m"), "6 m^2");
+        // Units that cancel leave a plain number
+        assert_eq!(eval_expr("5 km / 2 m").unwrap(), 2500.0);
+        assert_eq!(render("(2026-12-25 - 2026-10-16) to days"), "70 days");
+        assert_eq!(render("2026-10-16 + 36 h"), "2026-10-17 12:00");
+        assert!(eval_expr("1 m + 1 s").is_err());
+        assert!(eval_expr("1 m + 1").is_err());
+        assert!(eval_expr("5 kg to m").is_err());
+    }
+
+    #[test]
+    fn test_constants() {
+        assert_eq!(eval_expr("2 * pi").unwrap(), std::f64::consts::TAU);
+        assert_eq!(eval_expr("PI").unwrap(), std::f64::consts::PI);
+        let rest_energy = eval_expr("m_e * c^2 to MeV").unwrap();
+        assert!((rest_energy.nominal() - 0.51099895).abs() < 1e-9);
+        assert_close(eval_expr("g0 * 2 kg to N").unwrap(), 19.6133);
+        // `h` stays the hour
+        assert_close(eval_expr("2 * h to min").unwrap(), 120.0);
+        assert_close(eval_expr("planck * 1 Hz to eV").unwrap(), 4.135667696e-15);
+        assert_close(eval_expr("const.h * 1 Hz to eV").unwrap(), 4.135667696e-15);
+        assert_close(eval_expr("const.planck / const.h").unwrap(), 1.0);
+
+        // Variables hide constants; const.<name> always reaches them
+        let tokens = Lexer::new("c + const.c / (1 m/s)").tokenize().unwrap();
+        let mut evaluator = Evaluator {
+            variables: HashMap::from([("c".to_string(), Value::Number(1.0))]),
+            ..Evaluator::new()
+        };
+        assert_eq!(evaluator.evaluate(&tokens).unwrap(), 299792459.0);
+        assert!(eval_expr("const.nope").is_err());
+    }
 }
@@ -0,0 +1,409 @@
+//! Time value of money, following spreadsheet conventions.
+//!
+//! Rates are per period and cash flows are signed: money paid out is negative
+//! and money received is positive. `due` puts payments at the start of each
+//! period instead of the end.
+
+use crate::roots;
+
+/// Sum of the growth factors of `n` payments, ((1 + r)^n - 1) / r, exact near r = 0.
+fn annuity(rate: f64, n: f64) -> f64 {
+    if rate == 0.0 {
+        n
+    } else {
+        (n * rate.ln_1p()).exp_m1() / rate
+    }
+}
+
+fn growth(rate: f64, n: f64) -> f64 {
+    (n * rate.ln_1p()).exp()
+}
+
+fn timing(rate: f64, due: bool) -> f64 {
+    if due { 1.0 + rate } else { 1.0 }
+}
+
+fn check_rate(rate: f64) -> Result<(), String> {
+    if rate > -1.0 {
+        Ok(())
+    } else {
+        Err(format!("The rate must be above -100%, got {}", rate))
+    }
+}
+
+/// Future value of a present value and a series of payments.
+pub fn fv(rate: f64, nper: f64, pmt: f64, pv: f64, due: bool) -> Result<f64, String> {
+    check_rate(rate)?;
+    Ok(-(pv * growth(rate, nper) + pmt * timing(rate, due) * annuity(rate, nper)))
+}
+
+/// Present value of a series of payments and a future value.
+pub fn pv(rate: f64, nper: f64, pmt: f64, fv: f64, due: bool) -> Result<f64, String> {
+    check_rate(rate)?;
+    Ok(-(fv + pmt * timing(rate, due) * annuity(rate, nper)) / growth(rate, nper))
+}
+
+/// Payment per period that takes `pv` to `fv` over `nper` periods.
+pub fn pmt(rate: f64, nper: f64, pv: f64, fv: f64, due: bool) -> Result<f64, String> {
+    check_rate(rate)?;
+    if nper == 0.0 {
+        return Err("pmt() needs a non-zero number of periods".to_string());
+    }
+    Ok(-(fv + pv * growth(rate, nper)) / (timing(rate, due) * annuity(rate, nper)))
+}
+
+/// Number of periods for payments of `pmt` to take `pv` to `fv`.
+pub fn nper(rate: f64, pmt: f64, pv: f64, fv: f64, due: bool) -> Result<f64, String> {
+    check_rate(rate)?;
+    let unreachable = || "nper(): the payments never reach the future value".to_string();
+    if rate == 0.0 {
+        if pmt == 0.0 {
+            return Err(unreachable());
+        }
+        return Ok(-(pv + fv) / pmt);
+    }
+    // pv·g + a·(g - 1) + fv = 0 with a = pmt·timing / rate, solved for g = (1 + rate)^n
+    let a = pmt * timing(rate, due) / rate;
+    let g = (a - fv) / (a + pv);
+    if !(g > 0.0 && g.is_finite()) {
+        return Err(unreachable());
+    }
+    Ok(g.ln() / rate.ln_1p())
+}
+
+/// Finds a rate where `f` is zero: Newton's method from `guess` first, then
+/// Brent's method on the closest bracket found by scanning plausible rates.
+fn solve_rate(name: &str, guess: f64, f: impl Fn(f64) -> (f64, f64)) -> Result<f64, String> {
+    if let Some(rate) = roots::newton(&f, guess)
+        && rate > -1.0
+    {
+        return Ok(rate);
+    }
+    const RATES: [f64; 20] = [
+        -0.999, -0.99, -0.95, -0.9, -0.75, -0.5, -0.25, -0.1, 0.0, 0.05, 0.1, 0.2, 0.35, 0.5, 1.0,
+        2.0, 5.0, 10.0, 100.0, 1000.0,
+    ];
+    let bracket = RATES
+        .windows(2)
+        .filter(|pair| {
+            let (lo, hi) = (f(pair[0]).0, f(pair[1]).0);
+            lo.is_finite() && hi.is_finite() && (lo <= 0.0) != (hi <= 0.0)
+        })
+        .min_by(|a, b| {
+            let distance = |pair: &&[f64]| (pair[0] - guess).abs().min((pair[1] - guess).abs());
+            distance(a).total_cmp(&distance(b))
+        });
+    match bracket {
+        Some(pair) => roots::brent(|r| f(r).0, pair[0], pair[1])
+            .map_err(|e| format!("{}() did not converge: {}", name, e)),
+        None => Err(format!(
+            "{}() did not converge: no rate from -99.9% to 100000% fits the cash flows",
+            name
+        )),
+    }
+}
+
+/// Rate per period that takes `pv` to `fv` with payments of `pmt`.
+pub fn rate(nper: f64, pmt: f64, pv: f64, fv: f64, due: bool, guess: f64) -> Result<f64, String> {
+    if nper <= 0.0 {
+        return Err("rate() needs a positive number of periods".to_string());
+    }
+    let balance = |r: f64| pv * growth(r, nper) + pmt * timing(r, due) * annuity(r, nper) + fv;
+    solve_rate("rate", guess, |r| {
+        if r <= -1.0 {
+            return (f64::NAN, f64::NAN);
+        }
+        let h = 1e-7 * (1.0 + r.abs());
+        (balance(r), (balance(r + h) - balance(r - h)) / (2.0 * h))
+    })
+}
+
+/// Net present value of cash flows at the ends of periods 1, 2, ….
+pub fn npv(rate: f64, flows: &[f64]) -> Result<f64, String> {
+    check_rate(rate)?;
+    Ok(flows
+        .iter()
+        .enumerate()
+        .map(|(i, flow)| flow / growth(rate, i as f64 + 1.0))
+        .sum())
+}
+
+/// Net present value at `rate` of flows at the given times, and its derivative.
+fn discounted(rate: f64, flows: &[f64], times: impl Fn(usize) -> f64) -> (f64, f64) {
+    if rate <= -1.0 {
+        return (f64::NAN, f64::NAN);
+    }
+    flows
+        .iter()
+        .enumerate()
+        .fold((0.0, 0.0), |(value, slope), (i, flow)| {
+            let t = times(i);
+            let discounted = flow / growth(rate, t);
+            (value + discounted, slope - t * discounted / (1.0 + rate))
+        })
+}
+
+fn check_flows(name: &str, flows: &[f64]) -> Result<(), String> {
+    if flows.iter().any(|&f| f > 0.0) && flows.iter().any(|&f| f < 0.0) {
+        Ok(())
+    } else {
+        Err(format!(
+            "{}() needs at least one positive and one negative cash flow",
+            name
+        ))
+    }
+}
+
+/// Internal rate of return of cash flows at the starts of periods 0, 1, ….
+pub fn irr(flows: &[f64], guess: f64) -> Result<f64, String> {
+    check_flows("irr", flows)?;
+    solve_rate("irr", guess, |r| discounted(r, flows, |i| i as f64))
+}
+
+/// Internal rate of return, per year of 365 days, of cash flows on the given days.
+pub fn xirr(flows: &[f64], days: &[f64], guess: f64) -> Result<f64, String> {
+    check_flows("xirr", flows)?;
+    if flows.len() != days.len() {
+        return Err(format!(
+            "xirr() expects one date per cash flow, got {} flows and {} dates",
+            flows.len(),
+            days.len()
+        ));
+    }
+    solve_rate("xirr", guess, |r| {
+        discounted(r, flows, |i| (days[i] - days[0]) / 365.0)
+    })
+}
+
+/// One row of an amortization schedule.
+pub struct Installment {
+    pub period: usize,
+    pub payment: f64,
+    pub interest: f64,
+    pub principal: f64,
+    pub balance: f64,
+}
+
+/// Schedule of level payments repaying `principal` over `periods` periods.
+pub fn amortization(principal: f64, rate: f64, periods: f64) -> Result<Vec<Installment>, String> {
+    if periods < 1.0 || periods.fract() != 0.0 || periods > 10_000.0 {
+        return Err("amortize() expects a whole number of periods from 1 to 10000".to_string());
+    }
+    check_rate(rate)?;
+    // The principal repaid grows by 1 + rate each period. Taking it from the payment
+    // minus the interest would cancel to nothing on long schedules.
+    let first = principal / annuity(rate, periods);
+    let mut balance = principal;
+    let mut schedule = Vec::with_capacity(periods as usize);
+    for period in 1..=periods as usize {
+        let interest = balance * rate;
+        // The last payment clears whatever rounding left behind
+        let repaid = if period == periods as usize {
+            balance
+        } else {
+            first * growth(rate, period as f64 - 1.0)
+        };
+        balance -= repaid;
+        schedule.push(Installment {
+            period,
+            payment: interest + repaid,
+            interest,
+            principal: repaid,
+            balance,
+        });
+    }
+    Ok(schedule)
+}
+
+/// The schedule as a table, one row per period and a row of totals, with the
+/// amounts written by `render`.
+pub fn schedule_table(schedule: &[Installment], render: impl Fn(f64) -> String) -> String {
+    let mut rows =
+        vec![["Period", "Payment", "Interest", "Principal", "Balance"].map(String::from)];
+    for row in schedule {
+        rows.push([
+            row.period.to_string(),
+            render(row.payment),
+            render(row.interest),
+            render(row.principal),
+            render(row.balance),
+        ]);
+    }
+    let total = |part: fn(&Installment) -> f64| render(schedule.iter().map(part).sum());
+    rows.push([
+        "Total".to_string(),
+        total(|row| row.payment),
+        total(|row| row.interest),
+        total(|row| row.principal),
+        String::new(),
+    ]);
+
+    let mut widths = [0; 5];
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    // Starts on a line of its own, after the `=` of the result
+    let mut table = String::new();
+    for row in &rows {
+        let cells: Vec<String> = row
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{:>width$}", cell, width = width))
+            .collect();
+        table.push('\n');
+        table.push_str(cells.join("  ").trim_end());
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reference values are from spreadsheet functions, recomputed with mpmath.
+    fn assert_rel(actual: f64, expected: f64) {
+        assert!(
+            ((actual - expected) / expected).abs() < 1e-12,
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_time_value() {
+        assert_rel(
+            pmt(0.05 / 12.0, 360.0, 200000.0, 0.0, false).unwrap(),
+            -1073.643246024278,
+        );
+        assert_rel(
+            fv(0.06 / 12.0, 10.0, -200.0, -500.0, true).unwrap(),
+            2581.403374060179,
+        );
+        assert_rel(
+            pv(0.08 / 12.0, 240.0, 500.0, 0.0, false).unwrap(),
+            -59777.14585118802,
+        );
+        assert_rel(
+            nper(0.01, -100.0, -1000.0, 10000.0, true).unwrap(),
+            59.673865674294626,
+        );
+        assert_rel(
+            rate(48.0, -200.0, 8000.0, 0.0, false, 0.1).unwrap(),
+            0.007701472488202044,
+        );
+    }
+
+    #[test]
+    fn test_zero_and_tiny_rates() {
+        // Without interest the formulas reduce to sums
+        assert_eq!(pmt(0.0, 10.0, 1000.0, 0.0, false).unwrap(), -100.0);
+        assert_eq!(fv(0.0, 10.0, -100.0, 0.0, false).unwrap(), 1000.0);
+        assert_eq!(pv(0.0, 10.0, -100.0, 0.0, true).unwrap(), 1000.0);
+        assert_eq!(nper(0.0, -100.0, 1000.0, 0.0, false).unwrap(), 10.0);
+        // and a rate just above zero stays close to them instead of cancelling
+        assert_rel(
+            pmt(1e-12, 10.0, 1000.0, 0.0, false).unwrap(),
+            -100.00000000055,
+        );
+        assert_rel(
+            fv(1e-15, 360.0, -1.0, 0.0, false).unwrap(),
+            360.0000000000646,
+        );
+        assert!(nper(0.0, 0.0, 1000.0, 0.0, false).is_err());
+        assert!(pmt(0.05, 0.0, 1000.0, 0.0, false).is_err());
+    }
+
+    #[test]
+    fn test_payments_due_at_the_start() {
+        let rate = 0.05 / 12.0;
+        let end = pmt(rate, 360.0, 200000.0, 0.0, false).unwrap();
+        assert_rel(
+            pmt(rate, 360.0, 200000.0, 0.0, true).unwrap(),
+            end / (1.0 + rate),
+        );
+        // Each function undoes the others for either timing
+        for due in [false, true] {
+            let payment = pmt(rate, 60.0, 10000.0, -2000.0, due).unwrap();
+            assert_rel(pv(rate, 60.0, payment, -2000.0, due).unwrap(), 10000.0);
+            assert_rel(fv(rate, 60.0, payment, 10000.0, due).unwrap(), -2000.0);
+            assert_rel(nper(rate, payment, 10000.0, -2000.0, due).unwrap(), 60.0);
+            assert_rel(rate_of(60.0, payment, 10000.0, -2000.0, due), rate);
+        }
+    }
+
+    fn rate_of(nper: f64, pmt: f64, pv: f64, fv: f64, due: bool) -> f64 {
+        rate(nper, pmt, pv, fv, due, 0.1).unwrap()
+    }
+
+    #[test]
+    fn test_unreachable_values() {
+        // Rates at or below -100% leave nothing to grow
+        assert!(pmt(-1.5, 10.0, 1000.0, 0.0, false).is_err());
+        assert!(fv(-1.0, 10.0, -100.0, 0.0, false).is_err());
+        assert!(npv(f64::NAN, &[100.0]).is_err());
+        // Payments below the interest never repay the loan
+        assert!(nper(0.05, -10.0, 1000.0, 0.0, false).is_err());
+        assert!(nper(0.05, -50.0, 1000.0, 0.0, false).is_err());
+        assert!(rate(0.0, -100.0, 1000.0, 0.0, false, 0.1).is_err());
+        // Paying back less than was borrowed takes a negative rate; paying nothing, none at all
+        assert!(rate_of(10.0, -90.0, 1000.0, 0.0, false) < 0.0);
+        assert!(rate(10.0, 0.0, 1000.0, 0.0, false, 0.1).is_err());
+    }
+
+    #[test]
+    fn test_cash_flows() {
+        let flows = [-10000.0, 3000.0, 4200.0, 6800.0];
+        assert_rel(npv(0.1, &flows).unwrap(), 1188.443412335223);
+        let flows = [-70000.0, 12000.0, 15000.0, 18000.0, 21000.0, 26000.0];
+        assert_rel(irr(&flows, 0.1).unwrap(), 0.08663094803653161);
+        // Newton diverges from a poor guess; the bracket scan still finds the rate
+        assert_rel(irr(&flows, 50.0).unwrap(), 0.08663094803653161);
+        assert!(irr(&[100.0, 200.0], 0.1).is_err());
+        assert!(irr(&[], 0.1).is_err());
+        assert_eq!(npv(0.1, &[]).unwrap(), 0.0);
+        // Doubling in one period; a guess below -100% still finds it
+        assert_rel(irr(&[-100.0, 200.0], -5.0).unwrap(), 1.0);
+
+        let flows = [-10000.0, 2750.0, 4250.0, 3250.0, 2750.0];
+        // 2008-01-01, 2008-03-01, 2008-10-30, 2009-02-15, 2009-04-01
+        let days = [0.0, 60.0, 303.0, 411.0, 456.0];
+        assert_rel(xirr(&flows, &days, 0.1).unwrap(), 0.3733625335188315);
+        assert!(xirr(&flows, &days[..4], 0.1).is_err());
+        // A year apart, xirr() agrees with irr()
+        assert_rel(xirr(&[-100.0, 110.0], &[0.0, 365.0], 0.0).unwrap(), 0.1);
+    }
+
+    #[test]
+    fn test_amortization() {
+        let schedule = amortization(1000.0, 0.01, 12.0).unwrap();
+        assert_eq!(schedule.len(), 12);
+        assert_rel(schedule[0].interest, 10.0);
+        assert_rel(schedule[0].payment, 88.84878867834166);
+        assert_eq!(schedule[11].balance, 0.0);
+        let repaid: f64 = schedule.iter().map(|row| row.principal).sum();
+        assert!((repaid - 1000.0).abs() < 1e-9);
+        assert!(amortization(1000.0, 0.01, 0.5).is_err());
+        assert!(amortization(1000.0, 0.01, 0.0).is_err());
+        assert!(amortization(1000.0, 0.01, 10_001.0).is_err());
+        assert!(amortization(1000.0, -1.0, 12.0).is_err());
+
+        // The last payment absorbs the rounding of the others
+        let schedule = amortization(1000.0, 0.07 / 12.0, 10_000.0).unwrap();
+        let last = schedule.last().unwrap();
+        assert_eq!(last.balance, 0.0);
+        assert!((last.payment - schedule[0].payment).abs() < 1e-9);
+        assert!(schedule.iter().all(|row| row.principal > 0.0));
+
+        let table = schedule_table(&amortization(100.0, 0.0, 2.0).unwrap(), |n| n.to_string());
+        assert_eq!(
+            table,
+            "\nPeriod  Payment  Interest  Principal  Balance\
+             \n     1       50         0         50       50\
+             \n     2       50         0         50        0\
+             \n Total      100         0        100"
+        );
+    }
+}
@@ -0,0 +1,596 @@
+use crate::rounding::{self, RoundingMode};
+use crate::value::Value;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Notation {
+    /// Plain decimals, switching to scientific notation for very large or small magnitudes.
+    #[default]
+    Auto,
+    Fixed,
+    Scientific,
+    Engineering,
+    /// Engineering notation with an SI prefix instead of the exponent: `4.7µ`.
+    Si,
+}
+
+/// Whether non-integers are shown as fractions when one is close enough.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum FractionMode {
+    #[default]
+    Off,
+    /// `11/8`
+    Improper,
+    /// `1 3/8`
+    Mixed,
+    /// The continued-fraction expansion, `[1; 2, 1, 2]`
+    Continued,
+}
+
+const DEFAULT_MAX_DENOMINATOR: u64 = 10_000;
+const DEFAULT_FRACTION_TOLERANCE: f64 = 1e-9;
+
+/// Longest continued-fraction expansion that is printed.
+const MAX_CONTINUED_TERMS: usize = 24;
+
+/// How numbers are printed. Unset digit counts keep the shortest exact representation.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NumberFormat {
+    pub notation: Notation,
+    /// Number of significant digits.
+    pub digits: Option<usize>,
+    /// Number of digits after the decimal point; ignored when `digits` is set.
+    pub decimals: Option<usize>,
+    /// Group the integer part in thousands: `1,234,567`.
+    pub separators: bool,
+    /// Drop trailing zeros after the decimal point.
+    pub trim: bool,
+    pub fraction: FractionMode,
+    /// Largest denominator of a displayed fraction.
+    pub max_denominator: Option<u64>,
+    /// Relative error allowed between a number and its displayed fraction.
+    pub fraction_tolerance: Option<f64>,
+    /// How numbers are rounded to `digits` or `decimals`, and the default for `round()`.
+    pub rounding: RoundingMode,
+}
+
+fn parse_count(value: &str) -> Result<Option<usize>, String> {
+    match value.to_lowercase().as_str() {
+        "off" | "none" | "auto" => Ok(None),
+        _ => value
+            .parse::<usize>()
+            .map(Some)
+            .map_err(|_| format!("Expected a digit count or 'off', got '{}'", value)),
+    }
+}
+
+impl NumberFormat {
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key.to_lowercase().as_str() {
+            "notation" => {
+                self.notation = match value.to_lowercase().as_str() {
+                    "auto" => Notation::Auto,
+                    "fixed" => Notation::Fixed,
+                    "sci" | "scientific" => Notation::Scientific,
+                    "eng" | "engineering" => Notation::Engineering,
+                    "si" => Notation::Si,
+                    _ => return Err(format!("Unknown notation: {}", value)),
+                }
+            }
+            "digits" => {
+                self.digits = parse_count(value)?;
+                if self.digits == Some(0) {
+                    return Err("At least one significant digit is required".to_string());
+                }
+            }
+            "decimals" => self.decimals = parse_count(value)?,
+            "separators" => self.separators = crate::settings::parse_switch(value)?,
+            "trim" => self.trim = crate::settings::parse_switch(value)?,
+            "fraction" => {
+                self.fraction = match value.to_lowercase().as_str() {
+                    "off" | "false" | "no" | "0" => FractionMode::Off,
+                    "on" | "improper" => FractionMode::Improper,
+                    "mixed" => FractionMode::Mixed,
+                    "cf" | "continued" => FractionMode::Continued,
+                    _ => return Err(format!("Unknown fraction mode: {}", value)),
+                }
+            }
+            "maxden" => {
+                let max = parse_count(value)?;
+                if max == Some(0) {
+                    return Err("The largest denominator must be at least 1".to_string());
+                }
+                self.max_denominator = max.map(|n| n as u64);
+            }
+            "fractol" => {
+                self.fraction_tolerance = match value.to_lowercase().as_str() {
+                    "off" | "auto" => None,
+                    _ => match value.parse::<f64>() {
+                        Ok(tol) if tol >= 0.0 => Some(tol),
+                        _ => {
+                            return Err(format!(
+                                "Expected a non-negative tolerance, got '{}'",
+                                value
+                            ));
+                        }
+                    },
+                }
+            }
+            "rounding" => {
+                self.rounding = RoundingMode::parse(value).ok_or_else(|| {
+                    format!(
+                        "Unknown rounding mode: {} (half-up, half-even, half-down, toward-zero or away-from-zero)",
+                        value
+                    )
+                })?
+            }
+            _ => return Err(format!("Unknown format setting: {}", key)),
+        }
+        Ok(())
+    }
+
+    /// Current values as `:set` key/value pairs.
+    pub fn entries(&self) -> Vec<(&'static str, String)> {
+        let count = |n: Option<usize>| n.map_or("off".to_string(), |n| n.to_string());
+        let switch = |b: bool| if b { "on" } else { "off" }.to_string();
+        let notation = match self.notation {
+            Notation::Auto => "auto",
+            Notation::Fixed => "fixed",
+            Notation::Scientific => "sci",
+            Notation::Engineering => "eng",
+            Notation::Si => "si",
+        };
+        let fraction = match self.fraction {
+            FractionMode::Off => "off",
+            FractionMode::Improper => "improper",
+            FractionMode::Mixed => "mixed",
+            FractionMode::Continued => "cf",
+        };
+        vec![
+            ("notation", notation.to_string()),
+            ("digits", count(self.digits)),
+            ("decimals", count(self.decimals)),
+            ("separators", switch(self.separators)),
+            ("trim", switch(self.trim)),
+            ("fraction", fraction.to_string()),
+            (
+                "maxden",
+                self.max_denominator
+                    .unwrap_or(DEFAULT_MAX_DENOMINATOR)
+                    .to_string(),
+            ),
+            (
+                "fractol",
+                self.fraction_tolerance
+                    .unwrap_or(DEFAULT_FRACTION_TOLERANCE)
+                    .to_string(),
+            ),
+            ("rounding", self.rounding.name().to_string()),
+        ]
+    }
+
+    /// Parses a `format()` spec such as `"sci digits=4"` or `"decimals=2, separators"`.
+    /// Bare words select a notation or switch a flag on; `key=value` pairs work like `:set`.
+    pub fn from_spec(spec: &str) -> Result<Self, String> {
+        let mut format = NumberFormat::default();
+        for item in spec.split([' ', ',']).filter(|s| !s.is_empty()) {
+            match item.split_once('=') {
+                Some((key, value)) => format.set(key.trim(), value.trim())?,
+                None => match item.to_lowercase().as_str() {
+                    "separators" | "trim" => format.set(item, "on")?,
+                    "fraction" | "improper" => format.set("fraction", "improper")?,
+                    "mixed" | "cf" => format.set("fraction", item)?,
+                    _ => format.set("notation", item)?,
+                },
+            }
+        }
+        Ok(format)
+    }
+
+    pub fn format_number(&self, x: f64) -> String {
+        if x.is_nan() {
+            return "nan".to_string();
+        }
+        if x.is_infinite() {
+            return x.to_string();
+        }
+        if let Some(fraction) = self.format_fraction(x) {
+            return fraction;
+        }
+        // Round decimally first, so ties follow the rounding mode instead of
+        // falling whichever way the binary value happens to lie
+        let x = match (self.digits, self.decimals, self.notation) {
+            (Some(digits), _, Notation::Auto | Notation::Fixed) => {
+                // Integer digits are never dropped, matching the formatting below
+                let exponent = decimal_exponent(x, Some(digits));
+                if self.notation == Notation::Auto && !(-5..16).contains(&exponent) {
+                    rounding::round_significant(x, digits as u32, self.rounding)
+                } else {
+                    rounding::round(x, (digits as i32 - 1 - exponent).max(0), self.rounding)
+                }
+            }
+            (Some(digits), _, _) => rounding::round_significant(x, digits as u32, self.rounding),
+            (None, Some(decimals), Notation::Scientific) => {
+                rounding::round_significant(x, decimals as u32 + 1, self.rounding)
+            }
+            (None, Some(decimals), Notation::Auto | Notation::Fixed) => {
+                rounding::round(x, decimals as i32, self.rounding)
+            }
+            _ => x,
+        };
+        let text = match self.notation {
+            Notation::Auto => match (self.digits, self.decimals) {
+                (Some(digits), _) => {
+                    let exponent = decimal_exponent(x, Some(digits));
+                    if (-5..16).contains(&exponent) {
+                        format!("{:.*}", (digits as i32 - 1 - exponent).max(0) as usize, x)
+                    } else {
+                        format!("{:.*e}", digits - 1, x)
+                    }
+                }
+                (None, Some(decimals)) => format!("{:.*}", decimals, x),
+                (None, None) => {
+                    let exponent = decimal_exponent(x, None);
+                    if x == 0.0 || (-5..16).contains(&exponent) {
+                        x.to_string()
+                    } else {
+                        format!("{:e}", x)
+                    }
+                }
+            },
+            Notation::Fixed => match (self.digits, self.decimals) {
+                (Some(digits), _) => {
+                    let exponent = decimal_exponent(x, Some(digits));
+                    format!("{:.*}", (digits as i32 - 1 - exponent).max(0) as usize, x)
+                }
+                (None, Some(decimals)) => format!("{:.*}", decimals, x),
+                (None, None) => x.to_string(),
+            },
+            Notation::Scientific => match (self.digits, self.decimals) {
+                (Some(digits), _) => format!("{:.*e}", digits - 1, x),
+                (None, Some(decimals)) => format!("{:.*e}", decimals, x),
+                (None, None) => format!("{:e}", x),
+            },
+            Notation::Engineering => self.format_engineering(x),
+            Notation::Si => return self.format_si(x),
+        };
+        self.finish(text)
+    }
+
+    /// `x` as a fraction in the selected mode, if one is within the tolerance.
+    fn format_fraction(&self, x: f64) -> Option<String> {
+        if self.fraction == FractionMode::Off || x.fract() == 0.0 {
+            return None;
+        }
+        let max_denominator = self.max_denominator.unwrap_or(DEFAULT_MAX_DENOMINATOR);
+        let tolerance = self
+            .fraction_tolerance
+            .unwrap_or(DEFAULT_FRACTION_TOLERANCE);
+        let (terms, (num, den)) = continued_fraction(x, max_denominator, tolerance)?;
+        let sign = if x < 0.0 { "-" } else { "" };
+        Some(match self.fraction {
+            FractionMode::Mixed if num.abs() > den => {
+                format!("{}{} {}/{}", sign, num.abs() / den, num.abs() % den, den)
+            }
+            FractionMode::Continued => {
+                let tail: Vec<String> = terms[1..].iter().map(|t| t.to_string()).collect();
+                format!("[{}; {}]", terms[0], tail.join(", "))
+            }
+            _ => format!("{}/{}", num, den),
+        })
+    }
+
+    fn format_engineering(&self, x: f64) -> String {
+        let scientific = match self.digits {
+            Some(digits) => format!("{:.*e}", digits - 1, x),
+            None => format!("{:e}", x),
+        };
+        let (mantissa, exponent) = scientific.split_once('e').unwrap_or((&scientific, "0"));
+        let exponent: i32 = exponent.parse().unwrap_or(0);
+        let eng_exponent = exponent.div_euclid(3) * 3;
+        let mut mantissa = shift_point(mantissa, (exponent - eng_exponent) as usize);
+        if let (None, Some(decimals)) = (self.digits, self.decimals) {
+            mantissa = format!("{:.*}", decimals, mantissa.parse::<f64>().unwrap_or(0.0));
+        }
+        format!("{}e{}", mantissa, eng_exponent)
+    }
+
+    /// Engineering notation with the exponent replaced by an SI prefix, when one exists.
+    fn format_si(&self, x: f64) -> String {
+        let engineering = self.format_engineering(x);
+        let (mantissa, exponent) = engineering.split_once('e').unwrap_or((&engineering, "0"));
+        match si_prefix(exponent.parse().unwrap_or(0)) {
+            Some(prefix) => format!("{}{}", self.finish(mantissa.to_string()), prefix),
+            None => self.finish(engineering),
+        }
+    }
+
+    /// Applies trailing-zero trimming and thousands separators to a rendered number.
+    fn finish(&self, text: String) -> String {
+        let (mantissa, exponent) = match text.find('e') {
+            Some(i) => text.split_at(i),
+            None => (text.as_str(), ""),
+        };
+        let mut mantissa = mantissa.to_string();
+        if self.trim && mantissa.contains('.') {
+            mantissa = mantissa
+                .trim_end_matches('0')
+                .trim_end_matches('.')
+                .to_string();
+        }
+        if self.separators {
+            mantissa = group_thousands(&mantissa);
+        }
+        mantissa + exponent
+    }
+
+    /// The format of numbers in a list or matrix, whose entries are already
+    /// separated by commas and so are not grouped with them.
+    fn in_list(&self) -> NumberFormat {
+        NumberFormat {
+            separators: false,
+            ..self.clone()
+        }
+    }
+
+    /// A bound of an interval, rounded outward so that the printed interval still
+    /// encloses the value. Fractions are approximate, so bounds never use them.
+    fn format_bound(&self, x: f64, upper: bool) -> String {
+        let rounding = if (x >= 0.0) == upper {
+            RoundingMode::AwayFromZero
+        } else {
+            RoundingMode::TowardZero
+        };
+        NumberFormat {
+            rounding,
+            fraction: FractionMode::Off,
+            ..self.in_list()
+        }
+        .format_number(x)
+    }
+
+    pub fn format_value(&self, value: &Value) -> String {
+        match value {
+            Value::Number(n) => self.format_number(*n),
+            Value::Uncertain(u) => format!(
+                "{} ± {}",
+                self.format_number(u.value),
+                self.format_number(u.std_dev())
+            ),
+            Value::Interval(i) => format!(
+                "[{}, {}]",
+                self.format_bound(i.lo, false),
+                self.format_bound(i.hi, true)
+            ),
+            Value::Text(text) => text.clone(),
+            Value::Matrix(m) => {
+                let entries = self.in_list();
+                m.format_with(|x| entries.format_number(x))
+            }
+            Value::Date(_) | Value::Duration(_) => value.to_string(),
+            Value::Quantity(q) => format!("{} {}", self.format_number(q.value), q.unit),
+            Value::Percent(p) => format!("{}%", self.format_number(*p)),
+        }
+    }
+}
+
+/// Expands `x` as a continued fraction until a convergent is within `tolerance`
+/// (relative, or absolute below 1) of `x`. Returns the terms and that convergent,
+/// or `None` if its denominator would exceed `max_denominator`.
+fn continued_fraction(
+    x: f64,
+    max_denominator: u64,
+    tolerance: f64,
+) -> Option<(Vec<i64>, (i64, i64))> {
+    if x.abs() >= i64::MAX as f64 {
+        return None;
+    }
+    let allowed = tolerance * x.abs().max(1.0);
+    let (mut h, mut h_prev) = (1i128, 0i128);
+    let (mut k, mut k_prev) = (0i128, 1i128);
+    let mut terms = Vec::new();
+    let mut rest = x;
+    while terms.len() < MAX_CONTINUED_TERMS {
+        // Round instead of flooring when rounding error left `rest` just below an integer
+        let a = match rest.round() {
+            r if (rest - r).abs() < 1e-9 => r,
+            _ => rest.floor(),
+        };
+        terms.push(a as i64);
+        (h, h_prev) = (a as i128 * h + h_prev, h);
+        (k, k_prev) = (a as i128 * k + k_prev, k);
+        if k > max_denominator as i128 {
+            return None;
+        }
+        if (x - h as f64 / k as f64).abs() <= allowed {
+            return Some((terms, (h as i64, k as i64)));
+        }
+        let fraction = rest - a;
+        if fraction == 0.0 {
+            return None;
+        }
+        rest = 1.0 / fraction;
+    }
+    None
+}
+
+fn si_prefix(exponent: i32) -> Option<&'static str> {
+    let prefix = match exponent {
+        -24 => "y",
+        -21 => "z",
+        -18 => "a",
+        -15 => "f",
+        -12 => "p",
+        -9 => "n",
+        -6 => "µ",
+        -3 => "m",
+        0 => "",
+        3 => "k",
+        6 => "M",
+        9 => "G",
+        12 => "T",
+        15 => "P",
+        18 => "E",
+        21 => "Z",
+        24 => "Y",
+        _ => return None,
+    };
+    Some(prefix)
+}
+
+/// Power of ten of the leading digit of `x`, after rounding to `digits` significant digits.
+fn decimal_exponent(x: f64, digits: Option<usize>) -> i32 {
+    if x == 0.0 {
+        return 0;
+    }
+    let text = match digits {
+        Some(digits) => format!("{:.*e}", digits.max(1) - 1, x),
+        None => format!("{:e}", x),
+    };
+    text.split_once('e')
+        .and_then(|(_, e)| e.parse().ok())
+        .unwrap_or(0)
+}
+
+/// Moves the decimal point of a plain decimal string `places` digits to the right.
+fn shift_point(number: &str, places: usize) -> String {
+    let (sign, digits) = match number.strip_prefix('-') {
+        Some(rest) => ("-", rest),
+        None => ("", number),
+    };
+    let (int_part, frac_part) = digits.split_once('.').unwrap_or((digits, ""));
+    let mut frac_part = frac_part.to_string();
+    while frac_part.len() < places {
+        frac_part.push('0');
+    }
+    let (moved, rest) = frac_part.split_at(places);
+    if rest.is_empty() {
+        format!("{}{}{}", sign, int_part, moved)
+    } else {
+        format!("{}{}{}.{}", sign, int_part, moved, rest)
+    }
+}
+
+fn group_thousands(number: &str) -> String {
+    let (sign, rest) = match number.strip_prefix('-') {
+        Some(rest) => ("-", rest),
+        None => ("", number),
+    };
+    let (int_part, frac_part) = match rest.find('.') {
+        Some(i) => rest.split_at(i),
+        None => (rest, ""),
+    };
+    let mut grouped = String::new();
+    for (i, c) in int_part.chars().enumerate() {
+        if i > 0 && (int_part.len() - i) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(c);
+    }
+    format!("{}{}{}", sign, grouped, frac_part)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec(spec: &str) -> NumberFormat {
+        NumberFormat::from_spec(spec).unwrap()
+    }
+
+    #[test]
+    fn test_default_format() {
+        let format = NumberFormat::default();
+        assert_eq!(format.format_number(0.1 + 0.2), "0.30000000000000004");
+        assert_eq!(format.format_number(2f64.powi(81)), "2.4178516392292583e24");
+        assert_eq!(format.format_number(12.5), "12.5");
+    }
+
+    #[test]
+    fn test_significant_digits_and_decimals() {
+        assert_eq!(spec("digits=3").format_number(0.1 + 0.2), "0.300");
+        assert_eq!(spec("digits=3 trim").format_number(0.1 + 0.2), "0.3");
+        assert_eq!(spec("digits=3").format_number(99.96), "100");
+        assert_eq!(spec("decimals=2").format_number(1.23456), "1.23");
+        assert_eq!(spec("fixed digits=2").format_number(1234.0), "1234");
+        // Ties are decided on the decimal digits, by the rounding mode
+        assert_eq!(spec("decimals=2").format_number(2.675), "2.68");
+        assert_eq!(spec("decimals=2").format_number(0.125), "0.13");
+        assert_eq!(
+            spec("decimals=2 rounding=half-even").format_number(0.125),
+            "0.12"
+        );
+        assert_eq!(
+            spec("digits=2 rounding=toward-zero").format_number(-0.0199),
+            "-0.019"
+        );
+    }
+
+    #[test]
+    fn test_scientific_and_engineering() {
+        assert_eq!(spec("sci digits=3").format_number(123456.0), "1.23e5");
+        assert_eq!(spec("eng").format_number(0.0000047), "4.7e-6");
+        assert_eq!(spec("eng digits=3").format_number(12345.0), "12.3e3");
+        assert_eq!(spec("eng digits=3").format_number(999999.0), "1.00e6");
+        assert_eq!(spec("si").format_number(0.0000047), "4.7µ");
+        assert_eq!(spec("si digits=3").format_number(2200.0), "2.20k");
+        assert_eq!(spec("si").format_number(-330.0), "-330");
+        assert_eq!(spec("si").format_number(1e30), "1e30");
+    }
+
+    #[test]
+    fn test_separators() {
+        assert_eq!(
+            spec("separators").format_number(1234567.891),
+            "1,234,567.891"
+        );
+        assert_eq!(
+            spec("separators, decimals=0").format_number(-1234.0),
+            "-1,234"
+        );
+        // Entries of a list are separated by commas, so they are not grouped
+        let list = Value::Matrix(crate::matrix::Matrix::column(vec![1000.0, 2000.0]));
+        assert_eq!(spec("separators").format_value(&list), "[1000, 2000]");
+        assert!(NumberFormat::from_spec("bogus").is_err());
+    }
+
+    #[test]
+    fn test_interval_bounds() {
+        use crate::interval::Interval;
+        let third = Value::Interval(Interval::new(1.0 / 3.0, 2.0 / 3.0).unwrap());
+        let negated = Value::Interval(Interval::new(-2.0 / 3.0, -1.0 / 3.0).unwrap());
+        // Rounded outward, so the printed bounds still enclose the value
+        assert_eq!(spec("digits=3").format_value(&third), "[0.333, 0.667]");
+        assert_eq!(spec("digits=3").format_value(&negated), "[-0.667, -0.333]");
+        assert_eq!(
+            spec("fraction").format_value(&third),
+            "[0.3333333333333333, 0.6666666666666666]"
+        );
+        // Extreme bounds take an exponent rather than hundreds of digits
+        let wide = Interval::new(-f64::from_bits(1), 1e300).unwrap();
+        assert_eq!(wide.to_string(), "[-5e-324, 1e300]");
+        assert_eq!(
+            NumberFormat::default().format_value(&Value::Interval(wide)),
+            "[-5e-324, 1e300]"
+        );
+    }
+
+    #[test]
+    fn test_fractions() {
+        assert_eq!(spec("fraction").format_number(0.375), "3/8");
+        assert_eq!(spec("fraction").format_number(-1.375), "-11/8");
+        assert_eq!(spec("mixed").format_number(-1.375), "-1 3/8");
+        assert_eq!(spec("mixed").format_number(0.375), "3/8");
+        assert_eq!(spec("cf").format_number(0.375), "[0; 2, 1, 2]");
+        assert_eq!(spec("fraction").format_number(1.0 / 3.0), "1/3");
+        assert_eq!(spec("fraction").format_number(2.0), "2");
+        // No simple fraction is close enough: fall back to decimals
+        assert_eq!(
+            spec("fraction").format_number(std::f64::consts::SQRT_2),
+            "1.4142135623730951"
+        );
+        assert_eq!(
+            spec("fraction maxden=1000 fractol=0.0001").format_number(std::f64::consts::SQRT_2),
+            "99/70"
+        );
+    }
+}
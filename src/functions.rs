@@ -1,5 +1,5 @@
+use crate::value::Value;
 use lazy_static::lazy_static;
-use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::Path;
@@ -48,69 +48,40 @@ pub async fn register_custom_function_async(
     parameters: Vec<&str>,
     expression: &str,
 ) -> Result<(), String> {
-    {
-        let mut map = CUSTOM_FUNCTIONS.lock().unwrap();
-        if map.contains_key(name) {
-            return Err(format!("Function {} already exists", name));
-        }
-        map.insert(
-            name.to_string(),
-            CustomFunction {
-                parameters: parameters.iter().map(|s| s.to_string()).collect(),
-                expression: expression.to_string(),
-            },
-        );
-    }
+    insert_custom_function(name, parameters, expression)?;
     save_functions_async().await;
     Ok(())
 }
 
-pub fn expand_custom_functions(expr: &str) -> Result<String, String> {
-    let mut result = expr.to_string();
-    let re = Regex::new(r"([a-zA-Z_][a-zA-Z0-9_]*)\s*\(([^()]*)\)").unwrap();
-    for _ in 0..20 {
-        let map = CUSTOM_FUNCTIONS.lock().unwrap();
-        let temp = re
-            .replace_all(&result, |caps: &regex::Captures| {
-                let name = &caps[1];
-                let args_str = &caps[2];
-                if let Some(func) = map.get(name) {
-                    let args: Vec<&str> = args_str.split(',').map(|s| s.trim()).collect();
-                    if args.len() != func.parameters.len() {
-                        return format!(
-                            "The number of function parameters is incorrect"
-                        );
-                    }
-                    let mut body = func.expression.clone();
-                    for (param, value) in func.parameters.iter().zip(args.iter()) {
-                        let param_re =
-                            Regex::new(&format!(r"\b{}\b", regex::escape(param))).unwrap();
-                        body = param_re
-                            .replace_all(&body, format!("({})", value))
-                            .to_string();
-                    }
-                    format!("({})", body)
-                } else {
-                    caps[0].to_string()
-                }
-            })
-            .to_string();
-        if temp == result {
-            break;
-        }
-        result = temp;
+/// Defines a function for this session without saving it. Built-ins are looked
+/// up first, so their names are refused rather than silently shadowed.
+pub fn insert_custom_function(
+    name: &str,
+    parameters: Vec<&str>,
+    expression: &str,
+) -> Result<(), String> {
+    if let Some(builtin) = crate::builtins::lookup(name) {
+        return Err(format!(
+            "{} is the built-in function {}(); choose another name",
+            name, builtin.name
+        ));
     }
-    if result.contains(
-        "The number of function parameters is incorrect",
-    ) {
-        return Err("Custom function argument count mismatch".to_string());
+    let mut map = CUSTOM_FUNCTIONS.lock().unwrap();
+    if map.contains_key(name) {
+        return Err(format!("Function {} already exists", name));
     }
-    Ok(result)
+    map.insert(
+        name.to_string(),
+        CustomFunction {
+            parameters: parameters.iter().map(|s| s.to_string()).collect(),
+            expression: expression.to_string(),
+        },
+    );
+    Ok(())
 }
 
-pub fn calculate_with_custom(expr: &str) -> Result<f64, String> {
-    let expanded = expand_custom_functions(expr)?;
-    let mut lexer = crate::parser::Lexer::new(&expanded);
+pub fn calculate_with_custom(expr: &str) -> Result<Value, String> {
+    let mut lexer = crate::parser::Lexer::new(expr);
     let tokens = lexer.tokenize()?;
     crate::evaluator::evaluate(&tokens)
 }
@@ -120,7 +91,7 @@ pub fn list_custom_functions() -> Vec<(String, CustomFunction)> {
     map.iter().map(|(k, v)| (k.clone(), v.clone())).collect()
 }
 
-pub fn is_function_defined(name: &str) -> bool {
+pub fn get_custom_function(name: &str) -> Option<CustomFunction> {
     let map = CUSTOM_FUNCTIONS.lock().unwrap();
-    map.contains_key(name)
+    map.get(name).cloned()
 }
@@ -1,3 +1,5 @@
+use crate::settings::AngleMode;
+use crate::value::Value;
 use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
 use std::path::Path;
@@ -9,9 +11,39 @@ use tokio::io::{AsyncReadExt, AsyncWriteExt, BufReader, BufWriter}; // 添加显
 pub struct HistoryEntry {
     pub expression: String,
     pub result: f64,
+    /// Rendered result for values that are not a finite number, such as `1.2 ± 0.1`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display: Option<String>,
+    /// Angle mode the expression was evaluated in.
+    #[serde(default)]
+    pub angle: AngleMode,
+    /// Random seed of the session, for expressions that drew random numbers;
+    /// replaying the session with `--seed` repeats the draws.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
     pub timestamp: String,
 }
 
+impl HistoryEntry {
+    pub fn new(expression: &str, result: &Value) -> Self {
+        HistoryEntry {
+            expression: expression.to_string(),
+            // JSON has no NaN or infinity, so only finite numbers are stored as such.
+            result: match result.nominal() {
+                n if n.is_finite() => n,
+                _ => 0.0,
+            },
+            display: match result {
+                Value::Number(n) if n.is_finite() => None,
+                other => Some(other.to_string()),
+            },
+            angle: crate::settings::current().angle,
+            seed: crate::random::take_used(),
+            timestamp: current_timestamp(),
+        }
+    }
+}
+
 pub struct HistoryManager {
     file_path: String,
     max_entries: usize,
@@ -19,10 +51,10 @@ pub struct HistoryManager {
 
 impl HistoryManager {
     pub fn new(file_path: &str, max_entries: usize) -> Self {
-        if let Some(parent) = Path::new(file_path).parent() {
-            if !parent.exists() {
-                std::fs::create_dir_all(parent).expect("Failed to create history directory");
-            }
+        if let Some(parent) = Path::new(file_path).parent()
+            && !parent.exists()
+        {
+            std::fs::create_dir_all(parent).expect("Failed to create history directory");
         }
 
         HistoryManager {
@@ -115,6 +147,9 @@ mod tests {
             let entry1 = HistoryEntry {
                 expression: "2+2".to_string(),
                 result: 4.0,
+                display: None,
+                angle: AngleMode::Radians,
+                seed: None,
                 timestamp: current_timestamp(),
             };
 
@@ -128,6 +163,9 @@ mod tests {
                 let entry = HistoryEntry {
                     expression: format!("{}+{}", i, i),
                     result: (i * 2) as f64,
+                    display: None,
+                    angle: AngleMode::Degrees,
+                    seed: Some(42),
                     timestamp: current_timestamp(),
                 };
                 manager.add_entry(entry).await.unwrap();
@@ -137,6 +175,8 @@ mod tests {
             assert_eq!(history.len(), 3);
 
             assert_eq!(history[2].expression, "4+4");
+            assert_eq!(history[2].angle, AngleMode::Degrees);
+            assert_eq!(history[2].seed, Some(42));
 
             manager.clear_history().await.unwrap();
             assert_eq!(manager.get_history().await.unwrap().len(), 0);
@@ -0,0 +1,472 @@
+use crate::format::NumberFormat;
+use std::f64::consts::{FRAC_PI_2, PI};
+use std::fmt;
+
+/// A closed interval `[lo, hi]` that is guaranteed to contain the exact result.
+///
+/// Basic arithmetic detects inexact results with error-free transformations and
+/// only then rounds the bound outward, so exact results stay exact. Library
+/// functions (`sin`, `exp`, ...) are not correctly rounded and are always
+/// widened by one unit in the last place on each side.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Interval {
+    pub lo: f64,
+    pub hi: f64,
+}
+
+fn down(value: f64, error: f64) -> f64 {
+    if error < 0.0 {
+        value.next_down()
+    } else {
+        value
+    }
+}
+
+fn up(value: f64, error: f64) -> f64 {
+    if error > 0.0 { value.next_up() } else { value }
+}
+
+/// Rounding error of `a + b` (Knuth's TwoSum).
+fn sum_error(a: f64, b: f64, sum: f64) -> f64 {
+    let b_virtual = sum - a;
+    let a_virtual = sum - b_virtual;
+    (a - a_virtual) + (b - b_virtual)
+}
+
+fn add_down(a: f64, b: f64) -> f64 {
+    let s = a + b;
+    down(s, sum_error(a, b, s))
+}
+
+fn add_up(a: f64, b: f64) -> f64 {
+    let s = a + b;
+    up(s, sum_error(a, b, s))
+}
+
+fn mul_down(a: f64, b: f64) -> f64 {
+    let p = a * b;
+    down(p, a.mul_add(b, -p))
+}
+
+fn mul_up(a: f64, b: f64) -> f64 {
+    let p = a * b;
+    up(p, a.mul_add(b, -p))
+}
+
+fn div_down(a: f64, b: f64) -> f64 {
+    let q = a / b;
+    // The sign of the remainder `a - q*b` relative to `b` tells which way q was rounded.
+    let r = (-q).mul_add(b, a);
+    down(q, r * b.signum())
+}
+
+fn div_up(a: f64, b: f64) -> f64 {
+    let q = a / b;
+    let r = (-q).mul_add(b, a);
+    up(q, r * b.signum())
+}
+
+/// `a^n` for `a >= 0`, rounded down or up by directed square-and-multiply.
+fn powi_directed(a: f64, mut n: u32, round_up: bool) -> f64 {
+    let mul = if round_up { mul_up } else { mul_down };
+    let (mut result, mut base) = (1.0, a);
+    while n > 0 {
+        if n & 1 == 1 {
+            result = mul(result, base);
+        }
+        base = mul(base, base);
+        n >>= 1;
+    }
+    result
+}
+
+/// Whether the shortest decimal form of `value` is exactly the binary value,
+/// e.g. `0.5` or `3` but not `0.1`.
+pub fn is_exact_decimal(value: f64) -> bool {
+    let repr = format!("{:e}", value.abs());
+    let Some((mantissa, exponent)) = repr.split_once('e') else {
+        return false;
+    };
+    let digits: String = mantissa.chars().filter(|c| *c != '.').collect();
+    let fraction_digits = mantissa.split_once('.').map_or(0, |(_, f)| f.len()) as i32;
+    let (Ok(m), Ok(exponent)) = (digits.parse::<u64>(), exponent.parse::<i32>()) else {
+        return false;
+    };
+    // value = m * 10^scale, checked with an exact product
+    let scale = exponent - fraction_digits;
+    if m > 1 << 53 || scale.abs() > 22 {
+        return false;
+    }
+    let (m, magnitude, power) = (m as f64, value.abs(), 10f64.powi(scale.abs()));
+    if scale >= 0 {
+        let p = m * power;
+        p == magnitude && m.mul_add(power, -p) == 0.0
+    } else {
+        let p = magnitude * power;
+        p == m && magnitude.mul_add(power, -p) == 0.0
+    }
+}
+
+/// Whether the literal `mantissa`·10^`exponent`, as written, is exactly `value`,
+/// the double it was read as: it has to be the shortest decimal form of `value`,
+/// and that has to be exact. `9007199254740993` and `0.1` are not.
+pub fn is_exact_literal(mantissa: &str, exponent: i32, value: f64) -> bool {
+    // Digits without leading or trailing zeros, and the power of ten they scale by
+    let normalize = |mantissa: &str, exponent: i32| {
+        let fraction = mantissa.split_once('.').map_or(0, |(_, f)| f.len()) as i64;
+        let digits: String = mantissa.chars().filter(|c| *c != '.').collect();
+        let digits = digits.trim_start_matches('0');
+        let significant = digits.trim_end_matches('0');
+        let trailing = (digits.len() - significant.len()) as i64;
+        (
+            significant.to_string(),
+            i64::from(exponent) - fraction + trailing,
+        )
+    };
+    let (digits, scale) = normalize(mantissa, exponent);
+    if digits.is_empty() {
+        return value == 0.0;
+    }
+    let repr = format!("{:e}", value.abs());
+    let Some((shortest, power)) = repr.split_once('e') else {
+        return false;
+    };
+    power
+        .parse()
+        .is_ok_and(|power| normalize(shortest, power) == (digits, scale))
+        && is_exact_decimal(value)
+}
+
+/// Whether some `offset + k·period` may lie in `[lo, hi]`; errs towards `true`.
+fn may_contain_periodic(lo: f64, hi: f64, offset: f64, period: f64) -> bool {
+    const SLACK: f64 = 1e-9;
+    ((lo - offset) / period - SLACK).ceil() <= ((hi - offset) / period + SLACK).floor()
+}
+
+impl Interval {
+    pub fn new(lo: f64, hi: f64) -> Result<Self, String> {
+        if lo.is_nan() || hi.is_nan() {
+            return Err("Interval bounds must be numbers".to_string());
+        }
+        if lo > hi {
+            return Err(format!("Invalid interval: [{}, {}]", lo, hi));
+        }
+        Ok(Interval { lo, hi })
+    }
+
+    pub fn point(value: f64) -> Self {
+        Interval {
+            lo: value,
+            hi: value,
+        }
+    }
+
+    /// Encloses a number literal read as `value`, which was `rounded` if the
+    /// decimal written has no exact double.
+    pub fn from_literal(value: f64, rounded: bool) -> Self {
+        if rounded {
+            Interval::widened(value, value)
+        } else {
+            Interval::point(value)
+        }
+    }
+
+    fn widened(lo: f64, hi: f64) -> Self {
+        Interval {
+            lo: lo.next_down(),
+            hi: hi.next_up(),
+        }
+    }
+
+    pub fn is_point(&self) -> bool {
+        self.lo == self.hi
+    }
+
+    pub fn midpoint(&self) -> f64 {
+        self.lo + (self.hi - self.lo) / 2.0
+    }
+
+    pub fn contains(&self, value: f64) -> bool {
+        self.lo <= value && value <= self.hi
+    }
+
+    pub fn neg(&self) -> Self {
+        Interval {
+            lo: -self.hi,
+            hi: -self.lo,
+        }
+    }
+
+    pub fn add(&self, other: &Interval) -> Self {
+        Interval {
+            lo: add_down(self.lo, other.lo),
+            hi: add_up(self.hi, other.hi),
+        }
+    }
+
+    pub fn sub(&self, other: &Interval) -> Self {
+        self.add(&other.neg())
+    }
+
+    pub fn mul(&self, other: &Interval) -> Self {
+        let pairs = [
+            (self.lo, other.lo),
+            (self.lo, other.hi),
+            (self.hi, other.lo),
+            (self.hi, other.hi),
+        ];
+        Interval {
+            lo: pairs
+                .iter()
+                .map(|&(a, b)| mul_down(a, b))
+                .fold(f64::INFINITY, f64::min),
+            hi: pairs
+                .iter()
+                .map(|&(a, b)| mul_up(a, b))
+                .fold(f64::NEG_INFINITY, f64::max),
+        }
+    }
+
+    pub fn div(&self, other: &Interval) -> Result<Self, String> {
+        if other.contains(0.0) {
+            return Err("Division by an interval containing zero".to_string());
+        }
+        let pairs = [
+            (self.lo, other.lo),
+            (self.lo, other.hi),
+            (self.hi, other.lo),
+            (self.hi, other.hi),
+        ];
+        Ok(Interval {
+            lo: pairs
+                .iter()
+                .map(|&(a, b)| div_down(a, b))
+                .fold(f64::INFINITY, f64::min),
+            hi: pairs
+                .iter()
+                .map(|&(a, b)| div_up(a, b))
+                .fold(f64::NEG_INFINITY, f64::max),
+        })
+    }
+
+    fn powi(&self, n: u32) -> Self {
+        if self.lo >= 0.0 {
+            Interval {
+                lo: powi_directed(self.lo, n, false),
+                hi: powi_directed(self.hi, n, true),
+            }
+        } else if self.hi <= 0.0 {
+            let magnitude = self.neg().powi(n);
+            if n.is_multiple_of(2) {
+                magnitude
+            } else {
+                magnitude.neg()
+            }
+        } else if n.is_multiple_of(2) {
+            Interval {
+                lo: 0.0,
+                hi: powi_directed(self.hi.max(-self.lo), n, true),
+            }
+        } else {
+            Interval {
+                lo: -powi_directed(-self.lo, n, true),
+                hi: powi_directed(self.hi, n, true),
+            }
+        }
+    }
+
+    pub fn pow(&self, exponent: &Interval) -> Result<Self, String> {
+        if self.contains(0.0) && exponent.contains(0.0) {
+            return Err("Undefined operation: 0^0".to_string());
+        }
+        if exponent.is_point() && exponent.lo.fract() == 0.0 && exponent.lo.abs() <= u32::MAX as f64
+        {
+            let n = exponent.lo.abs() as u32;
+            let power = self.powi(n);
+            return if exponent.lo < 0.0 {
+                Interval::point(1.0).div(&power)
+            } else {
+                Ok(power)
+            };
+        }
+        if self.lo < 0.0 {
+            return Err("Negative base with fractional exponent is undefined".to_string());
+        }
+        if self.lo == 0.0 {
+            return Err(
+                "A fractional power of an interval touching zero is not supported".to_string(),
+            );
+        }
+        exponent.mul(&self.ln()?).exp()
+    }
+
+    pub fn exp(&self) -> Result<Self, String> {
+        let result = Interval::widened(self.lo.exp(), self.hi.exp());
+        Ok(Interval {
+            lo: result.lo.max(0.0),
+            hi: result.hi,
+        })
+    }
+
+    pub fn ln(&self) -> Result<Self, String> {
+        if self.lo <= 0.0 {
+            return Err("log() argument must be positive".to_string());
+        }
+        Ok(Interval::widened(self.lo.ln(), self.hi.ln()))
+    }
+
+    pub fn sin(&self) -> Result<Self, String> {
+        Ok(self.periodic_bounds(f64::sin, FRAC_PI_2, -FRAC_PI_2))
+    }
+
+    pub fn cos(&self) -> Result<Self, String> {
+        Ok(self.periodic_bounds(f64::cos, 0.0, PI))
+    }
+
+    /// Bounds of `sin`/`cos`, given where the function reaches its maximum and minimum.
+    fn periodic_bounds(&self, f: fn(f64) -> f64, max_at: f64, min_at: f64) -> Self {
+        if self.hi - self.lo >= 2.0 * PI {
+            return Interval { lo: -1.0, hi: 1.0 };
+        }
+        let ends = Interval::widened(f(self.lo).min(f(self.hi)), f(self.lo).max(f(self.hi)));
+        let lo = if may_contain_periodic(self.lo, self.hi, min_at, 2.0 * PI) {
+            -1.0
+        } else {
+            ends.lo.max(-1.0)
+        };
+        let hi = if may_contain_periodic(self.lo, self.hi, max_at, 2.0 * PI) {
+            1.0
+        } else {
+            ends.hi.min(1.0)
+        };
+        Interval { lo, hi }
+    }
+
+    pub fn tan(&self) -> Result<Self, String> {
+        if may_contain_periodic(self.lo, self.hi, FRAC_PI_2, PI) {
+            return Err("tan() is unbounded on the interval".to_string());
+        }
+        Ok(Interval::widened(self.lo.tan(), self.hi.tan()))
+    }
+
+    pub fn asin(&self) -> Result<Self, String> {
+        if self.lo < -1.0 || self.hi > 1.0 {
+            return Err("arcsin() argument must be within [-1, 1]".to_string());
+        }
+        Ok(Interval::widened(self.lo.asin(), self.hi.asin()))
+    }
+
+    pub fn acos(&self) -> Result<Self, String> {
+        if self.lo < -1.0 || self.hi > 1.0 {
+            return Err("arccos() argument must be within [-1, 1]".to_string());
+        }
+        let result = Interval::widened(self.hi.acos(), self.lo.acos());
+        Ok(Interval {
+            lo: result.lo.max(0.0),
+            hi: result.hi,
+        })
+    }
+
+    pub fn atan(&self) -> Result<Self, String> {
+        Ok(Interval::widened(self.lo.atan(), self.hi.atan()))
+    }
+
+    /// Bounds of a non-decreasing library function such as `sinh`.
+    pub fn increasing(&self, f: fn(f64) -> f64) -> Self {
+        Interval::widened(f(self.lo), f(self.hi))
+    }
+
+    /// Bounds of a non-decreasing function computed exactly, such as `floor`.
+    pub fn increasing_exact(&self, f: impl Fn(f64) -> f64) -> Self {
+        Interval {
+            lo: f(self.lo),
+            hi: f(self.hi),
+        }
+    }
+
+    pub fn abs(&self) -> Self {
+        if self.lo >= 0.0 {
+            *self
+        } else if self.hi <= 0.0 {
+            self.neg()
+        } else {
+            Interval {
+                lo: 0.0,
+                hi: self.hi.max(-self.lo),
+            }
+        }
+    }
+
+    pub fn cosh(&self) -> Self {
+        let magnitude = self.abs();
+        let result = magnitude.increasing(f64::cosh);
+        Interval {
+            lo: result.lo.max(1.0),
+            hi: result.hi,
+        }
+    }
+}
+
+impl fmt::Display for Interval {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // The shortest exact form of each bound, with an exponent when large or small
+        let format = NumberFormat::default();
+        write!(
+            f,
+            "[{}, {}]",
+            format.format_number(self.lo),
+            format.format_number(self.hi)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_outward_rounding() {
+        let tenth = Interval::from_literal(0.1, true);
+        let sum = tenth.add(&Interval::from_literal(0.2, true));
+        assert!(sum.lo < 0.3 && 0.3 < sum.hi);
+
+        let third = Interval::point(1.0).div(&Interval::point(3.0)).unwrap();
+        assert!(third.lo < third.hi);
+        assert_eq!(third.hi, third.lo.next_up());
+    }
+
+    #[test]
+    fn test_literal_exactness() {
+        assert!(is_exact_literal("0.5", 0, 0.5));
+        assert!(is_exact_literal("1.25", 3, 1250.0));
+        assert!(is_exact_literal("000.0", 0, 0.0));
+        assert!(!is_exact_literal("0.1", 0, 0.1));
+        assert!(!is_exact_literal("1", 23, 1e23));
+        // Past 2^53 the double can be exact and still not be the number typed
+        assert!(is_exact_literal("9007199254740992", 0, 2f64.powi(53)));
+        assert!(!is_exact_literal("9007199254740993", 0, 2f64.powi(53)));
+        assert!(!is_exact_literal("1", -400, 0.0));
+    }
+
+    #[test]
+    fn test_exact_results_stay_points() {
+        let sum = Interval::point(2.0).add(&Interval::point(3.0));
+        assert!(sum.is_point());
+        let square = Interval::new(-2.0, 3.0)
+            .unwrap()
+            .pow(&Interval::point(2.0))
+            .unwrap();
+        assert_eq!(square, Interval { lo: 0.0, hi: 9.0 });
+    }
+
+    #[test]
+    fn test_trig_extrema() {
+        let around_peak = Interval::new(1.0, 2.0).unwrap().sin().unwrap();
+        assert_eq!(around_peak.hi, 1.0);
+        assert!(around_peak.lo <= 1.0f64.sin());
+
+        let around_trough = Interval::new(3.0, 3.5).unwrap().cos().unwrap();
+        assert_eq!(around_trough.lo, -1.0);
+        assert!(Interval::new(1.0, 2.0).unwrap().tan().is_err());
+    }
+}
@@ -1,8 +1,29 @@
+mod builtins;
 mod cli;
+mod constants;
+mod datetime;
+mod distributions;
 mod evaluator;
+mod finance;
+mod format;
 mod functions;
 mod history;
+mod interval;
+mod matrix;
+mod number_theory;
 mod parser;
+mod quadrature;
+mod random;
+mod roots;
+mod rounding;
+mod settings;
+mod special;
+mod stats;
+mod symbolic;
+mod trig;
+mod uncertainty;
+mod units;
+mod value;
 
 use clap::Parser;
 use std::io::{self, BufRead};
@@ -35,6 +56,89 @@ struct Cli {
     ///Define a function
     #[arg(short = 'd', long)]
     define: Option<String>,
+
+    ///Interval mode (Every number is an interval with guaranteed bounds)
+    #[arg(long)]
+    interval: bool,
+
+    ///IEEE-754 mode (1/0 is inf and 0/0 is nan instead of an error)
+    #[arg(long)]
+    ieee: bool,
+
+    ///Seed for rand(), randint(), randn() and choice(), to reproduce a session
+    #[arg(long)]
+    seed: Option<u64>,
+
+    ///Angle mode for trigonometric functions: rad, deg or grad
+    #[arg(long)]
+    angle: Option<String>,
+
+    ///Number of significant digits in results
+    #[arg(long)]
+    digits: Option<usize>,
+
+    ///Number of decimal places in results
+    #[arg(long)]
+    decimals: Option<usize>,
+
+    ///Result notation: auto, fixed, sci, eng or si
+    #[arg(long)]
+    notation: Option<String>,
+
+    ///Group thousands with separators
+    #[arg(long)]
+    separators: bool,
+
+    ///Trim trailing zeros after the decimal point
+    #[arg(long)]
+    trim: bool,
+
+    ///Show results as fractions: off, improper, mixed or cf
+    #[arg(long)]
+    fraction: Option<String>,
+
+    ///Rounding mode for digits, decimals and round(): half-up, half-even, half-down,
+    ///toward-zero or away-from-zero
+    #[arg(long)]
+    rounding: Option<String>,
+}
+
+impl Cli {
+    /// Settings given on the command line, as `:set` key/value pairs.
+    fn setting_overrides(&self) -> Vec<(&'static str, String)> {
+        let mut overrides = Vec::new();
+        if self.interval {
+            overrides.push(("interval", "on".to_string()));
+        }
+        if self.ieee {
+            overrides.push(("ieee", "on".to_string()));
+        }
+        if let Some(angle) = &self.angle {
+            overrides.push(("angle", angle.clone()));
+        }
+        if let Some(digits) = self.digits {
+            overrides.push(("digits", digits.to_string()));
+        }
+        if let Some(decimals) = self.decimals {
+            overrides.push(("decimals", decimals.to_string()));
+        }
+        if let Some(notation) = &self.notation {
+            overrides.push(("notation", notation.clone()));
+        }
+        if self.separators {
+            overrides.push(("separators", "on".to_string()));
+        }
+        if self.trim {
+            overrides.push(("trim", "on".to_string()));
+        }
+        if let Some(fraction) = &self.fraction {
+            overrides.push(("fraction", fraction.clone()));
+        }
+        if let Some(rounding) = &self.rounding {
+            overrides.push(("rounding", rounding.clone()));
+        }
+        overrides
+    }
 }
 
 #[tokio::main]
@@ -42,6 +146,16 @@ async fn main() {
     let cli = Cli::parse();
     let history_manager = history::HistoryManager::new("history/calc_history.json", 50);
     functions::load_functions_async().await;
+    settings::load_settings_async().await;
+    for (key, value) in cli.setting_overrides() {
+        if let Err(e) = settings::set(key, &value) {
+            eprintln!("Invalid option: {}", e);
+            std::process::exit(2);
+        }
+    }
+    if let Some(seed) = cli.seed {
+        random::set_seed(seed);
+    }
     if cli.clear_history {
         if let Err(e) = history_manager.clear_history().await {
             eprintln!("Failed to clear history: {}", e);
@@ -59,16 +173,12 @@ async fn main() {
     if let Some(expr) = cli.expression {
         if let Ok(result) = cli::calculate(&expr) {
             if !cli.quiet {
-                println!("{} = {}", expr, result);
+                println!("{} = {}", expr, cli::render(&result));
             } else {
-                println!("{}", result);
+                println!("{}", cli::render(&result));
             }
 
-            let entry = history::HistoryEntry {
-                expression: expr.clone(),
-                result,
-                timestamp: history::current_timestamp(),
-            };
+            let entry = history::HistoryEntry::new(&expr, &result);
             if let Err(e) = history_manager.add_entry(entry).await {
                 eprintln!("Warning: Failed to save history {}", e);
             }
@@ -90,16 +200,12 @@ async fn main() {
 
             if let Ok(result) = cli::calculate(&expr) {
                 if !quiet {
-                    println!("{} = {}", expr, result);
+                    println!("{} = {}", expr, cli::render(&result));
                 } else {
-                    println!("{}", result);
+                    println!("{}", cli::render(&result));
                 }
 
-                let entry = history::HistoryEntry {
-                    expression: expr.clone(),
-                    result,
-                    timestamp: history::current_timestamp(),
-                };
+                let entry = history::HistoryEntry::new(&expr, &result);
                 if let Err(e) = history_manager.add_entry(entry).await {
                     eprintln!("Warning: Failed to save history :{}", e);
                 }
@@ -114,12 +220,8 @@ async fn main() {
     if let Some(fcall) = cli.fcall {
         match functions::calculate_with_custom(&fcall) {
             Ok(result) => {
-                println!("{} = {}", fcall, result);
-                let entry = history::HistoryEntry {
-                    expression: fcall.clone(),
-                    result,
-                    timestamp: history::current_timestamp(),
-                };
+                println!("{} = {}", fcall, cli::render(&result));
+                let entry = history::HistoryEntry::new(&fcall, &result);
                 if let Err(e) = history_manager.add_entry(entry).await {
                     eprintln!("Warning: Failed to save history: {}", e);
                 }
@@ -131,7 +233,8 @@ async fn main() {
 
     if let Some(def) = &cli.define {
         match cli::define_function_async(def).await {
-            Ok(_) => println!("Function defined successfully"),
+            Ok(None) => println!("Function defined successfully"),
+            Ok(Some(simplified)) => println!("Function defined successfully: {}", simplified),
             Err(e) => println!("Function definition failed: {}", e),
         }
         return;
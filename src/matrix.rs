@@ -0,0 +1,637 @@
+use std::fmt;
+
+/// Relative size below which a pivot is treated as zero.
+const SINGULAR_TOLERANCE: f64 = 1e-12;
+
+/// Iteration limit of the QR eigenvalue algorithm.
+const MAX_QR_ITERATIONS: usize = 10_000;
+
+/// A dense real matrix stored row by row.
+///
+/// A flat literal `[1, 2, 3]` is a column vector (3×1); `[[1, 2], [3, 4]]`
+/// lists the rows of a 2×2 matrix.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Matrix {
+    pub rows: usize,
+    pub cols: usize,
+    data: Vec<f64>,
+}
+
+impl Matrix {
+    pub fn new(rows: usize, cols: usize, data: Vec<f64>) -> Self {
+        debug_assert_eq!(rows * cols, data.len());
+        Matrix { rows, cols, data }
+    }
+
+    pub fn column(values: Vec<f64>) -> Self {
+        Matrix::new(values.len(), 1, values)
+    }
+
+    pub fn from_rows(rows: Vec<Vec<f64>>) -> Result<Self, String> {
+        let cols = rows.first().map_or(0, Vec::len);
+        if rows.iter().any(|row| row.len() != cols) {
+            return Err("All rows of a matrix must have the same length".to_string());
+        }
+        Ok(Matrix::new(rows.len(), cols, rows.concat()))
+    }
+
+    pub fn identity(n: usize) -> Self {
+        let mut m = Matrix::new(n, n, vec![0.0; n * n]);
+        for i in 0..n {
+            m[(i, i)] = 1.0;
+        }
+        m
+    }
+
+    pub fn is_vector(&self) -> bool {
+        self.cols == 1 || self.rows == 1
+    }
+
+    pub fn is_square(&self) -> bool {
+        self.rows == self.cols
+    }
+
+    /// Entries of a row or column vector.
+    pub fn as_vector(&self) -> Result<&[f64], String> {
+        if self.is_vector() {
+            Ok(&self.data)
+        } else {
+            Err(format!(
+                "Expected a vector, got a {}×{} matrix",
+                self.rows, self.cols
+            ))
+        }
+    }
+
+    fn require_square(&self, name: &str) -> Result<(), String> {
+        if self.is_square() {
+            Ok(())
+        } else {
+            Err(format!(
+                "{}() expects a square matrix, got {}×{}",
+                name, self.rows, self.cols
+            ))
+        }
+    }
+
+    fn same_shape(&self, other: &Matrix, op: &str) -> Result<(), String> {
+        if self.rows == other.rows && self.cols == other.cols {
+            Ok(())
+        } else {
+            Err(format!(
+                "Cannot {} a {}×{} and a {}×{} matrix",
+                op, self.rows, self.cols, other.rows, other.cols
+            ))
+        }
+    }
+
+    pub fn map(&self, f: impl Fn(f64) -> f64) -> Matrix {
+        Matrix::new(
+            self.rows,
+            self.cols,
+            self.data.iter().map(|&x| f(x)).collect(),
+        )
+    }
+
+    pub fn add(&self, other: &Matrix) -> Result<Matrix, String> {
+        self.same_shape(other, "add")?;
+        let data = self
+            .data
+            .iter()
+            .zip(&other.data)
+            .map(|(a, b)| a + b)
+            .collect();
+        Ok(Matrix::new(self.rows, self.cols, data))
+    }
+
+    pub fn sub(&self, other: &Matrix) -> Result<Matrix, String> {
+        self.same_shape(other, "subtract")?;
+        let data = self
+            .data
+            .iter()
+            .zip(&other.data)
+            .map(|(a, b)| a - b)
+            .collect();
+        Ok(Matrix::new(self.rows, self.cols, data))
+    }
+
+    pub fn mul(&self, other: &Matrix) -> Result<Matrix, String> {
+        if self.cols != other.rows {
+            return Err(format!(
+                "Cannot multiply a {}×{} by a {}×{} matrix (use dot() for vectors)",
+                self.rows, self.cols, other.rows, other.cols
+            ));
+        }
+        let mut result = Matrix::new(self.rows, other.cols, vec![0.0; self.rows * other.cols]);
+        for i in 0..self.rows {
+            for j in 0..other.cols {
+                result[(i, j)] = (0..self.cols).map(|k| self[(i, k)] * other[(k, j)]).sum();
+            }
+        }
+        Ok(result)
+    }
+
+    /// Integer power of a square matrix; negative exponents use the inverse.
+    pub fn pow(&self, n: f64) -> Result<Matrix, String> {
+        self.require_square("^")?;
+        if n.fract() != 0.0 {
+            return Err("Matrix powers require an integer exponent".to_string());
+        }
+        let mut base = if n < 0.0 { self.inv()? } else { self.clone() };
+        let mut n = n.abs() as u64;
+        let mut result = Matrix::identity(self.rows);
+        while n > 0 {
+            if n & 1 == 1 {
+                result = result.mul(&base)?;
+            }
+            base = base.mul(&base)?;
+            n >>= 1;
+        }
+        Ok(result)
+    }
+
+    pub fn transpose(&self) -> Matrix {
+        let mut result = Matrix::new(self.cols, self.rows, vec![0.0; self.data.len()]);
+        for i in 0..self.rows {
+            for j in 0..self.cols {
+                result[(j, i)] = self[(i, j)];
+            }
+        }
+        result
+    }
+
+    pub fn trace(&self) -> Result<f64, String> {
+        self.require_square("trace")?;
+        Ok((0..self.rows).map(|i| self[(i, i)]).sum())
+    }
+
+    /// Reduces `self` to row echelon form with partial pivoting, applying the same
+    /// row operations to `rhs`. Returns the pivot columns and the sign of the row
+    /// permutation.
+    fn eliminate(&mut self, rhs: &mut Matrix) -> (Vec<usize>, f64) {
+        let scale = self.data.iter().fold(0.0f64, |m, x| m.max(x.abs()));
+        let tolerance = SINGULAR_TOLERANCE * scale.max(f64::MIN_POSITIVE);
+        let mut pivots = Vec::new();
+        let mut sign = 1.0;
+        let mut row = 0;
+        for col in 0..self.cols {
+            if row == self.rows {
+                break;
+            }
+            let best = (row..self.rows)
+                .max_by(|&a, &b| self[(a, col)].abs().total_cmp(&self[(b, col)].abs()))
+                .unwrap();
+            if self[(best, col)].abs() <= tolerance {
+                continue;
+            }
+            if best != row {
+                self.swap_rows(best, row);
+                rhs.swap_rows(best, row);
+                sign = -sign;
+            }
+            for r in row + 1..self.rows {
+                let factor = self[(r, col)] / self[(row, col)];
+                if factor == 0.0 {
+                    continue;
+                }
+                for c in col..self.cols {
+                    let v = self[(row, c)];
+                    self[(r, c)] -= factor * v;
+                }
+                for c in 0..rhs.cols {
+                    let v = rhs[(row, c)];
+                    rhs[(r, c)] -= factor * v;
+                }
+            }
+            pivots.push(col);
+            row += 1;
+        }
+        (pivots, sign)
+    }
+
+    fn swap_rows(&mut self, a: usize, b: usize) {
+        for c in 0..self.cols {
+            self.data.swap(a * self.cols + c, b * self.cols + c);
+        }
+    }
+
+    pub fn det(&self) -> Result<f64, String> {
+        self.require_square("det")?;
+        let mut lu = self.clone();
+        let (pivots, sign) = lu.eliminate(&mut Matrix::new(self.rows, 0, Vec::new()));
+        if pivots.len() < self.rows {
+            return Ok(0.0);
+        }
+        Ok(sign * (0..self.rows).map(|i| lu[(i, i)]).product::<f64>())
+    }
+
+    pub fn rank(&self) -> usize {
+        let mut echelon = self.clone();
+        echelon
+            .eliminate(&mut Matrix::new(self.rows, 0, Vec::new()))
+            .0
+            .len()
+    }
+
+    /// Solves `self · x = rhs` for a square, non-singular `self`.
+    pub fn solve(&self, rhs: &Matrix) -> Result<Matrix, String> {
+        self.require_square("solve")?;
+        if rhs.rows != self.rows {
+            return Err(format!(
+                "solve() needs a right-hand side with {} rows, got {}",
+                self.rows, rhs.rows
+            ));
+        }
+        let mut upper = self.clone();
+        let mut x = rhs.clone();
+        let (pivots, _) = upper.eliminate(&mut x);
+        if pivots.len() < self.rows {
+            return Err("Matrix is singular".to_string());
+        }
+        for i in (0..self.rows).rev() {
+            for c in 0..x.cols {
+                let tail: f64 = (i + 1..self.rows).map(|k| upper[(i, k)] * x[(k, c)]).sum();
+                x[(i, c)] = (x[(i, c)] - tail) / upper[(i, i)];
+            }
+        }
+        Ok(x)
+    }
+
+    pub fn inv(&self) -> Result<Matrix, String> {
+        self.require_square("inv")?;
+        self.solve(&Matrix::identity(self.rows))
+    }
+
+    /// Real eigenvalues in descending order. 2×2 matrices are solved in closed form,
+    /// larger symmetric ones with Jacobi rotations and the rest by shifted QR
+    /// iteration, which fails on complex eigenvalues.
+    pub fn eigenvalues(&self) -> Result<Vec<f64>, String> {
+        self.require_square("eig")?;
+        let mut values = if self.rows > 2 && *self == self.transpose() {
+            self.symmetric_eigenvalues()
+        } else {
+            self.qr_eigenvalues()?
+        };
+        values.sort_by(|a, b| b.total_cmp(a));
+        Ok(values)
+    }
+
+    fn symmetric_eigenvalues(&self) -> Vec<f64> {
+        let n = self.rows;
+        let mut a = self.clone();
+        for _ in 0..100 {
+            let off: f64 = (0..n)
+                .flat_map(|i| (0..n).filter(move |&j| j != i).map(move |j| (i, j)))
+                .map(|(i, j)| a[(i, j)] * a[(i, j)])
+                .sum();
+            if off <= f64::EPSILON * f64::EPSILON * a.data.iter().map(|x| x * x).sum::<f64>() {
+                break;
+            }
+            for p in 0..n {
+                for q in p + 1..n {
+                    if a[(p, q)] == 0.0 {
+                        continue;
+                    }
+                    let theta = (a[(q, q)] - a[(p, p)]) / (2.0 * a[(p, q)]);
+                    let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+                    let c = 1.0 / (t * t + 1.0).sqrt();
+                    let s = t * c;
+                    for k in 0..n {
+                        let (akp, akq) = (a[(k, p)], a[(k, q)]);
+                        a[(k, p)] = c * akp - s * akq;
+                        a[(k, q)] = s * akp + c * akq;
+                    }
+                    for k in 0..n {
+                        let (apk, aqk) = (a[(p, k)], a[(q, k)]);
+                        a[(p, k)] = c * apk - s * aqk;
+                        a[(q, k)] = s * apk + c * aqk;
+                    }
+                }
+            }
+        }
+        (0..n).map(|i| a[(i, i)]).collect()
+    }
+
+    fn qr_eigenvalues(&self) -> Result<Vec<f64>, String> {
+        let mut a = self.clone();
+        let mut values = Vec::new();
+        let mut n = self.rows;
+        let mut iterations = 0;
+        while n > 0 {
+            if n == 1 {
+                values.push(a[(0, 0)]);
+                break;
+            }
+            let sub = a[(n - 1, n - 2)].abs();
+            let diag = a[(n - 1, n - 1)].abs() + a[(n - 2, n - 2)].abs();
+            if sub <= f64::EPSILON * diag.max(f64::MIN_POSITIVE) {
+                values.push(a[(n - 1, n - 1)]);
+                n -= 1;
+                a = a.leading(n);
+                continue;
+            }
+            let (p, q, r, s) = (
+                a[(n - 2, n - 2)],
+                a[(n - 2, n - 1)],
+                a[(n - 1, n - 2)],
+                a[(n - 1, n - 1)],
+            );
+            let half_trace = (p + s) / 2.0;
+            let discriminant = half_trace * half_trace - (p * s - q * r);
+            let block_converged = n == 2
+                || a[(n - 2, n - 3)].abs()
+                    <= f64::EPSILON * (p.abs() + a[(n - 3, n - 3)].abs()).max(f64::MIN_POSITIVE);
+            if block_converged || iterations > MAX_QR_ITERATIONS {
+                if discriminant < 0.0 {
+                    return Err(
+                        "eig() found complex eigenvalues, which are not supported".to_string()
+                    );
+                }
+                if !block_converged {
+                    return Err("eig() did not converge".to_string());
+                }
+                let root = discriminant.sqrt();
+                values.push(half_trace + root);
+                values.push(half_trace - root);
+                n -= 2;
+                a = a.leading(n);
+                continue;
+            }
+            // Wilkinson shift: the eigenvalue of the trailing 2×2 block closest to its last entry
+            let shift = if discriminant >= 0.0 {
+                let root = discriminant.sqrt();
+                let (l1, l2) = (half_trace + root, half_trace - root);
+                if (l1 - s).abs() < (l2 - s).abs() {
+                    l1
+                } else {
+                    l2
+                }
+            } else {
+                s
+            };
+            let shifted = a.sub(&Matrix::identity(n).map(|x| x * shift))?;
+            let (q_factor, r_factor) = shifted.qr();
+            a = r_factor
+                .mul(&q_factor)?
+                .add(&Matrix::identity(n).map(|x| x * shift))?;
+            iterations += 1;
+        }
+        Ok(values)
+    }
+
+    /// Top-left `n`×`n` block.
+    fn leading(&self, n: usize) -> Matrix {
+        let data = (0..n)
+            .flat_map(|i| (0..n).map(move |j| (i, j)))
+            .map(|(i, j)| self[(i, j)])
+            .collect();
+        Matrix::new(n, n, data)
+    }
+
+    /// QR decomposition by modified Gram-Schmidt.
+    fn qr(&self) -> (Matrix, Matrix) {
+        let n = self.rows;
+        let mut q = self.clone();
+        let mut r = Matrix::new(n, n, vec![0.0; n * n]);
+        for j in 0..n {
+            for k in 0..j {
+                let dot: f64 = (0..n).map(|i| q[(i, k)] * q[(i, j)]).sum();
+                r[(k, j)] = dot;
+                for i in 0..n {
+                    let v = q[(i, k)];
+                    q[(i, j)] -= dot * v;
+                }
+            }
+            let norm = (0..n).map(|i| q[(i, j)] * q[(i, j)]).sum::<f64>().sqrt();
+            r[(j, j)] = norm;
+            if norm > 0.0 {
+                for i in 0..n {
+                    q[(i, j)] /= norm;
+                }
+            }
+        }
+        (q, r)
+    }
+}
+
+pub fn dot(a: &Matrix, b: &Matrix) -> Result<f64, String> {
+    let (a, b) = (a.as_vector()?, b.as_vector()?);
+    if a.len() != b.len() {
+        return Err(format!(
+            "dot() expects vectors of the same length, got {} and {}",
+            a.len(),
+            b.len()
+        ));
+    }
+    Ok(a.iter().zip(b).map(|(x, y)| x * y).sum())
+}
+
+pub fn cross(a: &Matrix, b: &Matrix) -> Result<Matrix, String> {
+    match (a.as_vector()?, b.as_vector()?) {
+        ([a1, a2, a3], [b1, b2, b3]) => Ok(Matrix::column(vec![
+            a2 * b3 - a3 * b2,
+            a3 * b1 - a1 * b3,
+            a1 * b2 - a2 * b1,
+        ])),
+        _ => Err("cross() expects two vectors of length 3".to_string()),
+    }
+}
+
+impl std::ops::Index<(usize, usize)> for Matrix {
+    type Output = f64;
+
+    fn index(&self, (i, j): (usize, usize)) -> &f64 {
+        &self.data[i * self.cols + j]
+    }
+}
+
+impl std::ops::IndexMut<(usize, usize)> for Matrix {
+    fn index_mut(&mut self, (i, j): (usize, usize)) -> &mut f64 {
+        &mut self.data[i * self.cols + j]
+    }
+}
+
+impl Matrix {
+    /// Prints a column vector as `[a, b, c]` and anything else as a list of rows.
+    pub fn format_with(&self, number: impl Fn(f64) -> String) -> String {
+        let row = |i: usize| {
+            let items: Vec<String> = (0..self.cols).map(|j| number(self[(i, j)])).collect();
+            format!("[{}]", items.join(", "))
+        };
+        if self.cols == 1 {
+            let items: Vec<String> = self.data.iter().map(|&x| number(x)).collect();
+            format!("[{}]", items.join(", "))
+        } else {
+            let rows: Vec<String> = (0..self.rows).map(row).collect();
+            format!("[{}]", rows.join(", "))
+        }
+    }
+}
+
+impl fmt::Display for Matrix {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.format_with(|x| x.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn m(rows: &[&[f64]]) -> Matrix {
+        Matrix::from_rows(rows.iter().map(|r| r.to_vec()).collect()).unwrap()
+    }
+
+    #[test]
+    fn test_det_inverse_and_solve() {
+        let a = m(&[&[4.0, 7.0], &[2.0, 6.0]]);
+        assert!((a.det().unwrap() - 10.0).abs() < 1e-12);
+        let product = a.mul(&a.inv().unwrap()).unwrap();
+        assert!(
+            product
+                .sub(&Matrix::identity(2))
+                .unwrap()
+                .data
+                .iter()
+                .all(|x| x.abs() < 1e-12)
+        );
+
+        let x = a.solve(&Matrix::column(vec![1.0, 2.0])).unwrap();
+        assert!((x[(0, 0)] + 0.8).abs() < 1e-12 && (x[(1, 0)] - 0.6).abs() < 1e-12);
+
+        // Several right-hand sides at once
+        let x = a.solve(&m(&[&[1.0, 4.0], &[2.0, 2.0]])).unwrap();
+        assert!((x[(0, 1)] - 1.0).abs() < 1e-12 && x[(1, 1)].abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_pivoting_and_singularity() {
+        // A zero on the diagonal needs a row swap, which flips the determinant's sign
+        let swap = m(&[&[0.0, 1.0], &[1.0, 0.0]]);
+        assert_eq!(swap.det().unwrap(), -1.0);
+        assert_eq!(swap.inv().unwrap(), swap);
+        let x = m(&[&[0.0, 2.0, 1.0], &[1.0, 1.0, 0.0], &[2.0, 0.0, 1.0]])
+            .solve(&Matrix::column(vec![3.0, 2.0, 3.0]))
+            .unwrap();
+        assert!(x.data.iter().all(|v| (v - 1.0).abs() < 1e-12));
+
+        let singular = m(&[&[1.0, 2.0], &[2.0, 4.0]]);
+        assert_eq!(singular.det().unwrap(), 0.0);
+        assert_eq!(singular.rank(), 1);
+        assert!(singular.inv().is_err());
+        assert!(singular.solve(&Matrix::column(vec![1.0, 2.0])).is_err());
+        // Singularity is judged relative to the entries, not against a fixed size
+        let tiny = Matrix::identity(2).map(|x| x * 1e-20);
+        assert_eq!(tiny.rank(), 2);
+        assert!((tiny.det().unwrap() - 1e-40).abs() < 1e-52);
+        let nearly = m(&[&[1.0, 1.0], &[1.0, 1.0 + 1e-15]]);
+        assert_eq!(nearly.rank(), 1);
+        assert_eq!(Matrix::new(2, 2, vec![0.0; 4]).rank(), 0);
+        assert_eq!(m(&[&[1.0, 2.0, 3.0], &[2.0, 4.0, 7.0]]).rank(), 2);
+    }
+
+    #[test]
+    fn test_shapes() {
+        let wide = m(&[&[1.0, 2.0, 3.0], &[4.0, 5.0, 6.0]]);
+        assert!(Matrix::from_rows(vec![vec![1.0, 2.0], vec![3.0]]).is_err());
+        assert!(wide.det().is_err());
+        assert!(wide.inv().is_err());
+        assert!(wide.trace().is_err());
+        assert!(wide.pow(2.0).is_err());
+        assert!(wide.mul(&wide).is_err());
+        assert!(wide.add(&wide.transpose()).is_err());
+        let product = wide.mul(&wide.transpose()).unwrap();
+        assert_eq!(product, m(&[&[14.0, 32.0], &[32.0, 77.0]]));
+        assert!(
+            Matrix::identity(2)
+                .solve(&Matrix::column(vec![1.0, 2.0, 3.0]))
+                .is_err()
+        );
+        assert!(wide.as_vector().is_err());
+        assert_eq!(wide.transpose().transpose(), wide);
+    }
+
+    #[test]
+    fn test_powers() {
+        let a = m(&[&[1.0, 1.0], &[1.0, 0.0]]);
+        assert_eq!(a.pow(0.0).unwrap(), Matrix::identity(2));
+        assert_eq!(a.pow(10.0).unwrap(), m(&[&[89.0, 55.0], &[55.0, 34.0]]));
+        assert_eq!(a.pow(-1.0).unwrap(), a.inv().unwrap());
+        let back = a.pow(-3.0).unwrap().mul(&a.pow(3.0).unwrap()).unwrap();
+        assert!(
+            back.sub(&Matrix::identity(2))
+                .unwrap()
+                .data
+                .iter()
+                .all(|x| x.abs() < 1e-12)
+        );
+        assert!(a.pow(0.5).is_err());
+        assert!(m(&[&[1.0, 2.0], &[2.0, 4.0]]).pow(-1.0).is_err());
+    }
+
+    #[test]
+    fn test_eigenvalues() {
+        let symmetric = m(&[&[2.0, 1.0], &[1.0, 2.0]]);
+        let values = symmetric.eigenvalues().unwrap();
+        assert!((values[0] - 3.0).abs() < 1e-12 && (values[1] - 1.0).abs() < 1e-12);
+
+        let upper = m(&[&[1.0, 2.0, 3.0], &[0.0, 4.0, 5.0], &[0.0, 0.0, 6.0]]);
+        assert_eq!(upper.eigenvalues().unwrap(), vec![6.0, 4.0, 1.0]);
+
+        let general = m(&[&[4.0, 1.0], &[2.0, 3.0]]);
+        let values = general.eigenvalues().unwrap();
+        assert!((values[0] - 5.0).abs() < 1e-9 && (values[1] - 2.0).abs() < 1e-9);
+
+        let rotation = m(&[&[0.0, -1.0], &[1.0, 0.0]]);
+        assert!(rotation.eigenvalues().is_err());
+    }
+
+    #[test]
+    fn test_repeated_and_complex_eigenvalues() {
+        assert_eq!(Matrix::identity(3).eigenvalues().unwrap(), vec![1.0; 3]);
+        assert_eq!(
+            Matrix::new(3, 3, vec![0.0; 9]).eigenvalues().unwrap(),
+            vec![0.0; 3]
+        );
+        // Defective: one eigenvector for a double eigenvalue
+        assert_eq!(
+            m(&[&[1.0, 1.0], &[0.0, 1.0]]).eigenvalues().unwrap(),
+            vec![1.0, 1.0]
+        );
+
+        let values = m(&[&[2.0, 0.0, 0.0], &[0.0, 3.0, 4.0], &[0.0, 4.0, 9.0]])
+            .eigenvalues()
+            .unwrap();
+        for (value, expected) in values.iter().zip([11.0, 2.0, 1.0]) {
+            assert!((value - expected).abs() < 1e-12, "{:?}", values);
+        }
+        // A 3×3 rotation has one real and two complex eigenvalues
+        let rotation = m(&[&[0.0, -1.0, 0.0], &[1.0, 0.0, 0.0], &[0.0, 0.0, 2.0]]);
+        assert!(rotation.eigenvalues().is_err());
+        assert!(m(&[&[1.0, 2.0, 3.0]]).eigenvalues().is_err());
+    }
+
+    #[test]
+    fn test_vector_products() {
+        let x = Matrix::column(vec![1.0, 0.0, 0.0]);
+        let y = Matrix::column(vec![0.0, 1.0, 0.0]);
+        assert_eq!(cross(&x, &y).unwrap(), Matrix::column(vec![0.0, 0.0, 1.0]));
+        assert_eq!(dot(&x, &y).unwrap(), 0.0);
+        assert!(dot(&x, &Matrix::column(vec![1.0])).is_err());
+        // A row vector works as well as a column
+        let row = m(&[&[1.0, 2.0, 3.0]]);
+        assert_eq!(
+            dot(&row, &Matrix::column(vec![4.0, 5.0, 6.0])).unwrap(),
+            32.0
+        );
+        assert_eq!(cross(&y, &x).unwrap(), Matrix::column(vec![0.0, 0.0, -1.0]));
+        assert!(
+            cross(
+                &Matrix::column(vec![1.0, 0.0]),
+                &Matrix::column(vec![0.0, 1.0])
+            )
+            .is_err()
+        );
+        assert!(dot(&Matrix::identity(3), &x).is_err());
+    }
+}
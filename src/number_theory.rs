@@ -0,0 +1,326 @@
+//! Exact integer arithmetic for number theory built-ins.
+//!
+//! Arguments arrive as `f64` but are only accepted while they are integers
+//! below 2^53, so every value here is exact; intermediate products use `u128`.
+
+/// Largest integer magnitude an `f64` holds exactly.
+pub const MAX_EXACT: u64 = 1 << 53;
+
+pub fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+/// Least common multiple, or `None` on overflow.
+pub fn lcm(a: u64, b: u64) -> Option<u64> {
+    if a == 0 || b == 0 {
+        return Some(0);
+    }
+    (a / gcd(a, b)).checked_mul(b)
+}
+
+fn mul_mod(a: u64, b: u64, m: u64) -> u64 {
+    (a as u128 * b as u128 % m as u128) as u64
+}
+
+fn pow_mod(mut base: u64, mut exp: u64, m: u64) -> u64 {
+    let mut result = 1 % m;
+    base %= m;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = mul_mod(result, base, m);
+        }
+        base = mul_mod(base, base, m);
+        exp >>= 1;
+    }
+    result
+}
+
+/// Deterministic Miller–Rabin; these bases decide every 64-bit integer.
+pub fn is_prime(n: u64) -> bool {
+    const BASES: [u64; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+    if n < 2 {
+        return false;
+    }
+    for p in BASES {
+        if n.is_multiple_of(p) {
+            return n == p;
+        }
+    }
+    let (mut d, mut s) = (n - 1, 0);
+    while d % 2 == 0 {
+        d /= 2;
+        s += 1;
+    }
+    'bases: for a in BASES {
+        let mut x = pow_mod(a, d, n);
+        if x == 1 || x == n - 1 {
+            continue;
+        }
+        for _ in 1..s {
+            x = mul_mod(x, x, n);
+            if x == n - 1 {
+                continue 'bases;
+            }
+        }
+        return false;
+    }
+    true
+}
+
+/// Smallest prime greater than `n`.
+pub fn next_prime(n: u64) -> u64 {
+    let mut candidate = n + 1;
+    while !is_prime(candidate) {
+        candidate += 1;
+    }
+    candidate
+}
+
+/// Finds a non-trivial factor of a composite `n` with Pollard's rho.
+fn pollard_rho(n: u64) -> u64 {
+    for c in 1.. {
+        let f = |x: u64| (mul_mod(x, x, n) + c) % n;
+        let (mut x, mut y, mut d) = (2, 2, 1);
+        while d == 1 {
+            x = f(x);
+            y = f(f(y));
+            d = gcd(x.abs_diff(y), n);
+        }
+        if d != n {
+            return d;
+        }
+    }
+    unreachable!()
+}
+
+/// Prime factorization as ascending (prime, exponent) pairs; empty for 1.
+pub fn factorize(n: u64) -> Vec<(u64, u32)> {
+    fn split(n: u64, primes: &mut Vec<u64>) {
+        if n == 1 {
+            return;
+        }
+        if is_prime(n) {
+            primes.push(n);
+            return;
+        }
+        let d = pollard_rho(n);
+        split(d, primes);
+        split(n / d, primes);
+    }
+    let mut primes = Vec::new();
+    let mut rest = n;
+    for p in [2, 3, 5, 7, 11, 13] {
+        while rest.is_multiple_of(p) {
+            primes.push(p);
+            rest /= p;
+        }
+    }
+    split(rest, &mut primes);
+    primes.sort_unstable();
+    let mut factors: Vec<(u64, u32)> = Vec::new();
+    for p in primes {
+        match factors.last_mut() {
+            Some((q, k)) if *q == p => *k += 1,
+            _ => factors.push((p, 1)),
+        }
+    }
+    factors
+}
+
+/// Writes a factorization as `2^3 * 3 * 5`.
+pub fn format_factors(factors: &[(u64, u32)]) -> String {
+    factors
+        .iter()
+        .map(|&(p, k)| match k {
+            1 => p.to_string(),
+            _ => format!("{}^{}", p, k),
+        })
+        .collect::<Vec<_>>()
+        .join(" * ")
+}
+
+/// All positive divisors of `n`, ascending.
+pub fn divisors(n: u64) -> Vec<u64> {
+    let mut divisors = vec![1];
+    for (p, k) in factorize(n) {
+        let mut next = Vec::with_capacity(divisors.len() * (k as usize + 1));
+        for d in &divisors {
+            let mut power = 1;
+            for _ in 0..=k {
+                next.push(d * power);
+                power *= p;
+            }
+        }
+        divisors = next;
+    }
+    divisors.sort_unstable();
+    divisors
+}
+
+/// Euler's totient: how many of 1..=n are coprime to `n`.
+pub fn totient(n: u64) -> u64 {
+    factorize(n)
+        .into_iter()
+        .fold(n, |phi, (p, _)| phi / p * (p - 1))
+}
+
+/// Extended Euclid: (g, x, y) with a·x + b·y = g = gcd(a, b).
+fn extended_gcd(a: i128, b: i128) -> (i128, i128, i128) {
+    if b == 0 {
+        (a, 1, 0)
+    } else {
+        let (g, x, y) = extended_gcd(b, a % b);
+        (g, y, x - a / b * y)
+    }
+}
+
+/// Inverse of `a` modulo `m`.
+pub fn mod_inv(a: i64, m: u64) -> Result<u64, String> {
+    if m == 0 {
+        return Err("The modulus must be positive".to_string());
+    }
+    let a = (a as i128).rem_euclid(m as i128);
+    let (g, x, _) = extended_gcd(a, m as i128);
+    if g != 1 {
+        return Err(format!("{} has no inverse modulo {} (gcd is {})", a, m, g));
+    }
+    Ok(x.rem_euclid(m as i128) as u64)
+}
+
+/// `base^exp mod m`; a negative exponent uses the modular inverse.
+pub fn mod_pow(base: i64, exp: i64, m: u64) -> Result<u64, String> {
+    if m == 0 {
+        return Err("The modulus must be positive".to_string());
+    }
+    let base = if exp < 0 {
+        mod_inv(base, m)?
+    } else {
+        (base as i128).rem_euclid(m as i128) as u64
+    };
+    Ok(pow_mod(base, exp.unsigned_abs(), m))
+}
+
+/// Chinese remainder theorem: the smallest `x ≥ 0` with `x ≡ rᵢ (mod mᵢ)`, and the
+/// modulus of the combined congruence. Moduli need not be coprime.
+pub fn crt(residues: &[i64], moduli: &[u64]) -> Result<(u64, u64), String> {
+    if residues.len() != moduli.len() || residues.is_empty() {
+        return Err("Expected as many residues as moduli".to_string());
+    }
+    let (mut x, mut m) = (0i128, 1i128);
+    for (&r, &n) in residues.iter().zip(moduli) {
+        if n == 0 {
+            return Err("The moduli must be positive".to_string());
+        }
+        let (r, n) = ((r as i128).rem_euclid(n as i128), n as i128);
+        let (g, p, _) = extended_gcd(m, n);
+        if (r - x) % g != 0 {
+            return Err(format!(
+                "No solution: x ≡ {} (mod {}) contradicts the others",
+                r, n
+            ));
+        }
+        let step = n / g;
+        let t = ((r - x) / g % step * (p % step)).rem_euclid(step);
+        x += m * t;
+        m *= step;
+        if m > MAX_EXACT as i128 {
+            return Err("The combined modulus is too large to represent exactly".to_string());
+        }
+        x = x.rem_euclid(m);
+    }
+    Ok((x as u64, m as u64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_primes() {
+        let small: Vec<u64> = (0..30).filter(|&n| is_prime(n)).collect();
+        assert_eq!(small, vec![2, 3, 5, 7, 11, 13, 17, 19, 23, 29]);
+        // A Carmichael number and a strong pseudoprime to base 2
+        assert!(!is_prime(561));
+        assert!(!is_prime(2047));
+        assert!(is_prime(9007199254740881)); // largest prime below 2^53
+        assert_eq!(next_prime(13), 17);
+        assert_eq!(next_prime(0), 2);
+        assert_eq!(next_prime(2), 3);
+    }
+
+    #[test]
+    fn test_primality_edge_cases() {
+        assert!(!is_prime(0) && !is_prime(1));
+        assert!(is_prime(37) && is_prime(41));
+        // Squares of primes past the trial divisors
+        assert!(!is_prime(41 * 41));
+        assert!(!is_prime(9007199254740881u64.isqrt().pow(2)));
+        // Strong pseudoprimes to the bases 2, 3, 5 and 7 together
+        assert!(!is_prime(3215031751));
+        assert!(!is_prime(3825123056546413051));
+        // Products of squares overflow u64 without the u128 intermediate
+        assert!(is_prime(18446744073709551557)); // largest prime below 2^64
+        assert!(!is_prime(u64::MAX));
+        // The gap after 1327 is the first of 34
+        assert_eq!(next_prime(1327), 1361);
+    }
+
+    #[test]
+    fn test_factorization() {
+        assert_eq!(format_factors(&factorize(120)), "2^3 * 3 * 5");
+        assert_eq!(factorize(1), vec![]);
+        // Two large primes, beyond trial division
+        assert_eq!(
+            factorize(4294967291 * 2097143),
+            vec![(2097143, 1), (4294967291, 1)]
+        );
+        // A prime square, where rho can land on the whole number before a factor
+        assert_eq!(factorize(1000003 * 1000003), vec![(1000003, 2)]);
+        assert_eq!(factorize(MAX_EXACT), vec![(2, 53)]);
+        assert_eq!(factorize(9007199254740881), vec![(9007199254740881, 1)]);
+        assert_eq!(format_factors(&factorize(17)), "17");
+        assert_eq!(divisors(28), vec![1, 2, 4, 7, 14, 28]);
+        assert_eq!(divisors(36), vec![1, 2, 3, 4, 6, 9, 12, 18, 36]);
+        assert_eq!(divisors(1), vec![1]);
+        assert_eq!(divisors(1 << 20).len(), 21);
+        assert_eq!(totient(36), 12);
+        assert_eq!(totient(1), 1);
+        assert_eq!(totient(97), 96);
+        assert_eq!(totient(1 << 20), 1 << 19);
+    }
+
+    #[test]
+    fn test_modular() {
+        assert_eq!(gcd(48, 18), 6);
+        assert_eq!((gcd(0, 5), gcd(5, 0), gcd(0, 0)), (5, 5, 0));
+        assert_eq!(lcm(4, 6), Some(12));
+        assert_eq!(lcm(0, 6), Some(0));
+        assert_eq!(lcm(9007199254740881, 9007199254740847), None);
+        assert_eq!(mod_pow(4, 13, 497).unwrap(), 445);
+        assert_eq!(mod_pow(3, -1, 7).unwrap(), 5);
+        assert_eq!(mod_pow(-2, 3, 7).unwrap(), 6);
+        assert_eq!(mod_pow(5, 0, 7).unwrap(), 1);
+        // Everything is 0 modulo 1, even x^0
+        assert_eq!(mod_pow(5, 0, 1).unwrap(), 0);
+        assert_eq!(mod_pow(2, 100, 9007199254740881).unwrap(), 6614661952700527);
+        assert!(mod_pow(2, 3, 0).is_err());
+        assert!(mod_pow(3, -1, 9).is_err());
+        assert_eq!(mod_inv(-3, 7).unwrap(), 2);
+        assert_eq!(mod_inv(1, 1).unwrap(), 0);
+        assert!(mod_inv(6, 9).is_err());
+        assert!(mod_inv(0, 7).is_err());
+        assert!(mod_inv(3, 0).is_err());
+        assert_eq!(crt(&[2, 3, 2], &[3, 5, 7]).unwrap(), (23, 105));
+        assert_eq!(crt(&[1, 3], &[4, 6]).unwrap(), (9, 12));
+        assert!(crt(&[1, 2], &[4, 6]).is_err());
+        assert_eq!(crt(&[-1], &[7]).unwrap(), (6, 7));
+        assert_eq!(crt(&[-1, -1], &[4, 9]).unwrap(), (35, 36));
+        assert!(crt(&[], &[]).is_err());
+        assert!(crt(&[1, 2], &[3]).is_err());
+        assert!(crt(&[1], &[0]).is_err());
+        assert!(crt(&[1, 1], &[9007199254740881, 9007199254740847]).is_err());
+    }
+}
@@ -1,6 +1,16 @@
+use crate::builtins::{self, Builtin};
+use crate::datetime::{self, Span};
+use crate::interval;
+use crate::settings::AngleMode;
+use crate::units::Unit;
+use chrono::NaiveDateTime;
+
 #[derive(Debug, PartialEq, Clone)]
 pub enum Token {
     Number(f64),
+    /// A number literal no double holds exactly, such as `0.1`; interval mode
+    /// encloses it rather than taking the nearest double at face value.
+    Rounded(f64),
     Add,
     Subtract,
     Multiply,
@@ -10,29 +20,88 @@ pub enum Token {
     UnaryMinus,
     Modulo,
     Power,
-    FunctionCall(String, Vec<Token>), 
+    PlusMinus,
+    Variable(String),
+    FunctionCall(String, Vec<Vec<Token>>),
+    Bracket(Vec<Vec<Token>>),
+    Text(String),
+    /// A number with an angle unit suffix, such as `30deg`.
+    Angle(f64, AngleMode),
+    /// A date literal, `2026-10-16` or `2026-10-16T09:30`.
+    Date(NaiveDateTime),
+    /// A number followed by a time unit, such as `30 days`.
+    Duration(Span),
+    /// A number with a unit of measure, such as `5 km` or `9.81 m/s^2`.
+    Quantity(f64, Unit),
+    /// `const.<name>`, a named constant that variables cannot hide.
+    Constant(String),
+    /// `to <unit>` or `in <unit>` at the end of an expression.
+    Convert(Unit),
+    /// A postfix `!`, the factorial.
+    Factorial,
+    /// A postfix `%`, as in `200 + 15%`.
+    Percent,
+    /// `of`, as in `15% of 80` or `50 of 200`.
+    Of,
+    /// `as %` (or `to %`, `in %`) at the end of an expression.
+    AsPercent,
+    /// `=`, between the sides of an equation given to `solve`.
+    Equals,
 }
 
+/// Words with a meaning of their own after a number, never read as units.
+const KEYWORDS: &[&str] = &["to", "in", "as", "of"];
+
 pub struct Lexer<'a> {
     chars: std::iter::Peekable<std::str::Chars<'a>>,
+    /// Off inside formulas, where `3k` is 3 times a variable `k` rather than 3000.
+    si_suffixes: bool,
 }
 
 impl<'a> Lexer<'a> {
     pub fn new(input: &'a str) -> Self {
         Lexer {
             chars: input.chars().peekable(),
+            si_suffixes: true,
+        }
+    }
+
+    /// A lexer for a formula in free variables, which reads no SI suffixes.
+    pub fn formula(input: &'a str) -> Self {
+        Lexer {
+            si_suffixes: false,
+            ..Lexer::new(input)
         }
     }
 
     pub fn tokenize(&mut self) -> Result<Vec<Token>, String> {
         let mut tokens = Vec::new();
+        // Whether whitespace came right before the current character
+        let mut spaced = false;
         while let Some(c) = self.chars.peek() {
+            let after_space = std::mem::replace(&mut spaced, false);
             match c {
                 ' ' | '\t' | '\n' => {
                     self.chars.next();
+                    spaced = true;
                 }
                 '+' => {
-                    tokens.push(Token::Add);
+                    self.chars.next();
+                    // ` +- ` with a space on each side is `±`; glued, as in `2+-4`,
+                    // it adds a negative number
+                    let mut ahead = self.chars.clone();
+                    if after_space
+                        && ahead.next() == Some('-')
+                        && matches!(ahead.peek(), Some(' ' | '\t' | '\n'))
+                    {
+                        self.chars.next();
+                        tokens.push(Token::PlusMinus);
+                    } else {
+                        tokens.push(Token::Add);
+                    }
+                }
+                '±' => {
+                    tokens.push(Token::PlusMinus);
                     self.chars.next();
                 }
                 '-' => {
@@ -49,6 +118,9 @@ impl<'a> Lexer<'a> {
                                 | Some(Token::UnaryMinus)
                                 | Some(Token::Modulo)
                                 | Some(Token::Power)
+                                | Some(Token::PlusMinus)
+                                | Some(Token::Of)
+                                | Some(Token::Equals)
                         );
 
                     if is_unary {
@@ -66,13 +138,25 @@ impl<'a> Lexer<'a> {
                     self.chars.next();
                 }
                 '%' => {
-                    tokens.push(Token::Modulo);
+                    self.chars.next();
+                    if self.operand_follows(after_space)? {
+                        tokens.push(Token::Modulo);
+                    } else {
+                        tokens.push(Token::Percent);
+                    }
+                }
+                '!' => {
+                    tokens.push(Token::Factorial);
                     self.chars.next();
                 }
                 '^' => {
                     tokens.push(Token::Power);
                     self.chars.next();
                 }
+                '=' => {
+                    tokens.push(Token::Equals);
+                    self.chars.next();
+                }
                 '(' => {
                     tokens.push(Token::LeftParen);
                     self.chars.next();
@@ -81,25 +165,75 @@ impl<'a> Lexer<'a> {
                     tokens.push(Token::RightParen);
                     self.chars.next();
                 }
+                '"' => {
+                    self.chars.next();
+                    let text = self.parse_text()?;
+                    tokens.push(Token::Text(text));
+                }
+                '[' => {
+                    self.chars.next();
+                    let items = self.parse_args(']', self.si_suffixes)?;
+                    tokens.push(Token::Bracket(items));
+                }
                 'a'..='z' | 'A'..='Z' | '_' => {
                     let name = self.parse_identifier();
                     match name.to_lowercase().as_str() {
-                        "pi" => tokens.push(Token::Number(std::f64::consts::PI)),
-                        "e" => tokens.push(Token::Number(std::f64::consts::E)),
+                        "const" if self.chars.peek() == Some(&'.') => {
+                            self.chars.next();
+                            tokens.push(Token::Constant(self.parse_identifier()));
+                        }
+                        "to" | "in" | "as" => {
+                            let target: String = self.chars.by_ref().collect();
+                            if target.trim() == "%" {
+                                tokens.push(Token::AsPercent);
+                            } else if name.eq_ignore_ascii_case("as") {
+                                return Err(format!(
+                                    "Expected % after as, got '{}'",
+                                    target.trim()
+                                ));
+                            } else {
+                                tokens.push(Token::Convert(Unit::parse(&target)?));
+                            }
+                        }
+                        "of" => tokens.push(Token::Of),
+                        "solve" if tokens.is_empty() && self.chars.peek() == Some(&' ') => {
+                            match self.parse_solve_command()? {
+                                Some(call) => tokens.push(call),
+                                None => tokens.push(Token::Variable(name)),
+                            }
+                        }
                         _ => {
                             if let Some(&'(') = self.chars.peek() {
-                                self.chars.next(); 
-                                let args = self.parse_function_args()?;
+                                self.chars.next();
+                                let formulas =
+                                    builtins::lookup(&name).is_some_and(Builtin::takes_formulas);
+                                let args = self.parse_args(')', self.si_suffixes && !formulas)?;
                                 tokens.push(Token::FunctionCall(name, args));
                             } else {
-                                return Err(format!("Unexpected identifier: {}", name));
+                                tokens.push(Token::Variable(name));
                             }
                         }
                     }
                 }
                 '0'..='9' | '.' => {
-                    let num = self.parse_number()?;
-                    tokens.push(Token::Number(num));
+                    if let Some(date) = self.parse_date()? {
+                        tokens.push(Token::Date(date));
+                        continue;
+                    }
+                    let (num, exact) = self.parse_number()?;
+                    if let Some(unit) = self.parse_angle_suffix() {
+                        tokens.push(Token::Angle(num, unit));
+                    } else if let Some(span) = self.parse_duration_suffix(num)? {
+                        tokens.push(Token::Duration(span));
+                    } else if let Some(unit) = self.parse_unit_suffix() {
+                        tokens.push(Token::Quantity(num, unit));
+                    } else {
+                        tokens.push(if exact {
+                            Token::Number(num)
+                        } else {
+                            Token::Rounded(num)
+                        });
+                    }
                 }
                 _ => {
                     return Err(format!("Unexpected character: {}", c));
@@ -109,6 +243,60 @@ impl<'a> Lexer<'a> {
         Ok(tokens)
     }
 
+    /// Decides what a `%` means: modulo when an operand follows (`7 % 3`,
+    /// `x % -2`), otherwise a percentage (`15%`, `200 + 15%`, `15% of 80`).
+    /// A `%` written against its number and followed by a space is always a
+    /// percentage, so `200 + 15% -5` subtracts 5. Written against both, as in
+    /// `15%-5`, a signed operand could go either way and is refused.
+    fn operand_follows(&self, spaced_before: bool) -> Result<bool, String> {
+        let mut ahead = self.chars.clone();
+        if !spaced_before && ahead.peek().is_some_and(|c| c.is_whitespace()) {
+            return Ok(false);
+        }
+        while ahead.next_if(|c| c.is_whitespace()).is_some() {}
+        Ok(match ahead.peek() {
+            Some('0'..='9' | '.' | '(' | '[' | '"') => true,
+            // A minus sign attached to what follows is a unary minus
+            Some('-') => {
+                ahead.next();
+                let signed = ahead.peek().is_some_and(|c| !c.is_whitespace());
+                if signed && !spaced_before {
+                    return Err(
+                        "Ambiguous %-: write 15% - 5 for a percentage or 15 % -5 for modulo"
+                            .to_string(),
+                    );
+                }
+                signed
+            }
+            Some(c) if c.is_alphabetic() || *c == '_' => {
+                let word: String = ahead
+                    .take_while(|c| c.is_ascii_alphanumeric() || *c == '_')
+                    .collect();
+                !KEYWORDS.contains(&word.to_lowercase().as_str())
+            }
+            _ => false,
+        })
+    }
+
+    /// Reads the rest of `solve 3x + 5 = 20 for x` as the call
+    /// `solve(3x + 5 = 20, x)`, or nothing when `for <variable>` is missing.
+    fn parse_solve_command(&mut self) -> Result<Option<Token>, String> {
+        let rest: String = self.chars.clone().collect();
+        let Some((equation, var)) = rest.rsplit_once(" for ") else {
+            return Ok(None);
+        };
+        let var = Lexer::formula(var).tokenize()?;
+        if !matches!(var.as_slice(), [Token::Variable(_)]) {
+            return Ok(None);
+        }
+        let equation = Lexer::formula(equation).tokenize()?;
+        self.chars.by_ref().for_each(drop);
+        Ok(Some(Token::FunctionCall(
+            "solve".to_string(),
+            vec![equation, var],
+        )))
+    }
+
     fn parse_identifier(&mut self) -> String {
         let mut ident = String::new();
         while let Some(&c) = self.chars.peek() {
@@ -122,58 +310,199 @@ impl<'a> Lexer<'a> {
         ident
     }
 
-    fn parse_function_args(&mut self) -> Result<Vec<Token>, String> {
+    /// Splits a comma-separated list up to the matching `close` character,
+    /// tokenizing each element separately.
+    fn parse_args(&mut self, close: char, si_suffixes: bool) -> Result<Vec<Vec<Token>>, String> {
         let mut args = Vec::new();
         let mut current_arg = String::new();
-        let mut paren_count = 0;
+        let mut depth = 0;
+        let mut in_text = false;
 
-        while let Some(&c) = self.chars.peek() {
+        for c in self.chars.by_ref() {
             match c {
-                ')' => {
-                    if paren_count == 0 {
-                        self.chars.next();
-                        if !current_arg.is_empty() {
-                            let mut lexer = Lexer::new(&current_arg);
-                            let tokens = lexer.tokenize()?;
-                            args.extend(tokens);
-                        }
-                        return Ok(args);
-                    } else {
-                        paren_count -= 1;
-                        current_arg.push(c);
-                        self.chars.next();
-                    }
+                '"' => {
+                    in_text = !in_text;
+                    current_arg.push(c);
                 }
-                ',' => {
-                    if paren_count == 0 {
-                        self.chars.next(); 
-                        if !current_arg.is_empty() {
-                            let mut lexer = Lexer::new(&current_arg);
-                            let tokens = lexer.tokenize()?;
-                            args.extend(tokens);
-                            current_arg.clear();
-                        }
-                    } else {
-                        current_arg.push(c);
-                        self.chars.next();
-                    }
+                _ if in_text => current_arg.push(c),
+                c if c == close && depth == 0 => {
+                    Self::push_arg(&mut args, &current_arg, si_suffixes)?;
+                    return Ok(args);
                 }
-                '(' => {
-                    paren_count += 1;
+                ',' if depth == 0 => {
+                    Self::push_arg(&mut args, &current_arg, si_suffixes)?;
+                    current_arg.clear();
+                }
+                '(' | '[' => {
+                    depth += 1;
                     current_arg.push(c);
-                    self.chars.next();
                 }
-                _ => {
+                ')' | ']' => {
+                    depth -= 1;
                     current_arg.push(c);
-                    self.chars.next();
                 }
+                _ => current_arg.push(c),
+            }
+        }
+
+        if close == ')' {
+            Err("Unclosed function arguments".to_string())
+        } else {
+            Err("Unclosed bracket".to_string())
+        }
+    }
+
+    fn parse_text(&mut self) -> Result<String, String> {
+        let mut text = String::new();
+        for c in self.chars.by_ref() {
+            if c == '"' {
+                return Ok(text);
+            }
+            text.push(c);
+        }
+        Err("Unclosed string".to_string())
+    }
+
+    fn push_arg(args: &mut Vec<Vec<Token>>, arg: &str, si_suffixes: bool) -> Result<(), String> {
+        if !arg.trim().is_empty() {
+            let mut lexer = if si_suffixes {
+                Lexer::new(arg)
+            } else {
+                Lexer::formula(arg)
+            };
+            args.push(lexer.tokenize()?);
+        }
+        Ok(())
+    }
+
+    /// Consumes a unit directly after a number (`30deg`, `1.2rad`, `50grad`, `45°`).
+    fn parse_angle_suffix(&mut self) -> Option<AngleMode> {
+        if self.chars.peek() == Some(&'°') {
+            let mut ahead = self.chars.clone();
+            ahead.next();
+            // `°C` and `°F` are temperatures
+            if matches!(ahead.peek(), Some('C' | 'F')) {
+                return None;
+            }
+            self.chars = ahead;
+            return Some(AngleMode::Degrees);
+        }
+        let mut ahead = self.chars.clone();
+        let mut suffix = String::new();
+        while let Some(c) = ahead.next_if(|c| c.is_ascii_alphanumeric() || *c == '_') {
+            suffix.push(c);
+        }
+        let unit = match suffix.as_str() {
+            "deg" => AngleMode::Degrees,
+            "rad" => AngleMode::Radians,
+            "grad" => AngleMode::Gradians,
+            _ => return None,
+        };
+        self.chars = ahead;
+        Some(unit)
+    }
+
+    /// Consumes a date literal `YYYY-MM-DD`, optionally followed by `Thh:mm[:ss]`.
+    /// Anything else, including `2026-10-1`, is left for the number parser.
+    fn parse_date(&mut self) -> Result<Option<NaiveDateTime>, String> {
+        let mut ahead = self.chars.clone();
+        let mut text = String::new();
+        for expected in "dddd-dd-dd".chars() {
+            match ahead.next() {
+                Some(c) if expected == 'd' && c.is_ascii_digit() => text.push(c),
+                Some('-') if expected == '-' => text.push('-'),
+                _ => return Ok(None),
+            }
+        }
+        if ahead.peek().is_some_and(|c| c.is_ascii_digit()) {
+            return Ok(None);
+        }
+        if ahead.peek() == Some(&'T') {
+            text.push('T');
+            ahead.next();
+            while let Some(c) = ahead.next_if(|c| c.is_ascii_digit() || *c == ':') {
+                text.push(c);
+            }
+        }
+        self.chars = ahead;
+        datetime::parse_date(&text).map(Some)
+    }
+
+    /// Consumes a time unit after a number, separated by optional spaces: `30 days`.
+    fn parse_duration_suffix(&mut self, amount: f64) -> Result<Option<Span>, String> {
+        let mut ahead = self.chars.clone();
+        while ahead.next_if(|c| *c == ' ').is_some() {}
+        let mut unit = String::new();
+        while let Some(c) = ahead.next_if(|c| c.is_ascii_alphabetic()) {
+            unit.push(c);
+        }
+        if ahead.peek() == Some(&'(') {
+            return Ok(None);
+        }
+        match Span::from_unit(amount, &unit) {
+            Some(span) => {
+                self.chars = ahead;
+                span.map(Some)
             }
+            None => Ok(None),
         }
+    }
 
-        Err("Unclosed function arguments".to_string())
+    /// Consumes a unit after a number, separated by optional spaces: `5 km`,
+    /// `9.81 m/s^2`. Operators inside the unit are written without spaces.
+    fn parse_unit_suffix(&mut self) -> Option<Unit> {
+        let mut ahead = self.chars.clone();
+        while ahead.next_if(|c| *c == ' ').is_some() {}
+        let mut text = String::new();
+        let mut accepted = None;
+        loop {
+            let mut term = String::new();
+            while let Some(c) = ahead.next_if(|c| c.is_alphabetic() || *c == '°') {
+                term.push(c);
+            }
+            if Unit::lookup(&term).is_none()
+                || ahead.peek() == Some(&'(')
+                || KEYWORDS.contains(&term.as_str())
+            {
+                break;
+            }
+            if ahead.peek() == Some(&'^') {
+                let mut power = ahead.clone();
+                power.next();
+                let mut exponent = power
+                    .next_if(|c| *c == '-')
+                    .map(String::from)
+                    .unwrap_or_default();
+                while let Some(c) = power.next_if(|c| c.is_ascii_digit()) {
+                    exponent.push(c);
+                }
+                if exponent.trim_start_matches('-').is_empty() {
+                    // `m^(2)`: leave the power to the expression
+                    text.push_str(&term);
+                    accepted = Some((ahead.clone(), text.clone()));
+                    break;
+                }
+                term = format!("{}^{}", term, exponent);
+                ahead = power;
+            }
+            text.push_str(&term);
+            accepted = Some((ahead.clone(), text.clone()));
+            match ahead.next_if(|c| *c == '*' || *c == '/') {
+                Some(op) => text.push(op),
+                None => break,
+            }
+        }
+        let (rest, text) = accepted?;
+        let unit = Unit::parse(&text).ok()?;
+        self.chars = rest;
+        Some(unit)
     }
 
-    fn parse_number(&mut self) -> Result<f64, String> {
+    /// Reads a number with an optional exponent (`1e-9`, `2E3`) and SI prefix,
+    /// and whether the double read is exactly the number written. The prefix
+    /// shifts the decimal exponent before the conversion to binary, so `10µ` is
+    /// the double nearest to 0.00001.
+    fn parse_number(&mut self) -> Result<(f64, bool), String> {
         let mut num_str = String::new();
         while let Some(&c) = self.chars.peek() {
             if c.is_ascii_digit() || c == '.' {
@@ -186,9 +515,68 @@ impl<'a> Lexer<'a> {
         if num_str.is_empty() {
             return Err("Expected number".to_string());
         }
-        num_str
-            .parse::<f64>()
-            .map_err(|_| "Invalid number format".to_string())
+        let mut exponent = self.parse_exponent()?;
+        if self.si_suffixes {
+            exponent = exponent.saturating_add(self.parse_si_suffix());
+        }
+        let text = format!("{}e{}", num_str, exponent);
+        match text.parse::<f64>() {
+            Ok(num) if num.is_finite() => {
+                Ok((num, interval::is_exact_literal(&num_str, exponent, num)))
+            }
+            Ok(_) => Err(format!("Number out of range: {}", text)),
+            Err(_) => Err("Invalid number format".to_string()),
+        }
+    }
+
+    /// Consumes `e` or `E` and a signed power of ten, if digits follow.
+    fn parse_exponent(&mut self) -> Result<i32, String> {
+        let mut ahead = self.chars.clone();
+        if ahead.next_if(|c| matches!(c, 'e' | 'E')).is_none() {
+            return Ok(0);
+        }
+        let mut digits: String = ahead
+            .next_if(|c| matches!(c, '+' | '-'))
+            .map(String::from)
+            .unwrap_or_default();
+        let signed = digits.len();
+        while let Some(c) = ahead.next_if(char::is_ascii_digit) {
+            digits.push(c);
+        }
+        if digits.len() == signed {
+            return Ok(0);
+        }
+        self.chars = ahead;
+        digits
+            .parse()
+            .map_err(|_| format!("Exponent out of range: e{}", digits))
+    }
+
+    /// Consumes an SI prefix written directly after a number (`4.7k`, `220n`, `10µ`)
+    /// and returns its power of ten. A letter that continues into a unit, as in
+    /// `5km`, is left alone, and `m` always means metres.
+    fn parse_si_suffix(&mut self) -> i32 {
+        let power = match self.chars.peek() {
+            Some('T') => 12,
+            Some('G') => 9,
+            Some('M') => 6,
+            Some('k') => 3,
+            Some('µ' | 'u') => -6,
+            Some('n') => -9,
+            Some('p') => -12,
+            Some('f') => -15,
+            _ => return 0,
+        };
+        let mut ahead = self.chars.clone();
+        ahead.next();
+        if ahead
+            .peek()
+            .is_some_and(|c| c.is_alphanumeric() || *c == '_' || *c == '(' || *c == '°')
+        {
+            return 0;
+        }
+        self.chars = ahead;
+        power
     }
 }
 
@@ -249,6 +637,43 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_equations() {
+        let tokens = Lexer::new("2x = -4").tokenize().unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Number(2.0),
+                Token::Variable("x".to_string()),
+                Token::Equals,
+                Token::UnaryMinus,
+                Token::Number(4.0)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_solve_command() {
+        let tokenize = |input: &str| Lexer::new(input).tokenize().unwrap();
+        assert_eq!(
+            tokenize("solve 2n + 5 = 20 for n"),
+            tokenize("solve(2n + 5 = 20, n)")
+        );
+        // Only at the start, and only with a variable to solve for
+        assert_eq!(
+            tokenize("solve 2x = 4")[0],
+            Token::Variable("solve".to_string())
+        );
+        assert_eq!(
+            tokenize("solve 2x = 4 for 3")[0],
+            Token::Variable("solve".to_string())
+        );
+        assert_eq!(
+            tokenize("1 + solve")[2],
+            Token::Variable("solve".to_string())
+        );
+    }
+
     #[test]
     fn test_modulo_and_power() {
         // 取模运算
@@ -286,4 +711,213 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn test_plus_minus() {
+        let mut lexer = Lexer::new("2 ± 0.1");
+        assert_eq!(
+            lexer.tokenize().unwrap(),
+            vec![Token::Number(2.0), Token::PlusMinus, Token::Rounded(0.1)]
+        );
+
+        let mut lexer = Lexer::new("2 +- 0.1");
+        assert_eq!(
+            lexer.tokenize().unwrap(),
+            vec![Token::Number(2.0), Token::PlusMinus, Token::Rounded(0.1)]
+        );
+
+        // Apart or glued, the two signs add a negative number
+        for input in ["2 + -4", "2+-4", "2 +-4"] {
+            let mut lexer = Lexer::new(input);
+            assert_eq!(
+                lexer.tokenize().unwrap(),
+                vec![
+                    Token::Number(2.0),
+                    Token::Add,
+                    Token::UnaryMinus,
+                    Token::Number(4.0)
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn test_angle_suffixes() {
+        let mut lexer = Lexer::new("30deg + 1.2rad - 50grad + 45°");
+        assert_eq!(
+            lexer.tokenize().unwrap(),
+            vec![
+                Token::Angle(30.0, AngleMode::Degrees),
+                Token::Add,
+                Token::Angle(1.2, AngleMode::Radians),
+                Token::Subtract,
+                Token::Angle(50.0, AngleMode::Gradians),
+                Token::Add,
+                Token::Angle(45.0, AngleMode::Degrees)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dates_and_durations() {
+        let mut lexer = Lexer::new("2026-10-16 + 30 days - 2026-10-1");
+        let tokens = lexer.tokenize().unwrap();
+        assert_eq!(
+            tokens[0],
+            Token::Date(datetime::parse_date("2026-10-16").unwrap())
+        );
+        assert_eq!(
+            tokens[2],
+            Token::Duration(Span::from_unit(30.0, "days").unwrap().unwrap())
+        );
+        // Not a full date, so plain subtraction
+        assert_eq!(
+            tokens[3..],
+            [
+                Token::Subtract,
+                Token::Number(2026.0),
+                Token::Subtract,
+                Token::Number(10.0),
+                Token::Subtract,
+                Token::Number(1.0)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_si_suffixes() {
+        let mut lexer = Lexer::new("4.7k + 220n * 3.3M - 10µ");
+        assert_eq!(
+            lexer.tokenize().unwrap(),
+            vec![
+                Token::Number(4700.0),
+                Token::Add,
+                Token::Rounded(2.2e-7),
+                Token::Multiply,
+                Token::Number(3.3e6),
+                Token::Subtract,
+                Token::Rounded(1e-5)
+            ]
+        );
+
+        // Formulas, including the arguments of diff() and the like, read none
+        assert_eq!(
+            Lexer::formula("3k").tokenize().unwrap(),
+            vec![Token::Number(3.0), Token::Variable("k".to_string())]
+        );
+        let mut lexer = Lexer::new("diff(3k, k) + 1k");
+        let tokens = lexer.tokenize().unwrap();
+        let Token::FunctionCall(_, args) = &tokens[0] else {
+            panic!("expected a call, got {:?}", tokens[0]);
+        };
+        assert_eq!(args[0][1], Token::Variable("k".to_string()));
+        assert_eq!(tokens[2], Token::Number(1000.0));
+
+        // A prefix that continues into a unit stays part of the unit
+        let mut lexer = Lexer::new("4.7kΩ + 5m");
+        assert_eq!(
+            lexer.tokenize().unwrap(),
+            vec![
+                Token::Quantity(4.7, Unit::parse("kΩ").unwrap()),
+                Token::Add,
+                Token::Quantity(5.0, Unit::parse("m").unwrap())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_exponents() {
+        let tokenize = |input: &str| Lexer::new(input).tokenize().unwrap();
+        assert_eq!(tokenize("1e-9"), vec![Token::Rounded(1e-9)]);
+        assert_eq!(tokenize("2E3 + 1.5e+2"), tokenize("2000 + 150"));
+        assert_eq!(tokenize("4.7e3k"), vec![Token::Number(4.7e6)]);
+        // Without digits the `e` is not an exponent
+        assert_eq!(
+            tokenize("2 e"),
+            vec![Token::Number(2.0), Token::Variable("e".to_string())]
+        );
+        assert!(Lexer::new("2e").tokenize().is_ok());
+        // Literals past 2^53 may not be doubles either
+        assert_eq!(
+            tokenize("9007199254740992"),
+            vec![Token::Number(2f64.powi(53))]
+        );
+        assert_eq!(
+            tokenize("9007199254740993"),
+            vec![Token::Rounded(2f64.powi(53))]
+        );
+        assert_eq!(tokenize("0.50000000000000001"), vec![Token::Rounded(0.5)]);
+        assert_eq!(tokenize("1e-400"), vec![Token::Rounded(0.0)]);
+        assert!(Lexer::new("1e400").tokenize().is_err());
+        assert!(Lexer::new("1e99999999999").tokenize().is_err());
+    }
+
+    #[test]
+    fn test_units_and_conversion() {
+        let mut lexer = Lexer::new("9.81 m/s^2 * 3kg to N");
+        let tokens = lexer.tokenize().unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Quantity(9.81, Unit::parse("m/s^2").unwrap()),
+                Token::Multiply,
+                Token::Quantity(3.0, Unit::parse("kg").unwrap()),
+                Token::Convert(Unit::parse("N").unwrap())
+            ]
+        );
+
+        // `20°C` is a temperature, `20°` an angle
+        let mut lexer = Lexer::new("20°C + 20°");
+        let tokens = lexer.tokenize().unwrap();
+        assert_eq!(tokens[0], Token::Quantity(20.0, Unit::parse("°C").unwrap()));
+        assert_eq!(tokens[2], Token::Angle(20.0, AngleMode::Degrees));
+    }
+
+    #[test]
+    fn test_percent_or_modulo() {
+        let tokenize = |input: &str| Lexer::new(input).tokenize().unwrap();
+        // An operand after `%` makes it modulo
+        assert_eq!(tokenize("7 % 3")[1], Token::Modulo);
+        assert_eq!(tokenize("x % (y + 1)")[1], Token::Modulo);
+        assert_eq!(tokenize("10 % -3")[1], Token::Modulo);
+        assert_eq!(tokenize("10%3")[1], Token::Modulo);
+        // Against both numbers a signed operand is ambiguous
+        for input in ["10%-3", "200 + 15%-5"] {
+            assert!(Lexer::new(input).tokenize().is_err(), "{}", input);
+        }
+        // Otherwise it is a percentage
+        assert_eq!(
+            tokenize("200 + 15% - 5"),
+            vec![
+                Token::Number(200.0),
+                Token::Add,
+                Token::Number(15.0),
+                Token::Percent,
+                Token::Subtract,
+                Token::Number(5.0)
+            ]
+        );
+        assert_eq!(
+            tokenize("200 + 15% -5"),
+            vec![
+                Token::Number(200.0),
+                Token::Add,
+                Token::Number(15.0),
+                Token::Percent,
+                Token::Subtract,
+                Token::Number(5.0)
+            ]
+        );
+        assert_eq!(
+            tokenize("15% of -80"),
+            vec![
+                Token::Number(15.0),
+                Token::Percent,
+                Token::Of,
+                Token::UnaryMinus,
+                Token::Number(80.0)
+            ]
+        );
+        assert_eq!(tokenize("50 of 200 as %")[3], Token::AsPercent);
+    }
 }
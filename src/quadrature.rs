@@ -0,0 +1,352 @@
+//! Adaptive numerical integration.
+//!
+//! Each subinterval is integrated with the 15-point Kronrod rule and the 7-point
+//! Gauss rule nested in it; their difference estimates the error, and the
+//! subinterval with the largest error is halved until the total is small enough.
+//! Infinite ranges are mapped onto finite ones first.
+
+use std::fmt;
+
+/// Kronrod nodes on [-1, 1], the positive half; odd entries are the Gauss nodes.
+const NODES: [f64; 8] = [
+    0.9914553711208126,
+    0.9491079123427585,
+    0.8648644233597691,
+    0.7415311855993945,
+    0.5860872354676911,
+    0.4058451513773972,
+    0.20778495500789848,
+    0.0,
+];
+
+const KRONROD_WEIGHTS: [f64; 8] = [
+    0.022935322010529224,
+    0.06309209262997856,
+    0.10479001032225019,
+    0.14065325971552592,
+    0.1690047266392679,
+    0.19035057806478542,
+    0.20443294007529889,
+    0.20948214108472782,
+];
+
+/// Weights of the Gauss nodes `NODES[1]`, `NODES[3]`, `NODES[5]` and `NODES[7]`.
+const GAUSS_WEIGHTS: [f64; 4] = [
+    0.1294849661688697,
+    0.27970539148927664,
+    0.3818300505051189,
+    0.4179591836734694,
+];
+
+/// Subdivisions allowed before giving up.
+const MAX_INTERVALS: usize = 1000;
+
+/// Requested accuracy, relative to the integral's magnitude.
+const RELATIVE_TOLERANCE: f64 = 1e-10;
+const ABSOLUTE_TOLERANCE: f64 = 1e-12;
+
+#[derive(Debug)]
+pub enum IntegrationError {
+    /// The integrand was infinite or undefined at this point.
+    Undefined(f64),
+    /// The error estimate stayed above the tolerance.
+    NotConverged {
+        value: f64,
+        error: f64,
+        intervals: usize,
+    },
+}
+
+impl fmt::Display for IntegrationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IntegrationError::Undefined(x) => {
+                write!(f, "the integrand is not finite at x = {}", x)
+            }
+            IntegrationError::NotConverged {
+                value,
+                error,
+                intervals,
+            } => write!(
+                f,
+                "the estimate {} is uncertain by about {} after {} subintervals",
+                value, error, intervals
+            ),
+        }
+    }
+}
+
+struct Piece {
+    a: f64,
+    b: f64,
+    value: f64,
+    error: f64,
+}
+
+/// Gauss–Kronrod on [a, b], with the error estimate QUADPACK uses.
+fn kronrod(f: &mut impl FnMut(f64) -> f64, a: f64, b: f64) -> Result<Piece, IntegrationError> {
+    let center = 0.5 * (a + b);
+    let half = 0.5 * (b - a);
+    let mut values = [0.0; 15];
+    for (i, node) in NODES.iter().enumerate() {
+        let points = if *node == 0.0 {
+            vec![center]
+        } else {
+            vec![center - half * node, center + half * node]
+        };
+        for (j, x) in points.into_iter().enumerate() {
+            let y = f(x);
+            if !y.is_finite() {
+                return Err(IntegrationError::Undefined(x));
+            }
+            values[2 * i + j] = y;
+        }
+    }
+    // values[2i] and values[2i + 1] are the two points at NODES[i]; values[14] the center
+    let pair = |i: usize| values[2 * i] + if i < 7 { values[2 * i + 1] } else { 0.0 };
+    let kronrod: f64 = (0..8).map(|i| KRONROD_WEIGHTS[i] * pair(i)).sum();
+    let gauss: f64 = (0..4).map(|i| GAUSS_WEIGHTS[i] * pair(2 * i + 1)).sum();
+    let mean = kronrod / 2.0;
+    let spread: f64 = (0..8)
+        .map(|i| {
+            let deviation = (values[2 * i] - mean).abs()
+                + if i < 7 {
+                    (values[2 * i + 1] - mean).abs()
+                } else {
+                    0.0
+                };
+            KRONROD_WEIGHTS[i] * deviation
+        })
+        .sum::<f64>()
+        * half.abs();
+    let mut error = ((kronrod - gauss) * half).abs();
+    if spread != 0.0 && error != 0.0 {
+        error = spread * (200.0 * error / spread).powf(1.5).min(1.0);
+    }
+    Ok(Piece {
+        a,
+        b,
+        value: kronrod * half,
+        error,
+    })
+}
+
+/// Integrates `f` over a finite range by adaptive bisection.
+fn adaptive(mut f: impl FnMut(f64) -> f64, a: f64, b: f64) -> Result<f64, IntegrationError> {
+    let mut pieces = vec![kronrod(&mut f, a, b)?];
+    loop {
+        let value: f64 = pieces.iter().map(|p| p.value).sum();
+        let error: f64 = pieces.iter().map(|p| p.error).sum();
+        if error <= ABSOLUTE_TOLERANCE.max(RELATIVE_TOLERANCE * value.abs()) {
+            return Ok(value);
+        }
+        let (worst, _) = pieces
+            .iter()
+            .enumerate()
+            .max_by(|(_, p), (_, q)| p.error.total_cmp(&q.error))
+            .unwrap();
+        let Piece { a, b, .. } = pieces[worst];
+        let middle = 0.5 * (a + b);
+        // Out of subdivisions, or halving can no longer separate floats
+        if pieces.len() >= MAX_INTERVALS || middle <= a || middle >= b {
+            return Err(IntegrationError::NotConverged {
+                value,
+                error,
+                intervals: pieces.len(),
+            });
+        }
+        pieces[worst] = kronrod(&mut f, a, middle)?;
+        pieces.push(kronrod(&mut f, middle, b)?);
+    }
+}
+
+/// The integral of `f` from `a` to `b`; either bound may be infinite.
+pub fn integrate(f: impl FnMut(f64) -> f64, a: f64, b: f64) -> Result<f64, IntegrationError> {
+    if a > b {
+        return integrate(f, b, a).map(|value| -value);
+    }
+    if a == b {
+        return Ok(0.0);
+    }
+    // The substitutions below keep t inside (0, 1) or (-1, 1), where the rule
+    // never evaluates the endpoints that map to infinity
+    match (a.is_finite(), b.is_finite()) {
+        (true, true) => adaptive(f, a, b),
+        (true, false) => substitute(
+            f,
+            |t| a + t / (1.0 - t),
+            |t| 1.0 / ((1.0 - t) * (1.0 - t)),
+            0.0,
+            1.0,
+        ),
+        (false, true) => substitute(
+            f,
+            |t| b - t / (1.0 - t),
+            |t| 1.0 / ((1.0 - t) * (1.0 - t)),
+            0.0,
+            1.0,
+        ),
+        (false, false) => substitute(
+            f,
+            |t| t / (1.0 - t * t),
+            |t| (1.0 + t * t) / ((1.0 - t * t) * (1.0 - t * t)),
+            -1.0,
+            1.0,
+        ),
+    }
+}
+
+/// Integrates f(x(t))·x'(t) over [lo, hi]. Where x'(t) overflows the tail is far
+/// enough out to contribute nothing to an integral that converges.
+fn substitute(
+    mut f: impl FnMut(f64) -> f64,
+    x: impl Fn(f64) -> f64,
+    dx: impl Fn(f64) -> f64,
+    lo: f64,
+    hi: f64,
+) -> Result<f64, IntegrationError> {
+    adaptive(
+        |t| {
+            let scale = dx(t);
+            if scale.is_finite() {
+                f(x(t)) * scale
+            } else {
+                0.0
+            }
+        },
+        lo,
+        hi,
+    )
+    .map_err(|e| match e {
+        IntegrationError::Undefined(t) => IntegrationError::Undefined(x(t)),
+        e => e,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::PI;
+
+    fn assert_integral(f: impl FnMut(f64) -> f64, a: f64, b: f64, expected: f64) {
+        let value = integrate(f, a, b).unwrap();
+        assert!(
+            (value - expected).abs() < 1e-10 * expected.abs().max(1.0),
+            "{} != {}",
+            value,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_finite_ranges() {
+        assert_integral(|x| x * x, 0.0, 1.0, 1.0 / 3.0);
+        assert_integral(f64::sin, 0.0, PI, 2.0);
+        assert_integral(f64::sin, PI, 0.0, -2.0);
+        assert_integral(|x| x.exp(), 2.0, 2.0, 0.0);
+        // Singular at an endpoint
+        assert_integral(|x| 1.0 / x.sqrt(), 0.0, 1.0, 2.0);
+        assert_integral(|x| x.ln(), 0.0, 1.0, -1.0);
+        // Oscillating
+        assert_integral(|x| (20.0 * x).cos(), 0.0, 10.0, (200f64).sin() / 20.0);
+        // A jump, which only bisection down to the step can resolve
+        assert_integral(
+            |x| if x < 1.0 / 3.0 { 0.0 } else { 1.0 },
+            0.0,
+            1.0,
+            2.0 / 3.0,
+        );
+        // A narrow peak
+        assert_integral(
+            |x| (-1e6 * (x - 0.3).powi(2)).exp(),
+            0.0,
+            1.0,
+            PI.sqrt() / 1000.0,
+        );
+    }
+
+    #[test]
+    fn test_evaluation_counts() {
+        // A low-degree polynomial is exact on the first 15-point rule
+        let mut calls = 0;
+        let value = integrate(
+            |x| {
+                calls += 1;
+                x * x * x - x
+            },
+            -1.0,
+            2.0,
+        )
+        .unwrap();
+        assert!((value - 2.25).abs() < 1e-15 && calls == 15);
+        // A zero integrand converges through the absolute tolerance
+        let mut calls = 0;
+        assert_eq!(
+            integrate(
+                |_| {
+                    calls += 1;
+                    0.0
+                },
+                0.0,
+                1.0
+            )
+            .unwrap(),
+            0.0
+        );
+        assert_eq!(calls, 15);
+        // An empty range evaluates nothing
+        assert_eq!(integrate(|_| panic!("evaluated"), 1.0, 1.0).unwrap(), 0.0);
+    }
+
+    #[test]
+    fn test_infinite_ranges() {
+        assert_integral(|x| (-x).exp(), 0.0, f64::INFINITY, 1.0);
+        assert_integral(|x| x.exp(), f64::NEG_INFINITY, 0.0, 1.0);
+        assert_integral(
+            |x| (-x * x).exp(),
+            f64::NEG_INFINITY,
+            f64::INFINITY,
+            PI.sqrt(),
+        );
+        assert_integral(
+            |x| 1.0 / (1.0 + x * x),
+            f64::NEG_INFINITY,
+            f64::INFINITY,
+            PI,
+        );
+        // Half-lines that do not start at zero, and a reversed one
+        assert_integral(|x| 1.0 / (x * x), 1.0, f64::INFINITY, 1.0);
+        assert_integral(|x| x.exp(), f64::NEG_INFINITY, -1.0, (-1f64).exp());
+        assert_integral(|x| (-x).exp(), f64::INFINITY, 0.0, -1.0);
+        // The tail is cut off where the substitution's derivative overflows
+        assert_integral(|x| 1.0 / (1.0 + x).powi(3), 0.0, f64::INFINITY, 0.5);
+    }
+
+    #[test]
+    fn test_failures() {
+        assert!(matches!(
+            integrate(|x| 1.0 / x, -1.0, 1.0),
+            Err(IntegrationError::Undefined(x)) if x == 0.0
+        ));
+        // Divergent: the estimate keeps growing as the pole is resolved
+        assert!(matches!(
+            integrate(|x| 1.0 / x, 0.0, 1.0),
+            Err(IntegrationError::NotConverged { .. })
+        ));
+        assert!(matches!(
+            integrate(f64::sin, 0.0, f64::INFINITY),
+            Err(IntegrationError::NotConverged { .. })
+        ));
+        // Reported where the integrand failed, not at the substituted point
+        let failure = integrate(|x| if x > 5.0 { f64::NAN } else { 1.0 }, 0.0, f64::INFINITY);
+        assert!(matches!(failure, Err(IntegrationError::Undefined(x)) if x > 5.0));
+        let failure = integrate(|x| 1.0 / x, -1.0, 1.0).unwrap_err();
+        assert_eq!(failure.to_string(), "the integrand is not finite at x = 0");
+        let Err(IntegrationError::NotConverged { intervals, .. }) =
+            integrate(|x| 1.0 / x, 0.0, 1.0)
+        else {
+            panic!("expected no convergence");
+        };
+        assert!(intervals <= MAX_INTERVALS);
+    }
+}
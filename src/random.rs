@@ -0,0 +1,142 @@
+//! The session's random number generator.
+//!
+//! A xoshiro256** generator seeded through SplitMix64, so a seed given with
+//! `--seed` or `:seed` reproduces every draw that follows it.
+
+use lazy_static::lazy_static;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub struct Generator {
+    seed: u64,
+    state: [u64; 4],
+}
+
+impl Generator {
+    pub fn new(seed: u64) -> Self {
+        let mut mix = seed;
+        let mut next = || {
+            mix = mix.wrapping_add(0x9e3779b97f4a7c15);
+            let mut z = mix;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+            z ^ (z >> 31)
+        };
+        Generator {
+            seed,
+            state: [next(), next(), next(), next()],
+        }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let s = &mut self.state;
+        let result = s[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = s[1] << 17;
+        s[2] ^= s[0];
+        s[3] ^= s[1];
+        s[1] ^= s[2];
+        s[0] ^= s[3];
+        s[2] ^= t;
+        s[3] = s[3].rotate_left(45);
+        result
+    }
+
+    /// Uniform on [0, 1), with all 53 bits of the mantissa random.
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Uniform below `n`, rejecting the draws that would bias the low values.
+    pub fn below(&mut self, n: u64) -> u64 {
+        let zone = u64::MAX - u64::MAX % n;
+        loop {
+            let x = self.next_u64();
+            if x < zone {
+                return x % n;
+            }
+        }
+    }
+
+    /// Uniform integer from `a` to `b` inclusive.
+    pub fn int_between(&mut self, a: i64, b: i64) -> i64 {
+        a + self.below(a.abs_diff(b) + 1) as i64
+    }
+
+    /// Normal deviate by the Box–Muller transform.
+    pub fn normal(&mut self, mu: f64, sigma: f64) -> f64 {
+        let u = 1.0 - self.next_f64();
+        let v = self.next_f64();
+        mu + sigma * (-2.0 * u.ln()).sqrt() * (2.0 * std::f64::consts::PI * v).cos()
+    }
+}
+
+lazy_static! {
+    static ref GENERATOR: Mutex<Generator> = Mutex::new(Generator::new(clock_seed()));
+    /// Whether anything has been drawn since the last `take_used`.
+    static ref USED: Mutex<bool> = Mutex::new(false);
+}
+
+fn clock_seed() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0)
+}
+
+/// Restarts the session's generator from `seed`.
+pub fn set_seed(seed: u64) {
+    *GENERATOR.lock().unwrap() = Generator::new(seed);
+}
+
+/// The seed the session's generator last started from.
+pub fn seed() -> u64 {
+    GENERATOR.lock().unwrap().seed
+}
+
+/// Runs `draw` with the session's generator.
+pub fn with<T>(draw: impl FnOnce(&mut Generator) -> T) -> T {
+    *USED.lock().unwrap() = true;
+    draw(&mut GENERATOR.lock().unwrap())
+}
+
+/// The session seed if anything was drawn since the last call, for history entries.
+pub fn take_used() -> Option<u64> {
+    std::mem::take(&mut *USED.lock().unwrap()).then(seed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reproducible() {
+        let mut a = Generator::new(42);
+        let mut b = Generator::new(42);
+        let draws: Vec<u64> = (0..5).map(|_| a.next_u64()).collect();
+        assert_eq!(draws, (0..5).map(|_| b.next_u64()).collect::<Vec<_>>());
+        assert_ne!(Generator::new(43).next_u64(), draws[0]);
+    }
+
+    #[test]
+    fn test_ranges() {
+        let mut generator = Generator::new(7);
+        for _ in 0..1000 {
+            let x = generator.next_f64();
+            assert!((0.0..1.0).contains(&x));
+            let k = generator.int_between(-2, 3);
+            assert!((-2..=3).contains(&k));
+        }
+        assert_eq!(generator.int_between(5, 5), 5);
+        let all: std::collections::BTreeSet<i64> =
+            (0..200).map(|_| generator.int_between(1, 6)).collect();
+        assert_eq!(all.len(), 6);
+
+        let n = 20_000;
+        let samples: Vec<f64> = (0..n).map(|_| generator.normal(10.0, 2.0)).collect();
+        let mean = samples.iter().sum::<f64>() / n as f64;
+        let variance = samples.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / n as f64;
+        // Well within five standard errors
+        assert!((mean - 10.0).abs() < 0.08, "mean {}", mean);
+        assert!((variance - 4.0).abs() < 0.2, "variance {}", variance);
+    }
+}
@@ -0,0 +1,292 @@
+//! Root finding for equations in one unknown.
+
+use crate::rounding::{self, RoundingMode};
+
+/// Iteration cap; bisection alone halves any finite bracket down to adjacent
+/// floats well within it.
+const MAX_ITERATIONS: usize = 2000;
+
+/// Brent's method: a root of `f` in [a, b], where `f(a)` and `f(b)` differ in sign.
+/// Combines bisection's guarantee with the speed of inverse quadratic interpolation.
+pub fn brent(mut f: impl FnMut(f64) -> f64, mut a: f64, mut b: f64) -> Result<f64, String> {
+    let (mut fa, mut fb) = (f(a), f(b));
+    if fa == 0.0 {
+        return Ok(a);
+    }
+    if fb == 0.0 {
+        return Ok(b);
+    }
+    if fa.is_nan() || fb.is_nan() || fa.signum() == fb.signum() {
+        return Err(format!(
+            "The function does not change sign between {} and {}",
+            a, b
+        ));
+    }
+    let (mut c, mut fc) = (a, fa);
+    let (mut d, mut e) = (b - a, b - a);
+    for _ in 0..MAX_ITERATIONS {
+        if fb.signum() == fc.signum() {
+            (c, fc) = (a, fa);
+            d = b - a;
+            e = d;
+        }
+        if fc.abs() < fb.abs() {
+            (a, fa) = (b, fb);
+            (b, fb) = (c, fc);
+            (c, fc) = (a, fa);
+        }
+        let tolerance = 2.0 * f64::EPSILON * b.abs() + f64::MIN_POSITIVE;
+        let half = 0.5 * (c - b);
+        if half.abs() <= tolerance || fb == 0.0 {
+            return Ok(b);
+        }
+        if e.abs() >= tolerance && fa.abs() > fb.abs() {
+            // Secant or inverse quadratic step, kept only while it shrinks the bracket fast
+            let s = fb / fa;
+            let (mut p, mut q) = if a == c {
+                (2.0 * half * s, 1.0 - s)
+            } else {
+                let (q, r) = (fa / fc, fb / fc);
+                (
+                    s * (2.0 * half * q * (q - r) - (b - a) * (r - 1.0)),
+                    (q - 1.0) * (r - 1.0) * (s - 1.0),
+                )
+            };
+            if p > 0.0 {
+                q = -q;
+            } else {
+                p = -p;
+            }
+            if 2.0 * p < (3.0 * half * q - (tolerance * q).abs()).min((e * q).abs()) {
+                e = d;
+                d = p / q;
+            } else {
+                d = half;
+                e = d;
+            }
+        } else {
+            d = half;
+            e = d;
+        }
+        (a, fa) = (b, fb);
+        b += if d.abs() > tolerance {
+            d
+        } else {
+            tolerance.copysign(half)
+        };
+        fb = f(b);
+        if fb.is_nan() {
+            return Err(format!("The function is undefined at {}", b));
+        }
+    }
+    Err(format!(
+        "Root finding did not converge within {} iterations",
+        MAX_ITERATIONS
+    ))
+}
+
+/// Subintervals `grid` divides a range into.
+const SCAN_STEPS: usize = 2000;
+
+/// Points spread evenly over [a, b], for `all_roots` to scan.
+pub fn grid(a: f64, b: f64) -> Vec<f64> {
+    (0..=SCAN_STEPS)
+        .map(|i| a + (b - a) * i as f64 / SCAN_STEPS as f64)
+        .collect()
+}
+
+/// Points from -10^6 to 10^6, close together near zero and further apart away
+/// from it, for when there is no range to search.
+pub fn wide_grid() -> Vec<f64> {
+    let magnitudes: Vec<f64> = (-600..=1200)
+        .map(|k| 10f64.powf(k as f64 / 200.0))
+        .collect();
+    let negatives = magnitudes.iter().rev().map(|x| -x);
+    negatives
+        .chain(std::iter::once(0.0))
+        .chain(magnitudes.iter().copied())
+        .collect()
+}
+
+/// Every root of `f` found among `points`, in increasing order: Brent's method
+/// wherever the sign changes between neighbours, and Newton's method from the
+/// dips of |f| that stay on one side, which catches double roots such as that
+/// of x² at zero. Poles where the sign flips are told apart by |f| growing.
+pub fn all_roots(mut f: impl FnMut(f64) -> f64, points: &[f64]) -> Vec<f64> {
+    let values: Vec<f64> = points.iter().map(|&x| f(x)).collect();
+    let mut roots = Vec::new();
+    for (i, (&x, &y)) in points.iter().zip(&values).enumerate() {
+        if y == 0.0 {
+            roots.push(x);
+            continue;
+        }
+        if let (Some(&next), Some(&y_next)) = (points.get(i + 1), values.get(i + 1))
+            && y_next != 0.0
+            && y.is_finite()
+            && y_next.is_finite()
+            && (y < 0.0) != (y_next < 0.0)
+            && let Ok(root) = brent(&mut f, x, next)
+            && f(root).abs() <= 1e-6 * y.abs().max(y_next.abs())
+        {
+            roots.push(root);
+        }
+        if i == 0 || i + 1 == points.len() {
+            continue;
+        }
+        let (before, after) = (values[i - 1], values[i + 1]);
+        let dip = y.abs() < before.abs() && y.abs() < after.abs();
+        if dip && (before < 0.0) == (y < 0.0) && (after < 0.0) == (y < 0.0) {
+            let (lo, hi) = (points[i - 1], points[i + 1]);
+            let newton_root = newton(
+                |x| {
+                    let h = 1e-7 * (1.0 + x.abs());
+                    (f(x), (f(x + h) - f(x - h)) / (2.0 * h))
+                },
+                x,
+            );
+            if let Some(root) = newton_root
+                && (lo..=hi).contains(&root)
+                && f(root).abs() <= 1e-12 * before.abs().max(after.abs()).max(1.0)
+            {
+                roots.push(root);
+            }
+        }
+    }
+    let mut roots: Vec<f64> = roots.into_iter().map(|root| tidy(&mut f, root)).collect();
+    roots.sort_by(f64::total_cmp);
+    roots.dedup_by(|a, b| (*a - *b).abs() <= 1e-9 * a.abs().max(1.0));
+    roots
+}
+
+/// The root rounded to 12 significant digits when `f` is at least as close to
+/// zero there, so 2.0000000000000013 comes out as 2.
+pub fn tidy(mut f: impl FnMut(f64) -> f64, root: f64) -> f64 {
+    let rounded = rounding::round_significant(root, 12, RoundingMode::HalfEven);
+    if rounded != root && f(rounded).abs() <= f(root).abs() {
+        rounded
+    } else {
+        root
+    }
+}
+
+/// Newton's method from `guess`, given `f` and its derivative together; `None`
+/// when it stalls, leaves the finite numbers or runs out of iterations.
+pub fn newton(mut f: impl FnMut(f64) -> (f64, f64), guess: f64) -> Option<f64> {
+    let mut x = guess;
+    for _ in 0..100 {
+        let (y, dy) = f(x);
+        if y == 0.0 {
+            return Some(x);
+        }
+        let next = x - y / dy;
+        if !next.is_finite() {
+            return None;
+        }
+        if (next - x).abs() <= 4.0 * f64::EPSILON * next.abs() {
+            return Some(next);
+        }
+        x = next;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_brent() {
+        let root = brent(|x| x * x - 2.0, 0.0, 2.0).unwrap();
+        assert!((root - 2f64.sqrt()).abs() < 1e-15);
+        let root = brent(|x| x.cos() - x, 0.0, 1.0).unwrap();
+        assert!((root - 0.7390851332151607).abs() < 1e-15);
+        // A root at zero, where the relative tolerance vanishes
+        assert!(brent(|x| x.powi(3), -1.0, 2.0).unwrap().abs() < 1e-100);
+        // Discontinuous sign change: converges onto the jump
+        let jump = brent(|x| if x < 0.3 { -1.0 } else { 1.0 }, 0.0, 1.0).unwrap();
+        assert!((jump - 0.3).abs() < 1e-15);
+        assert!(brent(|x| x * x + 1.0, -1.0, 1.0).is_err());
+    }
+
+    #[test]
+    fn test_brent_brackets() {
+        // A root on the bracket is returned as is, without iterating
+        assert_eq!(brent(|x| x - 1.0, 1.0, 5.0).unwrap(), 1.0);
+        assert_eq!(brent(|x| x - 5.0, 1.0, 5.0).unwrap(), 5.0);
+        // The bracket may be given backwards, or span most of the floats
+        let root = brent(|x| x * x - 2.0, 2.0, 0.0).unwrap();
+        assert!((root - 2f64.sqrt()).abs() < 1e-15);
+        assert!((brent(|x| x - 3.0, -1e300, 1e300).unwrap() - 3.0).abs() < 1e-15);
+        // Interpolation needs far fewer evaluations than bisection's 50-odd
+        let mut calls = 0;
+        brent(
+            |x| {
+                calls += 1;
+                x.exp() - 2.0
+            },
+            0.0,
+            1.0,
+        )
+        .unwrap();
+        assert!(calls < 15, "{} evaluations", calls);
+
+        assert!(brent(|x| (x - 1.0).sqrt(), 0.0, 4.0).is_err());
+        // Undefined inside the bracket; the sign changes across a gap
+        let gap = brent(|x| if x.abs() < 0.5 { f64::NAN } else { x }, -1.0, 2.0);
+        assert!(gap.unwrap_err().starts_with("The function is undefined"));
+    }
+
+    #[test]
+    fn test_all_roots() {
+        let roots = all_roots(|x| x * x - 2.0, &wide_grid());
+        assert_eq!(roots.len(), 2);
+        assert!((roots[1] - 2f64.sqrt()).abs() < 1e-15 && roots[0] == -roots[1]);
+        // A double root, and a pole that is not a root
+        let roots = all_roots(|x| (x - 1.0 / 3.0).powi(2) * (x + 2.0), &grid(-5.0, 5.0));
+        assert_eq!(roots.len(), 2);
+        assert!((roots[0] + 2.0).abs() < 1e-12 && (roots[1] - 1.0 / 3.0).abs() < 1e-6);
+        assert!(all_roots(|x| 1.0 / (x - 0.123), &grid(-1.0, 1.0)).is_empty());
+        let roots = all_roots(f64::sin, &grid(-10.0, 10.0));
+        assert_eq!(roots.len(), 7);
+        assert!(all_roots(|x| x * x + 1.0, &wide_grid()).is_empty());
+    }
+
+    #[test]
+    fn test_roots_near_grid_points_and_poles() {
+        // A root on a grid point is found once, not again from each neighbour
+        assert_eq!(all_roots(|x| x - 0.5, &grid(-1.0, 1.0)), vec![0.5]);
+        assert_eq!(all_roots(|x| x * x, &grid(-1.0, 1.0)), vec![0.0]);
+        // tan() changes sign across its poles without a root there
+        let roots = all_roots(f64::tan, &grid(-4.0, 4.0));
+        assert_eq!(roots.len(), 3);
+        assert!((roots[0] + std::f64::consts::PI).abs() < 1e-12 && roots[1] == 0.0);
+        assert!(all_roots(|x| 1.0 / x, &wide_grid()).is_empty());
+        // Undefined on part of the range
+        assert_eq!(all_roots(|x| x.ln(), &grid(-1.0, 3.0)), vec![1.0]);
+        // The wide grid reaches roots far from zero
+        let roots = all_roots(|x| x - 123456.0, &wide_grid());
+        assert_eq!(roots, vec![123456.0]);
+    }
+
+    #[test]
+    fn test_tidy() {
+        // Rounded when that is at least as good a root
+        assert_eq!(tidy(|x| x * x - 4.0, 2.0000000000000013), 2.0);
+        // Kept when the digits past the twelfth matter
+        let root = 1.0 / 3.0 + 1e-14;
+        assert_eq!(tidy(|x| x - (1.0 / 3.0 + 1e-14), root), root);
+        assert_eq!(tidy(|x| x, 0.0), 0.0);
+    }
+
+    #[test]
+    fn test_newton() {
+        let root = newton(|x| (x * x - 2.0, 2.0 * x), 1.0).unwrap();
+        assert!((root - 2f64.sqrt()).abs() < 1e-15);
+        assert!(newton(|x| (x * x + 1.0, 2.0 * x), 0.0).is_none());
+        // x³ - 2x + 2 cycles between 0 and 1 from either
+        assert!(newton(|x| (x.powi(3) - 2.0 * x + 2.0, 3.0 * x * x - 2.0), 0.0).is_none());
+        // A double root converges, if only linearly
+        let root = newton(|x| ((x - 1.0).powi(2), 2.0 * (x - 1.0)), 3.0).unwrap();
+        assert!((root - 1.0).abs() < 1e-7);
+    }
+}
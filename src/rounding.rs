@@ -0,0 +1,213 @@
+//! Decimal rounding with an explicit rule for ties.
+//!
+//! Numbers are rounded on their shortest decimal representation, the one they
+//! are printed with, so `2.675` is a tie even though the nearest `f64` lies just
+//! below it.
+
+/// How a number is rounded to the digits that are kept.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum RoundingMode {
+    /// Ties away from zero: 2.5 → 3, -2.5 → -3.
+    #[default]
+    HalfUp,
+    /// Ties to the even neighbour (banker's rounding): 2.5 → 2, 3.5 → 4.
+    HalfEven,
+    /// Ties toward zero: 2.5 → 2, 2.51 → 3.
+    HalfDown,
+    /// Drops the extra digits (truncation): 2.9 → 2, -2.9 → -2.
+    TowardZero,
+    /// Any remainder rounds away from zero: 2.1 → 3, -2.1 → -3.
+    AwayFromZero,
+}
+
+impl RoundingMode {
+    pub fn parse(value: &str) -> Option<RoundingMode> {
+        let name: String = value
+            .to_lowercase()
+            .chars()
+            .filter(|c| !matches!(c, '-' | '_' | ' '))
+            .collect();
+        match name.as_str() {
+            "halfup" => Some(RoundingMode::HalfUp),
+            "halfeven" | "bankers" | "banker's" => Some(RoundingMode::HalfEven),
+            "halfdown" => Some(RoundingMode::HalfDown),
+            "towardzero" | "truncate" | "down" => Some(RoundingMode::TowardZero),
+            "awayfromzero" | "up" => Some(RoundingMode::AwayFromZero),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            RoundingMode::HalfUp => "half-up",
+            RoundingMode::HalfEven => "half-even",
+            RoundingMode::HalfDown => "half-down",
+            RoundingMode::TowardZero => "toward-zero",
+            RoundingMode::AwayFromZero => "away-from-zero",
+        }
+    }
+}
+
+/// Rounds `x` to `decimals` digits after the decimal point; a negative count
+/// rounds to tens, hundreds and so on.
+pub fn round(x: f64, decimals: i32, mode: RoundingMode) -> f64 {
+    if !x.is_finite() || x == 0.0 {
+        return x;
+    }
+    // Shortest representation as d.ddd…e±x
+    let scientific = format!("{:e}", x.abs());
+    let (mantissa, exponent) = scientific.split_once('e').unwrap_or((&scientific, "0"));
+    let exponent: i32 = exponent.parse().unwrap_or(0);
+    let digits: Vec<u8> = mantissa
+        .bytes()
+        .filter(u8::is_ascii_digit)
+        .map(|d| d - b'0')
+        .collect();
+
+    let keep = exponent + 1 + decimals;
+    if keep >= digits.len() as i32 {
+        return x;
+    }
+    let kept = &digits[..keep.max(0) as usize];
+    // The first dropped digit, and whether any digit after it is non-zero
+    let (first, rest) = if keep >= 0 {
+        let keep = keep as usize;
+        (digits[keep], digits[keep + 1..].iter().any(|&d| d != 0))
+    } else {
+        (0, true)
+    };
+    let odd = kept.last().is_some_and(|d| d % 2 == 1);
+    let up = match mode {
+        RoundingMode::HalfUp => first >= 5,
+        RoundingMode::HalfEven => first > 5 || (first == 5 && (rest || odd)),
+        RoundingMode::HalfDown => first > 5 || (first == 5 && rest),
+        RoundingMode::TowardZero => false,
+        RoundingMode::AwayFromZero => first > 0 || rest,
+    };
+    let units = kept.iter().fold(0u64, |n, &d| n * 10 + d as u64) + up as u64;
+    if units == 0 {
+        return 0.0;
+    }
+    let magnitude: f64 = format!("{}e{}", units, -decimals)
+        .parse()
+        .unwrap_or(f64::NAN);
+    magnitude.copysign(x)
+}
+
+/// Rounds `x` to `digits` significant digits.
+pub fn round_significant(x: f64, digits: u32, mode: RoundingMode) -> f64 {
+    if !x.is_finite() || x == 0.0 {
+        return x;
+    }
+    let scientific = format!("{:e}", x);
+    let exponent: i32 = scientific
+        .split_once('e')
+        .and_then(|(_, e)| e.parse().ok())
+        .unwrap_or(0);
+    round(x, digits as i32 - 1 - exponent, mode)
+}
+
+/// Rounds `x` to a multiple of `multiple`.
+pub fn round_multiple(x: f64, multiple: f64, mode: RoundingMode) -> f64 {
+    if multiple == 0.0 {
+        return 0.0;
+    }
+    let multiple = multiple.abs();
+    // Dividing leaves binary noise, as in 0.15 / 0.05 = 2.9999999999999996;
+    // 15 significant digits are free of it, if rounded to nearest whatever the mode
+    let quotient = round_significant(x / multiple, 15, RoundingMode::HalfEven);
+    let count = round(quotient, 0, mode);
+    round_significant(count * multiple, 15, RoundingMode::HalfEven)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use RoundingMode::*;
+
+    #[test]
+    fn test_ties() {
+        // 2.675 is stored as 2.67499999999999982236431605997495353221893310546875
+        assert_eq!(round(2.675, 2, HalfUp), 2.68);
+        assert_eq!(round(1.005, 2, HalfUp), 1.01);
+        assert_eq!(round(2.665, 2, HalfEven), 2.66);
+        assert_eq!(round(2.675, 2, HalfEven), 2.68);
+        assert_eq!(round(0.125, 2, HalfEven), 0.12);
+        assert_eq!(round(2.5, 0, HalfDown), 2.0);
+        assert_eq!(round(2.51, 0, HalfDown), 3.0);
+        assert_eq!(round(-2.5, 0, HalfUp), -3.0);
+        assert_eq!(round(-2.5, 0, HalfEven), -2.0);
+        assert_eq!(round(0.5, 0, HalfEven), 0.0);
+    }
+
+    #[test]
+    fn test_directed() {
+        assert_eq!(round(-2.79, 1, TowardZero), -2.7);
+        assert_eq!(round(2.01, 1, AwayFromZero), 2.1);
+        assert_eq!(round(-2.01, 0, AwayFromZero), -3.0);
+        assert_eq!(round(0.0004, 2, AwayFromZero), 0.01);
+        assert_eq!(round(0.0004, 2, HalfUp), 0.0);
+        assert_eq!(round(1234.5, -2, HalfUp), 1200.0);
+        assert_eq!(round(1250.0, -2, HalfEven), 1200.0);
+        assert_eq!(round(99.96, 1, HalfUp), 100.0);
+        // Already within the digits kept
+        assert_eq!(round(1e20, 2, HalfUp), 1e20);
+        assert_eq!(round(0.1 + 0.2, 2, TowardZero), 0.3);
+    }
+
+    #[test]
+    fn test_extremes() {
+        for mode in [HalfUp, HalfEven, HalfDown, TowardZero, AwayFromZero] {
+            assert!(round(f64::NAN, 2, mode).is_nan());
+            assert_eq!(round(f64::INFINITY, 2, mode), f64::INFINITY);
+            assert_eq!(round(-0.0, 2, mode).to_bits(), (-0.0f64).to_bits());
+            assert_eq!(
+                round_significant(f64::NEG_INFINITY, 3, mode),
+                f64::NEG_INFINITY
+            );
+        }
+        // Rounding to zero gives 0, never -0
+        assert_eq!(round(-0.4, 0, HalfUp).to_bits(), 0.0f64.to_bits());
+        // Everything dropped: only a first digit of 5 or more, or away-from-zero, rounds up
+        assert_eq!(round(0.4, -1, HalfUp), 0.0);
+        assert_eq!(round(4.9, -1, AwayFromZero), 10.0);
+        assert_eq!(round(1e-300, 2, AwayFromZero), 0.01);
+        assert_eq!(round(5e-309, 308, HalfUp), 1e-308);
+        // The nearest multiple of 10^308 is past the largest f64
+        assert_eq!(round(1.5e308, -308, HalfEven), f64::INFINITY);
+        // Carries all the way through the kept digits
+        assert_eq!(round(9.9999, 3, HalfUp), 10.0);
+        assert_eq!(round(-999.5, 0, HalfEven), -1000.0);
+        // Seventeen digits, the most an f64 prints
+        assert_eq!(round(0.30000000000000004, 16, HalfUp), 0.3);
+        assert_eq!(round(0.30000000000000004, 17, HalfUp), 0.30000000000000004);
+    }
+
+    #[test]
+    fn test_significant_and_multiples() {
+        assert_eq!(round_significant(123456.0, 2, HalfUp), 120000.0);
+        assert_eq!(round_significant(0.00123456, 3, HalfUp), 0.00123);
+        assert_eq!(round_significant(-0.0125, 2, HalfEven), -0.012);
+        assert_eq!(round_multiple(10.0, 3.0, HalfUp), 9.0);
+        assert_eq!(round_multiple(0.15, 0.05, HalfUp), 0.15);
+        assert_eq!(round_multiple(7.5, 5.0, HalfEven), 10.0);
+        assert_eq!(round_multiple(2.5, 5.0, HalfEven), 0.0);
+        assert_eq!(round_multiple(-17.0, 5.0, TowardZero), -15.0);
+        // Directed modes act on the true quotient, not on 2.9999999999999996
+        assert_eq!(round_multiple(0.15, 0.05, TowardZero), 0.15);
+        assert_eq!(round_multiple(0.7, 0.1, TowardZero), 0.7);
+        assert_eq!(round_multiple(0.3, 0.1, AwayFromZero), 0.3);
+        assert_eq!(round_multiple(0.31, 0.1, AwayFromZero), 0.4);
+        // The sign of the multiple is ignored and a zero multiple gives zero
+        assert_eq!(round_multiple(10.0, -3.0, HalfUp), 9.0);
+        assert_eq!(round_multiple(10.0, 0.0, HalfUp), 0.0);
+        assert_eq!(round_significant(999.5, 3, HalfUp), 1000.0);
+        assert_eq!(round_significant(123.0, 17, HalfUp), 123.0);
+        assert_eq!(RoundingMode::parse("Bankers"), Some(HalfEven));
+        assert_eq!(RoundingMode::parse("half_down"), Some(HalfDown));
+        assert_eq!(RoundingMode::parse("sideways"), None);
+        for mode in [HalfUp, HalfEven, HalfDown, TowardZero, AwayFromZero] {
+            assert_eq!(RoundingMode::parse(mode.name()), Some(mode));
+        }
+    }
+}
@@ -0,0 +1,159 @@
+use crate::format::NumberFormat;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+use std::sync::Mutex;
+use tokio::fs;
+
+const SETTINGS_FILE: &str = "config/settings.json";
+
+/// Unit in which trigonometric functions take and return angles.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum AngleMode {
+    #[default]
+    #[serde(rename = "rad")]
+    Radians,
+    #[serde(rename = "deg")]
+    Degrees,
+    #[serde(rename = "grad")]
+    Gradians,
+}
+
+impl AngleMode {
+    pub fn parse(value: &str) -> Option<AngleMode> {
+        match value.to_lowercase().as_str() {
+            "rad" | "radian" | "radians" => Some(AngleMode::Radians),
+            "deg" | "degree" | "degrees" | "°" => Some(AngleMode::Degrees),
+            "grad" | "gradian" | "gradians" | "gon" => Some(AngleMode::Gradians),
+            _ => None,
+        }
+    }
+
+    /// Size of a full turn in this unit.
+    pub fn per_turn(self) -> f64 {
+        match self {
+            AngleMode::Radians => 2.0 * std::f64::consts::PI,
+            AngleMode::Degrees => 360.0,
+            AngleMode::Gradians => 400.0,
+        }
+    }
+}
+
+impl fmt::Display for AngleMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AngleMode::Radians => write!(f, "rad"),
+            AngleMode::Degrees => write!(f, "deg"),
+            AngleMode::Gradians => write!(f, "grad"),
+        }
+    }
+}
+
+/// Session-wide evaluation settings, changed with CLI flags, `:set` in the REPL
+/// or `config/settings.json`.
+#[derive(Debug, Clone, Default)]
+pub struct Settings {
+    /// Evaluate every number as an interval with guaranteed bounds.
+    pub interval: bool,
+    pub angle: AngleMode,
+    /// Follow IEEE-754: `1/0` is `inf` and `0/0` is `nan` instead of an error.
+    pub ieee: bool,
+    /// How results are printed.
+    pub format: NumberFormat,
+}
+
+lazy_static! {
+    static ref SETTINGS: Mutex<Settings> = Mutex::new(Settings::default());
+}
+
+pub fn parse_switch(value: &str) -> Result<bool, String> {
+    match value.to_lowercase().as_str() {
+        "on" | "true" | "yes" | "1" => Ok(true),
+        "off" | "false" | "no" | "0" => Ok(false),
+        _ => Err(format!("Expected on or off, got '{}'", value)),
+    }
+}
+
+impl Settings {
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key.to_lowercase().as_str() {
+            "interval" => self.interval = parse_switch(value)?,
+            "ieee" => self.ieee = parse_switch(value)?,
+            "angle" => {
+                self.angle = AngleMode::parse(value).ok_or_else(|| {
+                    format!("Unknown angle mode: {} (use rad, deg or grad)", value)
+                })?
+            }
+            "notation" | "digits" | "decimals" | "separators" | "trim" | "fraction" | "maxden"
+            | "fractol" | "rounding" => self.format.set(key, value)?,
+            _ => return Err(format!("Unknown setting: {}", key)),
+        }
+        Ok(())
+    }
+
+    /// Current values as `:set` key/value pairs.
+    pub fn entries(&self) -> Vec<(&'static str, String)> {
+        let mut entries = vec![
+            (
+                "interval",
+                if self.interval { "on" } else { "off" }.to_string(),
+            ),
+            ("angle", self.angle.to_string()),
+            ("ieee", if self.ieee { "on" } else { "off" }.to_string()),
+        ];
+        entries.extend(self.format.entries());
+        entries
+    }
+}
+
+impl fmt::Display for Settings {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let lines: Vec<String> = self
+            .entries()
+            .iter()
+            .map(|(key, value)| format!("{} = {}", key, value))
+            .collect();
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+pub fn current() -> Settings {
+    SETTINGS.lock().unwrap().clone()
+}
+
+pub fn set(key: &str, value: &str) -> Result<(), String> {
+    SETTINGS.lock().unwrap().set(key, value)
+}
+
+/// Applies the saved settings, a JSON object of `:set` keys and values.
+pub async fn load_settings_async() {
+    if !Path::new(SETTINGS_FILE).exists() {
+        return;
+    }
+    let data = fs::read_to_string(SETTINGS_FILE).await.unwrap_or_default();
+    let map: BTreeMap<String, String> = match serde_json::from_str(&data) {
+        Ok(map) => map,
+        Err(e) => {
+            eprintln!("Warning: Ignoring invalid {}: {}", SETTINGS_FILE, e);
+            return;
+        }
+    };
+    for (key, value) in map {
+        if let Err(e) = set(&key, &value) {
+            eprintln!("Warning: {} in {}", e, SETTINGS_FILE);
+        }
+    }
+}
+
+pub async fn save_settings_async() -> Result<(), String> {
+    let map: BTreeMap<&str, String> = current().entries().into_iter().collect();
+    let json = serde_json::to_string_pretty(&map).map_err(|e| e.to_string())?;
+    if let Some(parent) = Path::new(SETTINGS_FILE).parent() {
+        let _ = fs::create_dir_all(parent).await;
+    }
+    fs::write(SETTINGS_FILE, json)
+        .await
+        .map_err(|e| e.to_string())
+}
@@ -0,0 +1,235 @@
+//! Descriptive statistics over a sample. Callers make sure `data` is not empty.
+
+pub fn mean(data: &[f64]) -> f64 {
+    let n = data.len() as f64;
+    let sum: f64 = data.iter().sum();
+    if sum.is_finite() {
+        sum / n
+    } else {
+        // The sum of values near the largest f64 overflows where their mean does not
+        data.iter().map(|x| x / n).sum()
+    }
+}
+
+fn sorted(data: &[f64]) -> Vec<f64> {
+    let mut sorted = data.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    sorted
+}
+
+pub fn median(data: &[f64]) -> f64 {
+    percentile(data, 50.0)
+}
+
+/// The most frequent values, in ascending order.
+pub fn mode(data: &[f64]) -> Vec<f64> {
+    let sorted = sorted(data);
+    let mut runs: Vec<(f64, usize)> = Vec::new();
+    for x in sorted {
+        match runs.last_mut() {
+            Some((value, count)) if *value == x => *count += 1,
+            _ => runs.push((x, 1)),
+        }
+    }
+    let most = runs.iter().map(|(_, count)| *count).max().unwrap_or(0);
+    runs.into_iter()
+        .filter(|(_, count)| *count == most)
+        .map(|(value, _)| value)
+        .collect()
+}
+
+/// Sum of squared deviations from the mean.
+fn sum_of_squares(data: &[f64]) -> f64 {
+    let m = mean(data);
+    data.iter().map(|x| (x - m).powi(2)).sum()
+}
+
+/// Sample variance, dividing by n - 1.
+pub fn variance(data: &[f64]) -> Result<f64, String> {
+    if data.len() < 2 {
+        return Err("Sample variance needs at least 2 values".to_string());
+    }
+    Ok(sum_of_squares(data) / (data.len() - 1) as f64)
+}
+
+/// Population variance, dividing by n.
+pub fn pvariance(data: &[f64]) -> f64 {
+    sum_of_squares(data) / data.len() as f64
+}
+
+/// Percentile `p` (0 to 100), interpolating linearly between closest ranks,
+/// as spreadsheets' PERCENTILE.INC does.
+pub fn percentile(data: &[f64], p: f64) -> f64 {
+    let sorted = sorted(data);
+    let rank = p / 100.0 * (sorted.len() - 1) as f64;
+    let (lower, upper) = (rank.floor() as usize, rank.ceil() as usize);
+    sorted[lower] + (rank - lower as f64) * (sorted[upper] - sorted[lower])
+}
+
+pub fn quartiles(data: &[f64]) -> [f64; 3] {
+    [
+        percentile(data, 25.0),
+        percentile(data, 50.0),
+        percentile(data, 75.0),
+    ]
+}
+
+/// Central moment of the given order, dividing by n.
+fn moment(data: &[f64], order: i32) -> f64 {
+    let m = mean(data);
+    data.iter().map(|x| (x - m).powi(order)).sum::<f64>() / data.len() as f64
+}
+
+/// Adjusted Fisher–Pearson sample skewness, as spreadsheets' SKEW.
+pub fn skewness(data: &[f64]) -> Result<f64, String> {
+    let n = data.len() as f64;
+    if data.len() < 3 {
+        return Err("Skewness needs at least 3 values".to_string());
+    }
+    let m2 = moment(data, 2);
+    if m2 == 0.0 {
+        return Err("Skewness is undefined when all values are equal".to_string());
+    }
+    let g1 = moment(data, 3) / m2.powf(1.5);
+    Ok(g1 * (n * (n - 1.0)).sqrt() / (n - 2.0))
+}
+
+/// Sample excess kurtosis, as spreadsheets' KURT; 0 for a normal distribution.
+pub fn kurtosis(data: &[f64]) -> Result<f64, String> {
+    let n = data.len() as f64;
+    if data.len() < 4 {
+        return Err("Kurtosis needs at least 4 values".to_string());
+    }
+    let m2 = moment(data, 2);
+    if m2 == 0.0 {
+        return Err("Kurtosis is undefined when all values are equal".to_string());
+    }
+    let g2 = moment(data, 4) / (m2 * m2) - 3.0;
+    Ok((n - 1.0) / ((n - 2.0) * (n - 3.0)) * ((n + 1.0) * g2 + 6.0))
+}
+
+fn check_pairs(x: &[f64], y: &[f64]) -> Result<(), String> {
+    if x.len() != y.len() {
+        return Err(format!(
+            "Expected two lists of the same length, got {} and {} values",
+            x.len(),
+            y.len()
+        ));
+    }
+    if x.len() < 2 {
+        return Err("Expected at least 2 pairs of values".to_string());
+    }
+    Ok(())
+}
+
+/// Sample covariance, dividing by n - 1.
+pub fn covariance(x: &[f64], y: &[f64]) -> Result<f64, String> {
+    check_pairs(x, y)?;
+    let (mx, my) = (mean(x), mean(y));
+    let sum: f64 = x.iter().zip(y).map(|(a, b)| (a - mx) * (b - my)).sum();
+    Ok(sum / (x.len() - 1) as f64)
+}
+
+/// Pearson correlation coefficient.
+pub fn correlation(x: &[f64], y: &[f64]) -> Result<f64, String> {
+    check_pairs(x, y)?;
+    let spread = (sum_of_squares(x) * sum_of_squares(y)).sqrt();
+    if spread == 0.0 {
+        return Err("Correlation is undefined when a list has no variation".to_string());
+    }
+    Ok(covariance(x, y)? * (x.len() - 1) as f64 / spread)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-12,
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_summaries() {
+        let data = [2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0];
+        assert_eq!(mean(&data), 5.0);
+        assert_eq!(median(&data), 4.5);
+        assert_eq!(mode(&data), vec![4.0]);
+        assert_eq!(mode(&[3.0, 1.0, 3.0, 1.0]), vec![1.0, 3.0]);
+        assert_eq!(pvariance(&data), 4.0);
+        assert_close(variance(&data).unwrap(), 32.0 / 7.0);
+        assert!(variance(&[1.0]).is_err());
+
+        let data = [1.0, 2.0, 3.0, 4.0, 5.0];
+        assert_eq!(percentile(&data, 0.0), 1.0);
+        assert_eq!(percentile(&data, 90.0), 4.6);
+        assert_eq!(quartiles(&data), [2.0, 3.0, 4.0]);
+    }
+
+    #[test]
+    fn test_order_and_size_edge_cases() {
+        // Unsorted input, a single value, and both ends of the range
+        let data = [9.0, 1.0, 5.0];
+        assert_eq!(median(&data), 5.0);
+        assert_eq!(percentile(&data, 100.0), 9.0);
+        assert_eq!(percentile(&[7.0], 37.0), 7.0);
+        assert_eq!(median(&[7.0]), 7.0);
+        assert_eq!(quartiles(&[3.0, 1.0]), [1.5, 2.0, 2.5]);
+        // With no value repeated, every value is a mode
+        assert_eq!(mode(&[3.0, 1.0, 2.0]), vec![1.0, 2.0, 3.0]);
+        assert_eq!(mode(&[-0.0, 0.0]).len(), 1);
+        assert_eq!(pvariance(&[4.0]), 0.0);
+        assert!(variance(&[]).is_err());
+        assert!(skewness(&[1.0, 2.0]).is_err());
+        assert!(kurtosis(&[1.0, 2.0, 3.0]).is_err());
+    }
+
+    #[test]
+    fn test_large_magnitudes() {
+        // Deviations are taken from the mean, so a large offset does not cancel them away
+        let offset = [1e9 + 4.0, 1e9 + 7.0, 1e9 + 13.0, 1e9 + 16.0];
+        assert_eq!(variance(&offset).unwrap(), 30.0);
+        assert_close(skewness(&offset).unwrap(), 0.0);
+        // Values whose sum overflows still have a mean
+        assert_eq!(mean(&[1e308, 1e308]), 1e308);
+        assert_eq!(variance(&[1e308, 1e308]).unwrap(), 0.0);
+        assert_eq!(mean(&[f64::MAX, -f64::MAX]), 0.0);
+        assert_eq!(median(&[f64::MAX, f64::MAX]), f64::MAX);
+    }
+
+    #[test]
+    fn test_shape() {
+        // Reference values from spreadsheet SKEW and KURT
+        let data = [2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0];
+        assert_close(skewness(&data).unwrap(), 0.8184875533567997);
+        assert_close(kurtosis(&data).unwrap(), 0.940625);
+        assert_close(skewness(&[1.0, 2.0, 3.0]).unwrap(), 0.0);
+        assert!(kurtosis(&[1.0, 1.0, 1.0, 1.0]).is_err());
+    }
+
+    #[test]
+    fn test_pairs() {
+        let x = [1.0, 2.0, 3.0, 4.0];
+        let y = [2.0, 4.0, 6.0, 8.0];
+        assert_close(covariance(&x, &y).unwrap(), 10.0 / 3.0);
+        assert_close(correlation(&x, &y).unwrap(), 1.0);
+        assert_close(correlation(&x, &[4.0, 3.0, 2.0, 1.0]).unwrap(), -1.0);
+        assert!(correlation(&x, &y[..3]).is_err());
+        assert!(covariance(&[1.0], &[2.0]).is_err());
+        // A list with no variation has a covariance but no correlation
+        assert_eq!(covariance(&x, &[5.0; 4]).unwrap(), 0.0);
+        assert!(correlation(&x, &[5.0; 4]).is_err());
+        assert_close(correlation(&x, &[1.0, 3.0, 2.0, 4.0]).unwrap(), 0.8);
+        // Exactly linear data stays within ±1 despite rounding
+        for scale in [0.1, 3.0, 1e-3, 7e5] {
+            let x = [0.1, 0.2, 0.7, 1.3, 2.9];
+            let y = x.map(|v| v * scale + 1.0);
+            let r = correlation(&x, &y).unwrap();
+            assert!(r <= 1.0 && r > 1.0 - 1e-12, "{}", r);
+        }
+    }
+}
@@ -0,0 +1,1247 @@
+//! Expressions with free variables, for calculus on formulas.
+//!
+//! The token stream the evaluator works on has no way to leave a variable
+//! unbound, so `diff` parses its argument into an `Expr` tree instead. Sums and
+//! products are n-ary; `a - b` is stored as `a + (-1)·b` and `a / b` as `a·b^-1`,
+//! which keeps the simplifier down to collecting like terms and like factors.
+
+use crate::builtins;
+use crate::evaluator::MAX_CALL_DEPTH;
+use crate::functions;
+use crate::number_theory;
+use crate::parser::{Lexer, Token};
+use crate::settings::AngleMode;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Number(f64),
+    Symbol(String),
+    Sum(Vec<Expr>),
+    Product(Vec<Expr>),
+    Power(Box<Expr>, Box<Expr>),
+    Call(String, Vec<Expr>),
+}
+
+fn number(n: f64) -> Expr {
+    Expr::Number(n)
+}
+
+fn call(name: &str, args: Vec<Expr>) -> Expr {
+    Expr::Call(name.to_string(), args)
+}
+
+fn add(a: Expr, b: Expr) -> Expr {
+    Expr::Sum(vec![a, b])
+}
+
+fn mul(a: Expr, b: Expr) -> Expr {
+    Expr::Product(vec![a, b])
+}
+
+fn pow(a: Expr, b: Expr) -> Expr {
+    Expr::Power(Box::new(a), Box::new(b))
+}
+
+fn neg(a: Expr) -> Expr {
+    mul(number(-1.0), a)
+}
+
+fn div(a: Expr, b: Expr) -> Expr {
+    mul(a, pow(b, number(-1.0)))
+}
+
+fn sub(a: Expr, b: Expr) -> Expr {
+    add(a, neg(b))
+}
+
+impl Expr {
+    /// Parses a tokenized expression. Multiplication may be implicit, as in `3x^2`.
+    pub fn parse(tokens: &[Token]) -> Result<Expr, String> {
+        let mut parser = Parser { tokens, pos: 0 };
+        let expr = parser.sum()?;
+        match parser.tokens.get(parser.pos) {
+            None => Ok(expr),
+            Some(Token::RightParen) => Err("Mismatched parentheses".to_string()),
+            Some(token) => Err(format!("Unexpected token: {:?}", token)),
+        }
+    }
+
+    /// Parses `lhs = rhs` as `lhs - rhs`, the formula that is zero where the
+    /// equation holds; a formula without `=` is taken as equal to zero.
+    pub fn parse_equation(tokens: &[Token]) -> Result<Expr, String> {
+        let mut sides = tokens.split(|token| *token == Token::Equals);
+        let lhs = Expr::parse(sides.next().unwrap_or_default())?;
+        match (sides.next(), sides.next()) {
+            (None, _) => Ok(lhs),
+            (Some(rhs), None) => Ok(sub(lhs, Expr::parse(rhs)?)),
+            (Some(_), Some(_)) => Err("An equation has a single =".to_string()),
+        }
+    }
+
+    pub fn depends_on(&self, var: &str) -> bool {
+        match self {
+            Expr::Number(_) => false,
+            Expr::Symbol(name) => name == var,
+            Expr::Sum(items) | Expr::Product(items) | Expr::Call(_, items) => {
+                items.iter().any(|item| item.depends_on(var))
+            }
+            Expr::Power(base, exponent) => base.depends_on(var) || exponent.depends_on(var),
+        }
+    }
+
+    /// Every symbol the expression mentions, in order.
+    pub fn symbols(&self) -> BTreeSet<String> {
+        match self {
+            Expr::Number(_) => BTreeSet::new(),
+            Expr::Symbol(name) => BTreeSet::from([name.clone()]),
+            Expr::Sum(items) | Expr::Product(items) | Expr::Call(_, items) => {
+                items.iter().flat_map(Expr::symbols).collect()
+            }
+            Expr::Power(base, exponent) => &base.symbols() | &exponent.symbols(),
+        }
+    }
+
+    /// Replaces symbols by the expressions bound to them.
+    pub fn substitute(&self, bindings: &HashMap<String, Expr>) -> Expr {
+        match self {
+            Expr::Symbol(name) => bindings.get(name).cloned().unwrap_or_else(|| self.clone()),
+            Expr::Number(_) => self.clone(),
+            Expr::Sum(terms) => Expr::Sum(terms.iter().map(|t| t.substitute(bindings)).collect()),
+            Expr::Product(factors) => {
+                Expr::Product(factors.iter().map(|f| f.substitute(bindings)).collect())
+            }
+            Expr::Power(base, exponent) => {
+                pow(base.substitute(bindings), exponent.substitute(bindings))
+            }
+            Expr::Call(name, args) => Expr::Call(
+                name.clone(),
+                args.iter().map(|a| a.substitute(bindings)).collect(),
+            ),
+        }
+    }
+
+    /// Tokens the evaluator can run, with every operand parenthesized.
+    pub fn to_tokens(&self) -> Vec<Token> {
+        fn grouped(expr: &Expr) -> Vec<Token> {
+            let mut tokens = vec![Token::LeftParen];
+            tokens.extend(expr.to_tokens());
+            tokens.push(Token::RightParen);
+            tokens
+        }
+        fn joined(items: &[Expr], op: Token) -> Vec<Token> {
+            let mut tokens = Vec::new();
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    tokens.push(op.clone());
+                }
+                tokens.extend(grouped(item));
+            }
+            tokens
+        }
+        match self {
+            Expr::Number(n) if *n < 0.0 => vec![
+                Token::LeftParen,
+                Token::UnaryMinus,
+                Token::Number(-n),
+                Token::RightParen,
+            ],
+            Expr::Number(n) => vec![Token::Number(*n)],
+            Expr::Symbol(name) => vec![Token::Variable(name.clone())],
+            Expr::Sum(terms) => joined(terms, Token::Add),
+            Expr::Product(factors) => joined(factors, Token::Multiply),
+            Expr::Power(base, exponent) => {
+                let mut tokens = grouped(base);
+                tokens.push(Token::Power);
+                tokens.extend(grouped(exponent));
+                tokens
+            }
+            Expr::Call(name, args) => vec![Token::FunctionCall(
+                name.clone(),
+                args.iter().map(Expr::to_tokens).collect(),
+            )],
+        }
+    }
+
+    /// The simplified derivative with respect to `var`, with trigonometry in `angle` units.
+    pub fn derivative(&self, var: &str, angle: AngleMode) -> Result<Expr, String> {
+        Ok(simplify(&self.derive(var, angle, 0)?))
+    }
+
+    /// Replaces `diff` calls by the derivatives they stand for, so the formula
+    /// evaluates to numbers.
+    pub fn expand_derivatives(&self, angle: AngleMode) -> Result<Expr, String> {
+        let expand = |items: &[Expr]| {
+            items
+                .iter()
+                .map(|item| item.expand_derivatives(angle))
+                .collect::<Result<Vec<Expr>, String>>()
+        };
+        Ok(match self {
+            Expr::Number(_) | Expr::Symbol(_) => self.clone(),
+            Expr::Sum(terms) => Expr::Sum(expand(terms)?),
+            Expr::Product(factors) => Expr::Product(expand(factors)?),
+            Expr::Power(base, exponent) => pow(
+                base.expand_derivatives(angle)?,
+                exponent.expand_derivatives(angle)?,
+            ),
+            Expr::Call(name, args) => match (builtins::lookup(name), args.as_slice()) {
+                (Some(builtin), [expr, Expr::Symbol(var)]) if builtin.name == "diff" => {
+                    expr.expand_derivatives(angle)?.derivative(var, angle)?
+                }
+                _ => Expr::Call(name.clone(), expand(args)?),
+            },
+        })
+    }
+
+    fn derive(&self, var: &str, angle: AngleMode, depth: usize) -> Result<Expr, String> {
+        if !self.depends_on(var) {
+            return Ok(number(0.0));
+        }
+        let d = |e: &Expr| e.derive(var, angle, depth);
+        match self {
+            Expr::Number(_) => Ok(number(0.0)),
+            Expr::Symbol(_) => Ok(number(1.0)),
+            Expr::Sum(terms) => Ok(Expr::Sum(terms.iter().map(d).collect::<Result<_, _>>()?)),
+            Expr::Product(factors) => {
+                // Product rule: each factor differentiated in turn
+                let mut terms = Vec::new();
+                for (i, factor) in factors.iter().enumerate() {
+                    if factor.depends_on(var) {
+                        let mut term = factors.clone();
+                        term[i] = d(factor)?;
+                        terms.push(Expr::Product(term));
+                    }
+                }
+                Ok(Expr::Sum(terms))
+            }
+            Expr::Power(base, exponent) => {
+                let (u, v) = (base.as_ref().clone(), exponent.as_ref().clone());
+                if !v.depends_on(var) {
+                    let lowered = pow(u.clone(), sub(v.clone(), number(1.0)));
+                    return Ok(Expr::Product(vec![v, lowered, d(&u)?]));
+                }
+                // u^v = exp(v·ln u); e^v needs no logarithm
+                let ln_u = match &u {
+                    Expr::Symbol(name) if name == "e" => number(1.0),
+                    _ => call("log", vec![u.clone()]),
+                };
+                let mut rate = mul(d(&v)?, ln_u);
+                if u.depends_on(var) {
+                    rate = add(
+                        rate,
+                        Expr::Product(vec![v.clone(), d(&u)?, pow(u.clone(), number(-1.0))]),
+                    );
+                }
+                Ok(mul(self.clone(), rate))
+            }
+            Expr::Call(name, args) => self.derive_call(name, args, var, angle, depth),
+        }
+    }
+
+    fn derive_call(
+        &self,
+        name: &str,
+        args: &[Expr],
+        var: &str,
+        angle: AngleMode,
+        depth: usize,
+    ) -> Result<Expr, String> {
+        let d = |e: &Expr| e.derive(var, angle, depth);
+        let Some(builtin) = builtins::lookup(name) else {
+            return self.derive_custom(name, args, var, angle, depth);
+        };
+        let unsupported = || {
+            Err(format!(
+                "diff() cannot differentiate {}() with respect to {}",
+                name, var
+            ))
+        };
+        // Radians per unit of the session's angle mode
+        let k = || {
+            div(
+                mul(number(2.0), Expr::Symbol("pi".to_string())),
+                number(angle.per_turn()),
+            )
+        };
+        let trig = |e: Expr| match angle {
+            AngleMode::Radians => e,
+            _ => mul(e, k()),
+        };
+        let inverse_trig = |e: Expr| match angle {
+            AngleMode::Radians => e,
+            _ => div(e, k()),
+        };
+        let u = args[0].clone();
+        let f = |name: &str| call(name, vec![u.clone()]);
+        let outer = match builtin.name {
+            "diff" => {
+                let [expr, Expr::Symbol(inner)] = args else {
+                    return unsupported();
+                };
+                return expr.derive(inner, angle, depth)?.derive(var, angle, depth);
+            }
+            "sin" => trig(f("cos")),
+            "cos" => trig(neg(f("sin"))),
+            "tan" => trig(pow(f("cos"), number(-2.0))),
+            "arcsin" => inverse_trig(pow(
+                sub(number(1.0), pow(u.clone(), number(2.0))),
+                number(-0.5),
+            )),
+            "arccos" => inverse_trig(neg(pow(
+                sub(number(1.0), pow(u.clone(), number(2.0))),
+                number(-0.5),
+            ))),
+            "arctan" => inverse_trig(div(
+                number(1.0),
+                add(number(1.0), pow(u.clone(), number(2.0))),
+            )),
+            "atan2" => {
+                // atan2(y, x) turns through (x·dy - y·dx) / (x² + y²)
+                let (y, x) = (&args[0], &args[1]);
+                let turn = div(
+                    sub(mul(x.clone(), d(y)?), mul(y.clone(), d(x)?)),
+                    add(pow(x.clone(), number(2.0)), pow(y.clone(), number(2.0))),
+                );
+                return Ok(inverse_trig(turn));
+            }
+            "deg" => div(number(180.0), Expr::Symbol("pi".to_string())),
+            "rad" => div(Expr::Symbol("pi".to_string()), number(180.0)),
+            "sinh" => f("cosh"),
+            "cosh" => f("sinh"),
+            "tanh" => pow(f("cosh"), number(-2.0)),
+            "asinh" => pow(add(pow(u.clone(), number(2.0)), number(1.0)), number(-0.5)),
+            "acosh" => pow(sub(pow(u.clone(), number(2.0)), number(1.0)), number(-0.5)),
+            "atanh" => div(number(1.0), sub(number(1.0), pow(u.clone(), number(2.0)))),
+            "sqrt" => div(number(1.0), mul(number(2.0), f("sqrt"))),
+            "cbrt" => div(number(1.0), mul(number(3.0), pow(f("cbrt"), number(2.0)))),
+            "exp" => f("exp"),
+            "log" if args.len() == 2 => {
+                let quotient = div(f("log"), call("log", vec![args[1].clone()]));
+                return quotient.derive(var, angle, depth);
+            }
+            "log" => div(number(1.0), u.clone()),
+            "log10" => div(number(1.0), mul(u.clone(), call("log", vec![number(10.0)]))),
+            "log2" => div(number(1.0), mul(u.clone(), call("log", vec![number(2.0)]))),
+            "hypot" => {
+                let (a, b) = (&args[0], &args[1]);
+                return Ok(div(
+                    add(mul(a.clone(), d(a)?), mul(b.clone(), d(b)?)),
+                    self.clone(),
+                ));
+            }
+            "abs" => f("sign"),
+            // Flat wherever they are differentiable
+            "sign" | "floor" | "ceil" | "round" | "trunc" | "roundsig" | "mround" => number(0.0),
+            "fact" => {
+                let next = add(u.clone(), number(1.0));
+                mul(
+                    call("gamma", vec![next.clone()]),
+                    call("digamma", vec![next]),
+                )
+            }
+            "gamma" => mul(f("gamma"), f("digamma")),
+            "lgamma" => f("digamma"),
+            "beta" => {
+                let (a, b) = (&args[0], &args[1]);
+                let both = call("digamma", vec![add(a.clone(), b.clone())]);
+                let partial = |x: &Expr| sub(call("digamma", vec![x.clone()]), both.clone());
+                let rate = add(mul(partial(a), d(a)?), mul(partial(b), d(b)?));
+                return Ok(mul(self.clone(), rate));
+            }
+            "erf" => mul(
+                div(
+                    number(2.0),
+                    call("sqrt", vec![Expr::Symbol("pi".to_string())]),
+                ),
+                call("exp", vec![neg(pow(u.clone(), number(2.0)))]),
+            ),
+            "erfc" => neg(mul(
+                div(
+                    number(2.0),
+                    call("sqrt", vec![Expr::Symbol("pi".to_string())]),
+                ),
+                call("exp", vec![neg(pow(u.clone(), number(2.0)))]),
+            )),
+            "erfinv" => mul(
+                div(
+                    call("sqrt", vec![Expr::Symbol("pi".to_string())]),
+                    number(2.0),
+                ),
+                call("exp", vec![pow(f("erfinv"), number(2.0))]),
+            ),
+            "j0" => neg(f("j1")),
+            "j1" => sub(f("j0"), div(f("j1"), u.clone())),
+            "normpdf" | "normcdf" if args[1..].iter().all(|a| !a.depends_on(var)) => {
+                let mu = args.get(1).cloned().unwrap_or(number(0.0));
+                let sigma = args.get(2).cloned().unwrap_or(number(1.0));
+                let pdf = call("normpdf", vec![u.clone(), mu.clone(), sigma.clone()]);
+                if builtin.name == "normcdf" {
+                    pdf
+                } else {
+                    Expr::Product(vec![neg(sub(u.clone(), mu)), pow(sigma, number(-2.0)), pdf])
+                }
+            }
+            _ => return unsupported(),
+        };
+        if args.len() > 1 && args[1..].iter().any(|a| a.depends_on(var)) {
+            return unsupported();
+        }
+        Ok(mul(outer, d(&u)?))
+    }
+
+    /// Differentiates a function made with `define` through its body.
+    fn derive_custom(
+        &self,
+        name: &str,
+        args: &[Expr],
+        var: &str,
+        angle: AngleMode,
+        depth: usize,
+    ) -> Result<Expr, String> {
+        let func = functions::get_custom_function(name)
+            .ok_or_else(|| format!("Function '{}' is not defined", name))?;
+        if args.len() != func.parameters.len() {
+            return Err("Custom function argument count mismatch".to_string());
+        }
+        if depth >= MAX_CALL_DEPTH {
+            return Err(format!(
+                "Too many nested calls of custom function '{}'",
+                name
+            ));
+        }
+        let body = Expr::parse(&Lexer::formula(&func.expression).tokenize()?)?;
+        let bindings = func
+            .parameters
+            .into_iter()
+            .zip(args.iter().cloned())
+            .collect();
+        body.substitute(&bindings).derive(var, angle, depth + 1)
+    }
+
+    /// Whether a number written right before this factor still reads as a
+    /// product: `3x` does, but `3k` is 3000 and `3m` three metres.
+    fn reads_after_number(&self) -> bool {
+        let name = match self {
+            Expr::Symbol(name) => name,
+            Expr::Power(base, _) => match base.as_ref() {
+                Expr::Symbol(name) => name,
+                _ => return false,
+            },
+            _ => return false,
+        };
+        Lexer::new(&format!("2{}", name)).tokenize()
+            == Ok(vec![Token::Number(2.0), Token::Variable(name.clone())])
+    }
+
+    fn precedence(&self) -> u8 {
+        match self {
+            Expr::Sum(_) => 1,
+            Expr::Product(_) => 2,
+            Expr::Number(n) if *n < 0.0 => 2,
+            Expr::Power(_, exponent) if matches!(**exponent, Expr::Number(e) if e < 0.0) => 2,
+            Expr::Power(..) => 3,
+            _ => 4,
+        }
+    }
+
+    /// Renders the expression, parenthesized unless it binds at least as tightly as `min`.
+    fn wrapped(&self, min: u8) -> String {
+        if self.precedence() < min {
+            format!("({})", self)
+        } else {
+            self.to_string()
+        }
+    }
+
+    /// The numeric coefficient of a term and what it multiplies.
+    fn split_coefficient(&self) -> (f64, Expr) {
+        match self {
+            Expr::Number(n) => (*n, number(1.0)),
+            Expr::Product(factors) => match factors.split_first() {
+                Some((Expr::Number(c), rest)) => (*c, product(rest.to_vec())),
+                _ => (1.0, self.clone()),
+            },
+            _ => (1.0, self.clone()),
+        }
+    }
+}
+
+struct Parser<'a> {
+    tokens: &'a [Token],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<&Token> {
+        self.pos += 1;
+        self.tokens.get(self.pos - 1)
+    }
+
+    fn sum(&mut self) -> Result<Expr, String> {
+        let mut terms = vec![self.product()?];
+        loop {
+            match self.peek() {
+                Some(Token::Add) => {
+                    self.next();
+                    terms.push(self.product()?);
+                }
+                Some(Token::Subtract) => {
+                    self.next();
+                    terms.push(neg(self.product()?));
+                }
+                _ => break,
+            }
+        }
+        Ok(if terms.len() == 1 {
+            terms.remove(0)
+        } else {
+            Expr::Sum(terms)
+        })
+    }
+
+    fn product(&mut self) -> Result<Expr, String> {
+        let mut factors = vec![self.unary()?];
+        loop {
+            match self.peek() {
+                Some(Token::Multiply) => {
+                    self.next();
+                    factors.push(self.unary()?);
+                }
+                Some(Token::Divide) => {
+                    self.next();
+                    factors.push(pow(self.unary()?, number(-1.0)));
+                }
+                // Implicit multiplication, as in `3x` or `2 sin(x)`
+                Some(
+                    Token::Number(_)
+                    | Token::Rounded(_)
+                    | Token::Variable(_)
+                    | Token::Constant(_)
+                    | Token::FunctionCall(..)
+                    | Token::LeftParen
+                    | Token::Quantity(..),
+                ) => factors.push(self.unary()?),
+                _ => break,
+            }
+        }
+        Ok(if factors.len() == 1 {
+            factors.remove(0)
+        } else {
+            Expr::Product(factors)
+        })
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        if let Some(Token::UnaryMinus) = self.peek() {
+            self.next();
+            return Ok(neg(self.unary()?));
+        }
+        let base = self.postfix()?;
+        if let Some(Token::Power) = self.peek() {
+            self.next();
+            return Ok(pow(base, self.unary()?));
+        }
+        Ok(base)
+    }
+
+    fn postfix(&mut self) -> Result<Expr, String> {
+        let mut expr = self.atom()?;
+        loop {
+            match self.peek() {
+                Some(Token::Factorial) => expr = call("fact", vec![expr]),
+                Some(Token::Percent) => expr = div(expr, number(100.0)),
+                _ => return Ok(expr),
+            }
+            self.next();
+        }
+    }
+
+    fn atom(&mut self) -> Result<Expr, String> {
+        let token = self.next().ok_or("Missing operand")?.clone();
+        match token {
+            Token::Number(n) | Token::Rounded(n) => Ok(number(n)),
+            Token::Variable(name) | Token::Constant(name) => Ok(Expr::Symbol(name)),
+            // `3s` lexes as a quantity of seconds; read the unit as symbols
+            Token::Quantity(n, unit) => {
+                let symbols = Lexer::new(&unit.to_string())
+                    .tokenize()
+                    .and_then(|tokens| Expr::parse(&tokens))
+                    .map_err(|_| format!("{} {} cannot be used in a formula", n, unit))?;
+                Ok(mul(number(n), symbols))
+            }
+            Token::FunctionCall(name, args) => {
+                let args = args
+                    .iter()
+                    .map(|arg| Expr::parse(arg))
+                    .collect::<Result<Vec<Expr>, String>>()?;
+                Ok(Expr::Call(name, args))
+            }
+            Token::LeftParen => {
+                let expr = self.sum()?;
+                match self.next() {
+                    Some(Token::RightParen) => Ok(expr),
+                    _ => Err("Mismatched parentheses".to_string()),
+                }
+            }
+            other => Err(format!("{:?} cannot be used in a formula", other)),
+        }
+    }
+}
+
+/// Simplifies bottom-up: constants fold, like terms and like factors combine.
+pub fn simplify(expr: &Expr) -> Expr {
+    match expr {
+        Expr::Number(_) | Expr::Symbol(_) => expr.clone(),
+        Expr::Sum(terms) => sum(terms.iter().map(simplify).collect()),
+        Expr::Product(factors) => product(factors.iter().map(simplify).collect()),
+        Expr::Power(base, exponent) => power(simplify(base), simplify(exponent)),
+        Expr::Call(name, args) => Expr::Call(name.clone(), args.iter().map(simplify).collect()),
+    }
+}
+
+/// Sum of simplified terms.
+fn sum(terms: Vec<Expr>) -> Expr {
+    let mut constant = 0.0;
+    let mut collected: Vec<(f64, Expr)> = Vec::new();
+    let mut pending = terms;
+    while let Some(term) = pending.pop() {
+        match term {
+            Expr::Sum(inner) => pending.extend(inner),
+            Expr::Number(n) => constant += n,
+            term => {
+                let (c, rest) = term.split_coefficient();
+                match collected.iter_mut().find(|(_, r)| *r == rest) {
+                    Some((total, _)) => *total += c,
+                    None => collected.push((c, rest)),
+                }
+            }
+        }
+    }
+    // Popping reversed the order the terms were written in
+    collected.reverse();
+    let mut terms: Vec<Expr> = collected
+        .into_iter()
+        .filter(|(c, _)| *c != 0.0)
+        .map(|(c, rest)| product(vec![number(c), rest]))
+        .collect();
+    terms.sort_by(|a, b| degree(b).total_cmp(&degree(a)));
+    if constant != 0.0 || terms.is_empty() {
+        terms.push(number(constant));
+    }
+    if terms.len() == 1 {
+        terms.remove(0)
+    } else {
+        Expr::Sum(terms)
+    }
+}
+
+/// Product of simplified factors.
+fn product(factors: Vec<Expr>) -> Expr {
+    let mut coefficient = 1.0;
+    let mut powers: Vec<(Expr, Expr)> = Vec::new();
+    let mut pending = factors;
+    pending.reverse();
+    while let Some(factor) = pending.pop() {
+        let (base, exponent) = match factor {
+            Expr::Product(inner) => {
+                pending.extend(inner.into_iter().rev());
+                continue;
+            }
+            Expr::Number(n) => {
+                coefficient *= n;
+                continue;
+            }
+            Expr::Power(base, exponent) => (*base, *exponent),
+            other => (other, number(1.0)),
+        };
+        match powers.iter_mut().find(|(b, _)| *b == base) {
+            Some((_, total)) => *total = sum(vec![total.clone(), exponent]),
+            None => powers.push((base, exponent)),
+        }
+    }
+    if coefficient == 0.0 {
+        return number(0.0);
+    }
+    let mut factors = Vec::new();
+    for (base, exponent) in powers {
+        match power(base, exponent) {
+            Expr::Number(n) => coefficient *= n,
+            Expr::Product(inner) => factors.extend(inner),
+            other => factors.push(other),
+        }
+    }
+    factors.sort_by_cached_key(order);
+    // A number times a sum distributes: -(x - 1) is 1 - x
+    if let [Expr::Sum(terms)] = factors.as_slice()
+        && coefficient != 1.0
+    {
+        return sum(terms
+            .iter()
+            .map(|term| product(vec![number(coefficient), term.clone()]))
+            .collect());
+    }
+    if coefficient != 1.0 || factors.is_empty() {
+        factors.insert(0, number(coefficient));
+    }
+    if factors.len() == 1 {
+        factors.remove(0)
+    } else {
+        Expr::Product(factors)
+    }
+}
+
+/// A simplified base raised to a simplified exponent.
+fn power(base: Expr, exponent: Expr) -> Expr {
+    let integer = |e: &Expr| matches!(e, Expr::Number(n) if n.fract() == 0.0);
+    match (base, exponent) {
+        (_, Expr::Number(0.0)) => number(1.0),
+        (base, Expr::Number(1.0)) => base,
+        (Expr::Number(1.0), _) => number(1.0),
+        (Expr::Number(b), Expr::Number(e))
+            if {
+                let folded = b.powf(e);
+                folded.is_finite() && (e.fract() == 0.0 || folded.fract() == 0.0)
+            } =>
+        {
+            number(b.powf(e))
+        }
+        // (x^a)^n = x^(a·n) for whole n
+        (Expr::Power(base, inner), exponent) if integer(&exponent) => {
+            power(*base, product(vec![*inner, exponent]))
+        }
+        (Expr::Product(factors), exponent) if integer(&exponent) => product(
+            factors
+                .into_iter()
+                .map(|f| power(f, exponent.clone()))
+                .collect(),
+        ),
+        (base, exponent) => pow(base, exponent),
+    }
+}
+
+/// Powers of sums above this are left as they are rather than multiplied out.
+const MAX_EXPANDED_POWER: f64 = 100.0;
+
+/// Terms a single multiplication may produce while expanding.
+const MAX_EXPANDED_TERMS: usize = 1000;
+
+/// Multiplies out products and whole powers of sums, so `(x + 1)^2` becomes
+/// `x^2 + 2x + 1`.
+pub fn expand(expr: &Expr) -> Result<Expr, String> {
+    expanded(simplify(expr))
+}
+
+fn expanded(expr: Expr) -> Result<Expr, String> {
+    Ok(match expr {
+        Expr::Sum(terms) => sum(terms.into_iter().map(expanded).collect::<Result<_, _>>()?),
+        Expr::Product(factors) => {
+            let mut result = number(1.0);
+            for factor in factors {
+                result = multiply_out(result, expanded(factor)?)?;
+            }
+            result
+        }
+        Expr::Power(base, exponent) => {
+            let base = expanded(*base)?;
+            match (&base, *exponent) {
+                (Expr::Sum(_), Expr::Number(n))
+                    if n.fract() == 0.0 && (2.0..=MAX_EXPANDED_POWER).contains(&n) =>
+                {
+                    let mut result = base.clone();
+                    for _ in 1..n as usize {
+                        result = multiply_out(result, base.clone())?;
+                    }
+                    result
+                }
+                (_, exponent) => power(base, exponent),
+            }
+        }
+        Expr::Call(name, args) => Expr::Call(
+            name,
+            args.into_iter().map(expanded).collect::<Result<_, _>>()?,
+        ),
+        other => other,
+    })
+}
+
+/// The product of two expanded expressions, with every term of one multiplied
+/// by every term of the other.
+fn multiply_out(a: Expr, b: Expr) -> Result<Expr, String> {
+    let terms = |e: Expr| match e {
+        Expr::Sum(terms) => terms,
+        e => vec![e],
+    };
+    let (a, b) = (terms(a), terms(b));
+    if a.len() * b.len() > MAX_EXPANDED_TERMS {
+        return Err(format!(
+            "expand() would produce more than {} terms",
+            MAX_EXPANDED_TERMS
+        ));
+    }
+    Ok(sum(a
+        .iter()
+        .flat_map(|x| b.iter().map(move |y| product(vec![x.clone(), y.clone()])))
+        .collect()))
+}
+
+/// Coefficients of an expanded polynomial in `var`, lowest power first.
+fn polynomial(expr: &Expr, var: &str) -> Option<Vec<f64>> {
+    let terms = match expr {
+        Expr::Sum(terms) => terms.clone(),
+        e => vec![e.clone()],
+    };
+    let mut coefficients = Vec::new();
+    for term in terms {
+        let (c, rest) = term.split_coefficient();
+        let power = match rest {
+            Expr::Number(1.0) => 0,
+            Expr::Symbol(name) if name == var => 1,
+            Expr::Power(base, exponent) => match (*base, *exponent) {
+                (Expr::Symbol(name), Expr::Number(n))
+                    if name == var
+                        && n.fract() == 0.0
+                        && (2.0..=MAX_EXPANDED_POWER).contains(&n) =>
+                {
+                    n as usize
+                }
+                _ => return None,
+            },
+            _ => return None,
+        };
+        if coefficients.len() <= power {
+            coefficients.resize(power + 1, 0.0);
+        }
+        coefficients[power] += c;
+    }
+    Some(coefficients)
+}
+
+/// Factors a polynomial in `var` with rational coefficients into its content,
+/// a power of `var`, linear factors for its rational roots and whatever is left.
+/// Anything else comes back expanded.
+pub fn factor(expr: &Expr, var: &str) -> Result<Expr, String> {
+    let expanded = expand(expr)?;
+    let Some(coefficients) = polynomial(&expanded, var) else {
+        return Ok(expanded);
+    };
+    // Scale to whole numbers small enough to stay exact as floats
+    let scale = coefficients
+        .iter()
+        .map(|c| fraction(*c).1)
+        .fold(1.0, |l, q| {
+            l * q / number_theory::gcd(l as u64, q as u64) as f64
+        });
+    let exact = |c: &f64| (c * scale).round();
+    // Relative to the coefficient, so a tiny one is not taken for zero
+    if coefficients.iter().any(|c| {
+        let whole = exact(c);
+        whole.abs() > 2f64.powi(53) || (c * scale - whole).abs() > 1e-9 * (c * scale).abs()
+    }) {
+        return Ok(expanded);
+    }
+    let mut whole: Vec<i128> = coefficients.iter().map(|c| exact(c) as i128).collect();
+    while whole.last() == Some(&0) {
+        whole.pop();
+    }
+    let Some(&leading) = whole.last() else {
+        return Ok(number(0.0));
+    };
+    let content = whole
+        .iter()
+        .fold(0, |g, c| number_theory::gcd(g, c.unsigned_abs() as u64)) as i128
+        * leading.signum();
+    whole.iter_mut().for_each(|c| *c /= content);
+    let lowest = whole.iter().position(|c| *c != 0).unwrap_or(0);
+    whole.drain(..lowest);
+
+    let x = Expr::Symbol(var.to_string());
+    let mut roots: Vec<(i128, i128, usize)> = Vec::new();
+    while whole.len() > 1 {
+        let Some((p, q)) = rational_root(&whole) else {
+            break;
+        };
+        whole = divide_root(&whole, p, q);
+        match roots.iter_mut().find(|(rp, rq, _)| (*rp, *rq) == (p, q)) {
+            Some((_, _, multiplicity)) => *multiplicity += 1,
+            None => roots.push((p, q, 1)),
+        }
+    }
+    roots.sort_by(|(p1, q1, _), (p2, q2, _)| {
+        let key = |p: i128, q: i128| ((p.abs() as f64) / q as f64, p < 0);
+        key(*p1, *q1).partial_cmp(&key(*p2, *q2)).unwrap()
+    });
+
+    let constant = content as f64 / scale;
+    let mut factors = Vec::new();
+    if constant != 1.0 {
+        factors.push(number(constant));
+    }
+    if lowest > 0 {
+        factors.push(power(x.clone(), number(lowest as f64)));
+    }
+    for (p, q, multiplicity) in roots {
+        let linear = sum(vec![
+            product(vec![number(q as f64), x.clone()]),
+            number(-p as f64),
+        ]);
+        factors.push(power(linear, number(multiplicity as f64)));
+    }
+    if whole.len() > 1 {
+        factors.push(sum(whole
+            .iter()
+            .enumerate()
+            .map(|(i, c)| product(vec![number(*c as f64), power(x.clone(), number(i as f64))]))
+            .collect()));
+    }
+    // Built directly, since product() would distribute the constant again
+    Ok(match factors.len() {
+        0 => number(1.0),
+        1 => factors.remove(0),
+        _ => Expr::Product(factors),
+    })
+}
+
+/// A root p/q of a polynomial with whole coefficients, lowest power first and a
+/// non-zero constant term.
+fn rational_root(coefficients: &[i128]) -> Option<(i128, i128)> {
+    let first = coefficients[0].unsigned_abs() as u64;
+    let last = coefficients[coefficients.len() - 1].unsigned_abs() as u64;
+    let numerators = number_theory::divisors(first);
+    let denominators = number_theory::divisors(last);
+    denominators.iter().find_map(|&q| {
+        numerators.iter().find_map(|&p| {
+            let (p, q) = (p as i128, q as i128);
+            if number_theory::gcd(p as u64, q as u64) != 1 {
+                return None;
+            }
+            [p, -p]
+                .into_iter()
+                .find(|p| scaled_value(coefficients, *p, q) == Some(0))
+                .map(|p| (p, q))
+        })
+    })
+}
+
+/// q^n · P(p/q), which is a whole number; `None` if it overflows.
+fn scaled_value(coefficients: &[i128], p: i128, q: i128) -> Option<i128> {
+    let n = coefficients.len() as u32 - 1;
+    let mut total: i128 = 0;
+    for (i, c) in coefficients.iter().enumerate() {
+        let term = c
+            .checked_mul(p.checked_pow(i as u32)?)?
+            .checked_mul(q.checked_pow(n - i as u32)?)?;
+        total = total.checked_add(term)?;
+    }
+    Some(total)
+}
+
+/// Divides out the factor (q·x - p), which divides the polynomial exactly.
+fn divide_root(coefficients: &[i128], p: i128, q: i128) -> Vec<i128> {
+    // Synthetic division from the top, since a_i = q·b_(i-1) - p·b_i
+    let n = coefficients.len() - 1;
+    let mut quotient = vec![0; n];
+    let mut carry = 0;
+    for i in (1..=n).rev() {
+        quotient[i - 1] = (coefficients[i] + carry) / q;
+        carry = p * quotient[i - 1];
+    }
+    quotient
+}
+
+/// Sort key for factors: numbers, then variables by name, then everything else.
+fn order(factor: &Expr) -> (u8, String) {
+    match factor {
+        Expr::Number(_) => (0, String::new()),
+        Expr::Symbol(name) => (1, name.clone()),
+        Expr::Power(base, _) => order(base),
+        Expr::Call(..) => (2, factor.to_string()),
+        _ => (3, factor.to_string()),
+    }
+}
+
+/// Total power of the variables in a term, so polynomials print highest first.
+fn degree(term: &Expr) -> f64 {
+    match term {
+        Expr::Symbol(_) => 1.0,
+        Expr::Power(base, exponent) => match (base.as_ref(), exponent.as_ref()) {
+            (Expr::Symbol(_), Expr::Number(e)) => *e,
+            _ => 0.0,
+        },
+        Expr::Product(factors) => factors.iter().map(degree).sum(),
+        _ => 0.0,
+    }
+}
+
+/// Writes a coefficient as a fraction with a small denominator where one fits.
+fn fraction(c: f64) -> (f64, f64) {
+    (1..=1000)
+        .map(f64::from)
+        .find(|q| {
+            let p = c * q;
+            (p - p.round()).abs() <= 1e-12 * p.abs()
+        })
+        .map_or((c, 1.0), |q| ((c * q).round(), q))
+}
+
+fn format_number(n: f64) -> String {
+    format!("{}", n)
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Expr::Number(n) => write!(f, "{}", format_number(*n)),
+            Expr::Symbol(name) => write!(f, "{}", name),
+            Expr::Sum(terms) => {
+                for (i, term) in terms.iter().enumerate() {
+                    let (c, rest) = term.split_coefficient();
+                    let negative = c < 0.0;
+                    let magnitude = if negative {
+                        product(vec![number(-c), rest])
+                    } else {
+                        term.clone()
+                    };
+                    match (i, negative) {
+                        (0, true) => write!(f, "-{}", magnitude.wrapped(2))?,
+                        (0, false) => write!(f, "{}", magnitude.wrapped(2))?,
+                        (_, true) => write!(f, " - {}", magnitude.wrapped(2))?,
+                        (_, false) => write!(f, " + {}", magnitude.wrapped(2))?,
+                    }
+                }
+                Ok(())
+            }
+            Expr::Product(factors) => {
+                let mut coefficient = 1.0;
+                let mut numerator = Vec::new();
+                let mut denominator = Vec::new();
+                for factor in factors {
+                    match factor {
+                        Expr::Number(n) => coefficient *= n,
+                        Expr::Power(base, exponent) => match exponent.as_ref() {
+                            Expr::Number(e) if *e < 0.0 => {
+                                denominator.push(power(base.as_ref().clone(), number(-e)))
+                            }
+                            _ => numerator.push(factor),
+                        },
+                        _ => numerator.push(factor),
+                    }
+                }
+                let (p, q) = fraction(coefficient);
+                if p < 0.0 {
+                    write!(f, "-")?;
+                }
+                let mut text: Vec<String> = numerator.iter().map(|n| n.wrapped(2)).collect();
+                if p.abs() != 1.0 || numerator.is_empty() {
+                    // A coefficient runs into a variable after it, as in 3x^2
+                    match numerator.first() {
+                        Some(first) if first.reads_after_number() => {
+                            text[0] = format!("{}{}", format_number(p.abs()), text[0])
+                        }
+                        _ => text.insert(0, format_number(p.abs())),
+                    }
+                }
+                write!(f, "{}", text.join(" * "))?;
+                let mut below = denominator;
+                if q != 1.0 {
+                    below.insert(0, number(q));
+                }
+                match below.len() {
+                    0 => Ok(()),
+                    1 => write!(f, " / {}", below[0].wrapped(3)),
+                    _ => write!(f, " / {}", Expr::Product(below).wrapped(3)),
+                }
+            }
+            // x^-1 prints as 1 / x
+            Expr::Power(_, exponent) if matches!(**exponent, Expr::Number(e) if e < 0.0) => {
+                write!(f, "{}", Expr::Product(vec![self.clone()]))
+            }
+            Expr::Power(base, exponent) => {
+                write!(f, "{}^{}", base.wrapped(4), exponent.wrapped(4))
+            }
+            Expr::Call(name, args) => {
+                let args: Vec<String> = args.iter().map(Expr::to_string).collect();
+                write!(f, "{}({})", name, args.join(", "))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn derivative(input: &str, angle: AngleMode) -> String {
+        let tokens = Lexer::formula(input).tokenize().unwrap();
+        Expr::parse(&tokens)
+            .unwrap()
+            .derivative("x", angle)
+            .unwrap()
+            .to_string()
+    }
+
+    fn d(input: &str) -> String {
+        derivative(input, AngleMode::Radians)
+    }
+
+    #[test]
+    fn test_simplify() {
+        let simplified = |input: &str| {
+            let tokens = Lexer::formula(input).tokenize().unwrap();
+            simplify(&Expr::parse(&tokens).unwrap()).to_string()
+        };
+        assert_eq!(simplified("x + x + 1 + 2"), "2x + 3");
+        assert_eq!(simplified("x * x^2 / x"), "x^2");
+        assert_eq!(simplified("3x - 3 * x"), "0");
+        assert_eq!(simplified("(x + 1)(x + 1)"), "(x + 1)^2");
+        assert_eq!(simplified("x / 2 - y / (2 * z)"), "x / 2 - y / (2z)");
+        assert_eq!(simplified("2^3 * a^0 * b^1"), "8b");
+        assert_eq!(simplified("-(x - 1)"), "-x + 1");
+        // Like terms and factors meet whatever order they were written in
+        assert_eq!(simplified("x * y - y * x"), "0");
+        assert_eq!(simplified("x^-1 * x^2"), "x");
+        assert_eq!(simplified("x / x"), "1");
+        assert_eq!(simplified("(2x)^3"), "8x^3");
+    }
+
+    #[test]
+    fn test_powers_fold_only_when_exact() {
+        let simplified = |input: &str| {
+            let tokens = Lexer::formula(input).tokenize().unwrap();
+            simplify(&Expr::parse(&tokens).unwrap()).to_string()
+        };
+        assert_eq!(simplified("4^0.5"), "2");
+        assert_eq!(simplified("2^0.5"), "2^0.5");
+        // Not folded to infinity
+        assert_eq!(simplified("0^-1"), "1 / 0");
+        // (x^2)^0.5 is |x|, not x, so only whole outer powers combine
+        assert_eq!(simplified("(x^2)^0.5"), "(x^2)^0.5");
+        assert_eq!(simplified("(x^0.5)^2"), "x");
+        assert_eq!(simplified("x^0"), "1");
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let parse = |input: &str| Expr::parse(&Lexer::formula(input).tokenize().unwrap());
+        let equation =
+            |input: &str| Expr::parse_equation(&Lexer::formula(input).tokenize().unwrap());
+        assert_eq!(parse("x +"), Err("Missing operand".to_string()));
+        assert_eq!(parse("(x"), Err("Mismatched parentheses".to_string()));
+        assert_eq!(parse("x)"), Err("Mismatched parentheses".to_string()));
+        assert_eq!(parse("x y").unwrap().to_string(), "x * y");
+        assert_eq!(simplify(&equation("2x = 4").unwrap()).to_string(), "2x - 4");
+        assert_eq!(
+            equation("x = 1 = 2"),
+            Err("An equation has a single =".to_string())
+        );
+        assert!(equation("= 3").is_err());
+        assert!(equation("x =").is_err());
+    }
+
+    #[test]
+    fn test_expand_and_factor() {
+        let parse = |input: &str| Expr::parse(&Lexer::formula(input).tokenize().unwrap()).unwrap();
+        let expanded = |input: &str| expand(&parse(input)).unwrap().to_string();
+        let factored = |input: &str| factor(&parse(input), "x").unwrap().to_string();
+        assert_eq!(expanded("(x + 1)^3"), "x^3 + 3x^2 + 3x + 1");
+        assert_eq!(expanded("(x - y)(x + y)"), "x^2 - y^2");
+        assert_eq!(expanded("2(x + 1) - 2x"), "2");
+        assert_eq!(expanded("sin((x + 1)^2)"), "sin(x^2 + 2x + 1)");
+        assert!(expand(&parse("(x + y + z)^60")).is_err());
+        // Powers that are not whole, or too high, stay as they are
+        assert_eq!(expanded("(x + 1)^2.5"), "(x + 1)^2.5");
+        assert_eq!(expanded("(x + 1)^101"), "(x + 1)^101");
+        assert_eq!(expanded("(x + 1)^-2"), "1 / (x + 1)^2");
+        assert_eq!(factored("x^2 - 1"), "(x - 1) * (x + 1)");
+        assert_eq!(factored("x^3 + 3x^2 + 3x + 1"), "(x + 1)^3");
+        assert_eq!(factored("2x^3 - 2x"), "2x * (x - 1) * (x + 1)");
+        assert_eq!(factored("4x^2 - 1"), "(2x - 1) * (2x + 1)");
+        assert_eq!(factored("x^2 / 2 - 2"), "(x - 2) * (x + 2) / 2");
+        assert_eq!(factored("1 - x^2"), "-(x - 1) * (x + 1)");
+        assert_eq!(factored("x^3 - 2x"), "x * (x^2 - 2)");
+        assert_eq!(factored("sin(x) + 1"), "sin(x) + 1");
+    }
+
+    #[test]
+    fn test_factor_limits() {
+        let parse = |input: &str| Expr::parse(&Lexer::formula(input).tokenize().unwrap()).unwrap();
+        let factored = |input: &str| factor(&parse(input), "x").unwrap().to_string();
+        // Rational roots with a leading coefficient, and an irreducible remainder
+        assert_eq!(factored("6x^2 + 5x + 1"), "(3x + 1) * (2x + 1)");
+        assert_eq!(factored("x^4 - 1"), "(x - 1) * (x + 1) * (x^2 + 1)");
+        assert_eq!(factored("x^2 - 2"), "x^2 - 2");
+        assert_eq!(factored("0.1x^2 - 0.1"), "(x - 1) * (x + 1) / 10");
+        // A coefficient too small to write as a fraction is kept, not rounded to 0
+        assert_eq!(factored("x^2 + 1e-20x"), "x^2 + 0.00000000000000000001x");
+        // Other variables make it no polynomial in x
+        assert_eq!(factored("x^2 - y^2"), "x^2 - y^2");
+        assert_eq!(factored("0 * x"), "0");
+        assert_eq!(factored("5"), "5");
+        assert_eq!(factored("-x"), "-x");
+    }
+
+    #[test]
+    fn test_derivatives() {
+        assert_eq!(d("x^3"), "3x^2");
+        assert_eq!(d("x^2 + 3x + 1"), "2x + 3");
+        assert_eq!(d("x^2 - 3x"), "2x - 3");
+        assert_eq!(d("a * x^2"), "2a * x");
+        assert_eq!(d("1 / x"), "-1 / x^2");
+        assert_eq!(d("sqrt(x)"), "1 / (2 * sqrt(x))");
+        assert_eq!(d("x * sin(x)"), "x * cos(x) + sin(x)");
+        assert_eq!(d("sin(x)^2"), "2 * cos(x) * sin(x)");
+        assert_eq!(d("exp(2x)"), "2 * exp(2x)");
+        assert_eq!(d("e^x"), "e^x");
+        assert_eq!(d("2^x"), "2^x * log(2)");
+        assert_eq!(d("ln(x)"), "1 / x");
+        assert_eq!(d("x^x"), "x^x * (log(x) + 1)");
+        assert_eq!(d("cos(x)"), "-sin(x)");
+        assert_eq!(d("diff(x^3, x)"), "6x");
+        assert_eq!(d("y^2"), "0");
+        assert_eq!(
+            derivative("sin(x)", AngleMode::Degrees),
+            "pi * cos(x) / 180"
+        );
+        let tokens = Lexer::formula("max(x, 1)").tokenize().unwrap();
+        assert!(
+            Expr::parse(&tokens)
+                .unwrap()
+                .derivative("x", AngleMode::Radians)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_derivatives_of_builtins() {
+        assert_eq!(d("tan(x)"), "1 / cos(x)^2");
+        assert_eq!(d("atan(x)"), "1 / (x^2 + 1)");
+        assert_eq!(d("abs(x)"), "sign(x)");
+        assert_eq!(d("sqrt(x^2 + 1)"), "x / sqrt(x^2 + 1)");
+        assert_eq!(d("fact(x)"), "digamma(x + 1) * gamma(x + 1)");
+        assert_eq!(d("x^y"), "x^(y - 1) * y");
+        assert_eq!(d("y^x"), "y^x * log(y)");
+        assert_eq!(d("normcdf(x, 0, 1)"), "normpdf(x, 0, 1)");
+        let derive = |input: &str| {
+            let tokens = Lexer::formula(input).tokenize().unwrap();
+            Expr::parse(&tokens)
+                .unwrap()
+                .derivative("x", AngleMode::Radians)
+        };
+        // Only the first argument may depend on the variable
+        assert_eq!(
+            derive("normcdf(1, x, 1)"),
+            Err("diff() cannot differentiate normcdf() with respect to x".to_string())
+        );
+        assert_eq!(
+            derive("foo(x)"),
+            Err("Function 'foo' is not defined".to_string())
+        );
+        // A call that does not mention x is a constant, defined or not
+        assert_eq!(derive("foo(y)").unwrap().to_string(), "0");
+    }
+}
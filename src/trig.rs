@@ -0,0 +1,179 @@
+//! Trigonometry in the session's angle unit, exact at multiples of 15° (π/12).
+//!
+//! An argument within a few ulps of `k·π/12` is treated as exactly that angle,
+//! which covers every multiple of π/6 and π/4: `sin(pi)` is 0 and `cos(pi/3)` is
+//! 0.5. Other arguments, including genuinely tiny ones, are left untouched.
+
+use crate::settings::AngleMode;
+use std::f64::consts::FRAC_1_SQRT_2;
+
+const SQRT_3_2: f64 = 0.8660254037844386;
+
+/// `k` such that `x` is `k` twelfths of a half turn, within rounding error.
+fn special_angle(x: f64, mode: AngleMode) -> Option<i64> {
+    let q = x * 24.0 / mode.per_turn();
+    let k = q.round();
+    if k == 0.0 || k.abs() > (1u64 << 40) as f64 {
+        return None;
+    }
+    if (q - k).abs() <= 4.0 * f64::EPSILON * k.abs() {
+        Some(k as i64)
+    } else {
+        None
+    }
+}
+
+/// Exact `sin(k·15°)` when it has a simple closed form.
+fn sin_table(k: i64) -> Option<f64> {
+    let value = match k.rem_euclid(24) {
+        0 | 12 => 0.0,
+        2 | 10 => 0.5,
+        3 | 9 => FRAC_1_SQRT_2,
+        4 | 8 => SQRT_3_2,
+        6 => 1.0,
+        14 | 22 => -0.5,
+        15 | 21 => -FRAC_1_SQRT_2,
+        16 | 20 => -SQRT_3_2,
+        18 => -1.0,
+        _ => return None,
+    };
+    Some(value)
+}
+
+pub fn radians_per_unit(mode: AngleMode) -> f64 {
+    match mode {
+        AngleMode::Radians => 1.0,
+        _ => AngleMode::Radians.per_turn() / mode.per_turn(),
+    }
+}
+
+/// `x` less its whole turns, which `%` removes exactly in degrees and gradians;
+/// a turn of radians is not a float, so those are left to the library functions.
+fn reduced(x: f64, mode: AngleMode) -> f64 {
+    match mode {
+        AngleMode::Radians => x,
+        _ => x % mode.per_turn(),
+    }
+}
+
+fn to_radians(x: f64, mode: AngleMode) -> f64 {
+    match mode {
+        AngleMode::Radians => x,
+        _ => x * AngleMode::Radians.per_turn() / mode.per_turn(),
+    }
+}
+
+/// Converts an angle in radians to `mode`, landing exactly on special angles in degrees or gradians.
+pub fn from_radians(x: f64, mode: AngleMode) -> f64 {
+    if mode == AngleMode::Radians {
+        return x;
+    }
+    let value = x * mode.per_turn() / AngleMode::Radians.per_turn();
+    match special_angle(value, mode) {
+        Some(k) => k as f64 * mode.per_turn() / 24.0,
+        None => value,
+    }
+}
+
+pub fn sin(x: f64, mode: AngleMode) -> f64 {
+    let x = reduced(x, mode);
+    special_angle(x, mode)
+        .and_then(sin_table)
+        .unwrap_or_else(|| to_radians(x, mode).sin())
+}
+
+pub fn cos(x: f64, mode: AngleMode) -> f64 {
+    let x = reduced(x, mode);
+    special_angle(x, mode)
+        .and_then(|k| sin_table(k + 6))
+        .unwrap_or_else(|| to_radians(x, mode).cos())
+}
+
+pub fn tan(x: f64, mode: AngleMode) -> Result<f64, String> {
+    let x = reduced(x, mode);
+    if let Some(k) = special_angle(x, mode)
+        && let (Some(s), Some(c)) = (sin_table(k), sin_table(k + 6))
+    {
+        if c == 0.0 {
+            return Err("tan() is undefined at odd multiples of a quarter turn".to_string());
+        }
+        return Ok(match k.rem_euclid(12) {
+            0 => 0.0,
+            3 => 1.0,
+            9 => -1.0,
+            _ => s / c,
+        });
+    }
+    Ok(to_radians(x, mode).tan())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::PI;
+
+    #[test]
+    fn test_special_angles_are_exact() {
+        assert_eq!(sin(PI, AngleMode::Radians), 0.0);
+        assert_eq!(cos(PI / 3.0, AngleMode::Radians), 0.5);
+        assert_eq!(cos(PI / 2.0, AngleMode::Radians), 0.0);
+        assert_eq!(sin(-PI / 6.0, AngleMode::Radians), -0.5);
+        assert_eq!(tan(PI / 4.0, AngleMode::Radians).unwrap(), 1.0);
+        assert_eq!(sin(2.0 * PI, AngleMode::Radians), 0.0);
+        assert_eq!(sin(30.0, AngleMode::Degrees), 0.5);
+        assert_eq!(cos(100.0, AngleMode::Gradians), 0.0);
+        assert_eq!(sin(45.0, AngleMode::Degrees), FRAC_1_SQRT_2);
+        assert!(tan(PI / 2.0, AngleMode::Radians).is_err());
+        assert!(tan(270.0, AngleMode::Degrees).is_err());
+        assert_eq!(tan(-45.0, AngleMode::Degrees).unwrap(), -1.0);
+        assert_eq!(tan(150.0, AngleMode::Gradians).unwrap(), -1.0);
+        assert_eq!(tan(60.0, AngleMode::Degrees).unwrap(), SQRT_3_2 / 0.5);
+        // 15° has no entry in the table and is computed as usual
+        assert_eq!(sin(15.0, AngleMode::Degrees), (PI / 12.0).sin());
+    }
+
+    #[test]
+    fn test_many_turns() {
+        // Whole turns come off exactly in degrees and gradians, however many there are
+        assert_eq!(cos(3.6e20, AngleMode::Degrees), 1.0);
+        assert_eq!(sin(3600000030.0, AngleMode::Degrees), 0.5);
+        assert_eq!(cos(-4e20, AngleMode::Gradians), 1.0);
+        assert!(tan(9000000000000090.0, AngleMode::Degrees).is_err());
+        assert_eq!(
+            sin(1e300, AngleMode::Degrees),
+            sin(1e300 % 360.0, AngleMode::Degrees)
+        );
+        // Radians have no exact turn; past 2^40 twelfths of π nothing is snapped
+        assert_eq!(sin(1e15, AngleMode::Radians), 1e15f64.sin());
+        assert!(sin(f64::NAN, AngleMode::Degrees).is_nan());
+        assert!(cos(f64::INFINITY, AngleMode::Radians).is_nan());
+        assert!(tan(f64::INFINITY, AngleMode::Degrees).unwrap().is_nan());
+    }
+
+    #[test]
+    fn test_small_arguments_are_kept() {
+        assert_eq!(sin(1e-9, AngleMode::Radians), 1e-9f64.sin());
+        assert!(sin(1e-300, AngleMode::Radians) > 0.0);
+        // Close to, but measurably away from, a special angle
+        assert_ne!(sin(PI + 1e-10, AngleMode::Radians), 0.0);
+        assert_ne!(cos(60.0000000001, AngleMode::Degrees), 0.5);
+        // Close to a pole, tan() is large rather than an error
+        assert!(tan(PI / 2.0 + 1e-10, AngleMode::Radians).unwrap() < -1e9);
+        assert!(tan(89.9999999, AngleMode::Degrees).unwrap() > 1e8);
+    }
+
+    #[test]
+    fn test_inverse_results_snap_in_degrees() {
+        assert_eq!(from_radians(0.5f64.asin(), AngleMode::Degrees), 30.0);
+        assert_eq!(from_radians(1f64.atan(), AngleMode::Gradians), 50.0);
+        assert_eq!(
+            from_radians(0.5f64.asin(), AngleMode::Radians),
+            0.5f64.asin()
+        );
+        assert_eq!(from_radians(-PI, AngleMode::Degrees), -180.0);
+        // Results away from a special angle are only converted
+        let angle = 0.3f64.acos();
+        assert_eq!(from_radians(angle, AngleMode::Degrees), angle * 180.0 / PI);
+        assert_eq!(from_radians(0.0, AngleMode::Degrees), 0.0);
+    }
+}
@@ -0,0 +1,124 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+static NEXT_SOURCE: AtomicU64 = AtomicU64::new(1);
+
+/// A value with a standard uncertainty, propagated to first order.
+///
+/// Every `a ± b` literal is an independent source of error. Results keep the
+/// contribution of each source separately, so reusing the same value (for
+/// example a custom function parameter used twice) stays correlated:
+/// `x - x` is exactly zero rather than `0 ± sqrt(2)·σ`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Uncertain {
+    pub value: f64,
+    terms: BTreeMap<u64, f64>,
+}
+
+impl Uncertain {
+    pub fn new(value: f64, std_dev: f64) -> Self {
+        let mut terms = BTreeMap::new();
+        if std_dev != 0.0 {
+            terms.insert(NEXT_SOURCE.fetch_add(1, Ordering::Relaxed), std_dev);
+        }
+        Uncertain { value, terms }
+    }
+
+    pub fn exact(value: f64) -> Self {
+        Uncertain {
+            value,
+            terms: BTreeMap::new(),
+        }
+    }
+
+    pub fn std_dev(&self) -> f64 {
+        self.terms.values().map(|t| t * t).sum::<f64>().sqrt()
+    }
+
+    /// Applies a function of one variable given its value and derivative at `self.value`.
+    pub fn apply(&self, value: f64, derivative: f64) -> Self {
+        Uncertain {
+            value,
+            terms: self
+                .terms
+                .iter()
+                .map(|(&id, &t)| (id, t * derivative))
+                .collect(),
+        }
+    }
+
+    /// Applies a function of two variables given its value and partial derivatives.
+    pub fn combine(&self, other: &Uncertain, value: f64, da: f64, db: f64) -> Self {
+        let mut terms = BTreeMap::new();
+        for (&id, &t) in &self.terms {
+            *terms.entry(id).or_insert(0.0) += t * da;
+        }
+        for (&id, &t) in &other.terms {
+            *terms.entry(id).or_insert(0.0) += t * db;
+        }
+        terms.retain(|_, t| *t != 0.0);
+        Uncertain { value, terms }
+    }
+
+    pub fn neg(&self) -> Self {
+        self.apply(-self.value, -1.0)
+    }
+
+    pub fn add(&self, other: &Uncertain) -> Self {
+        self.combine(other, self.value + other.value, 1.0, 1.0)
+    }
+
+    pub fn sub(&self, other: &Uncertain) -> Self {
+        self.combine(other, self.value - other.value, 1.0, -1.0)
+    }
+
+    pub fn mul(&self, other: &Uncertain) -> Self {
+        self.combine(other, self.value * other.value, other.value, self.value)
+    }
+
+    pub fn div(&self, other: &Uncertain) -> Self {
+        let (a, b) = (self.value, other.value);
+        self.combine(other, a / b, 1.0 / b, -a / (b * b))
+    }
+
+    pub fn powf(&self, other: &Uncertain) -> Self {
+        let (a, b) = (self.value, other.value);
+        let value = a.powf(b);
+        let da = if b == 0.0 { 0.0 } else { b * a.powf(b - 1.0) };
+        let db = if other.terms.is_empty() {
+            0.0
+        } else {
+            value * a.ln()
+        };
+        self.combine(other, value, da, db)
+    }
+}
+
+impl fmt::Display for Uncertain {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} ± {}", self.value, self.std_dev())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_independent_sources_add_in_quadrature() {
+        let a = Uncertain::new(3.0, 0.3);
+        let b = Uncertain::new(4.0, 0.4);
+        let sum = a.add(&b);
+        assert_eq!(sum.value, 7.0);
+        assert!((sum.std_dev() - 0.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_same_source_is_correlated() {
+        let x = Uncertain::new(2.0, 0.1);
+        assert_eq!(x.sub(&x).std_dev(), 0.0);
+        assert!((x.add(&x).std_dev() - 0.2).abs() < 1e-12);
+        assert!((x.mul(&x).std_dev() - 0.4).abs() < 1e-12);
+    }
+}
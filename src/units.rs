@@ -0,0 +1,587 @@
+use std::fmt;
+
+/// Exponents of the SI base dimensions: length, mass, time, current,
+/// temperature, amount of substance and luminous intensity.
+pub type Dimension = [i32; 7];
+
+const NONE: Dimension = [0; 7];
+const LENGTH: Dimension = [1, 0, 0, 0, 0, 0, 0];
+const MASS: Dimension = [0, 1, 0, 0, 0, 0, 0];
+const TIME: Dimension = [0, 0, 1, 0, 0, 0, 0];
+const CURRENT: Dimension = [0, 0, 0, 1, 0, 0, 0];
+const TEMPERATURE: Dimension = [0, 0, 0, 0, 1, 0, 0];
+const AMOUNT: Dimension = [0, 0, 0, 0, 0, 1, 0];
+const LUMINOSITY: Dimension = [0, 0, 0, 0, 0, 0, 1];
+const AREA: Dimension = [2, 0, 0, 0, 0, 0, 0];
+const VOLUME: Dimension = [3, 0, 0, 0, 0, 0, 0];
+const SPEED: Dimension = [1, 0, -1, 0, 0, 0, 0];
+const FREQUENCY: Dimension = [0, 0, -1, 0, 0, 0, 0];
+const FORCE: Dimension = [1, 1, -2, 0, 0, 0, 0];
+const PRESSURE: Dimension = [-1, 1, -2, 0, 0, 0, 0];
+const ENERGY: Dimension = [2, 1, -2, 0, 0, 0, 0];
+const POWER: Dimension = [2, 1, -3, 0, 0, 0, 0];
+const CHARGE: Dimension = [0, 0, 1, 1, 0, 0, 0];
+const VOLTAGE: Dimension = [2, 1, -3, -1, 0, 0, 0];
+const RESISTANCE: Dimension = [2, 1, -3, -2, 0, 0, 0];
+const CAPACITANCE: Dimension = [-2, -1, 4, 2, 0, 0, 0];
+const INDUCTANCE: Dimension = [2, 1, -2, -2, 0, 0, 0];
+
+const BASE_NAMES: [&str; 7] = ["m", "kg", "s", "A", "K", "mol", "cd"];
+
+/// Name, size in SI base units, offset of its zero point (temperatures only),
+/// dimension and whether SI prefixes may be attached.
+const UNITS: &[(&str, f64, f64, Dimension, bool)] = &[
+    ("m", 1.0, 0.0, LENGTH, true),
+    ("ft", 0.3048, 0.0, LENGTH, false),
+    ("inch", 0.0254, 0.0, LENGTH, false),
+    ("yd", 0.9144, 0.0, LENGTH, false),
+    ("mi", 1609.344, 0.0, LENGTH, false),
+    ("nmi", 1852.0, 0.0, LENGTH, false),
+    ("au", 149597870700.0, 0.0, LENGTH, false),
+    ("ly", 9460730472580800.0, 0.0, LENGTH, false),
+    ("g", 0.001, 0.0, MASS, true),
+    ("t", 1000.0, 0.0, MASS, false),
+    ("lb", 0.45359237, 0.0, MASS, false),
+    ("oz", 0.028349523125, 0.0, MASS, false),
+    ("s", 1.0, 0.0, TIME, true),
+    ("min", 60.0, 0.0, TIME, false),
+    ("h", 3600.0, 0.0, TIME, false),
+    ("second", 1.0, 0.0, TIME, false),
+    ("minute", 60.0, 0.0, TIME, false),
+    ("hour", 3600.0, 0.0, TIME, false),
+    ("d", 86400.0, 0.0, TIME, false),
+    ("day", 86400.0, 0.0, TIME, false),
+    ("week", 604800.0, 0.0, TIME, false),
+    ("A", 1.0, 0.0, CURRENT, true),
+    ("K", 1.0, 0.0, TEMPERATURE, true),
+    ("degC", 1.0, 273.15, TEMPERATURE, false),
+    ("°C", 1.0, 273.15, TEMPERATURE, false),
+    ("degF", 5.0 / 9.0, 459.67 * 5.0 / 9.0, TEMPERATURE, false),
+    ("°F", 5.0 / 9.0, 459.67 * 5.0 / 9.0, TEMPERATURE, false),
+    ("mol", 1.0, 0.0, AMOUNT, true),
+    ("cd", 1.0, 0.0, LUMINOSITY, true),
+    ("ha", 10000.0, 0.0, AREA, false),
+    ("acre", 4046.8564224, 0.0, AREA, false),
+    ("L", 0.001, 0.0, VOLUME, true),
+    ("gal", 0.003785411784, 0.0, VOLUME, false),
+    ("mph", 0.44704, 0.0, SPEED, false),
+    ("kph", 1.0 / 3.6, 0.0, SPEED, false),
+    ("knot", 1852.0 / 3600.0, 0.0, SPEED, false),
+    ("Hz", 1.0, 0.0, FREQUENCY, true),
+    ("N", 1.0, 0.0, FORCE, true),
+    ("lbf", 4.4482216152605, 0.0, FORCE, false),
+    ("Pa", 1.0, 0.0, PRESSURE, true),
+    ("bar", 100000.0, 0.0, PRESSURE, true),
+    ("atm", 101325.0, 0.0, PRESSURE, false),
+    ("psi", 6894.757293168361, 0.0, PRESSURE, false),
+    ("J", 1.0, 0.0, ENERGY, true),
+    ("cal", 4.184, 0.0, ENERGY, true),
+    ("eV", 1.602176634e-19, 0.0, ENERGY, true),
+    ("Wh", 3600.0, 0.0, ENERGY, true),
+    ("W", 1.0, 0.0, POWER, true),
+    ("C", 1.0, 0.0, CHARGE, true),
+    ("V", 1.0, 0.0, VOLTAGE, true),
+    ("ohm", 1.0, 0.0, RESISTANCE, true),
+    ("Ω", 1.0, 0.0, RESISTANCE, true),
+    ("F", 1.0, 0.0, CAPACITANCE, true),
+    ("H", 1.0, 0.0, INDUCTANCE, true),
+];
+
+/// What each offset scale reads at the freezing point of water. These are exact,
+/// unlike the offsets in kelvin, so `0 degC to degF` comes out as 32.
+const ICE_POINTS: &[(&str, f64)] = &[("degC", 0.0), ("°C", 0.0), ("degF", 32.0), ("°F", 32.0)];
+
+const PREFIXES: &[(&str, f64)] = &[
+    ("Y", 1e24),
+    ("Z", 1e21),
+    ("E", 1e18),
+    ("P", 1e15),
+    ("T", 1e12),
+    ("G", 1e9),
+    ("M", 1e6),
+    ("k", 1e3),
+    ("h", 1e2),
+    ("da", 1e1),
+    ("d", 1e-1),
+    ("c", 1e-2),
+    ("m", 1e-3),
+    ("µ", 1e-6),
+    ("u", 1e-6),
+    ("n", 1e-9),
+    ("p", 1e-12),
+    ("f", 1e-15),
+    ("a", 1e-18),
+    ("z", 1e-21),
+    ("y", 1e-24),
+];
+
+/// A product of named units with integer exponents, such as `kg*m/s^2`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Unit {
+    factors: Vec<(String, i32)>,
+    /// Size in SI base units.
+    pub factor: f64,
+    /// Zero point in SI base units; only non-zero for a lone `degC` or `degF`.
+    pub offset: f64,
+    pub dim: Dimension,
+}
+
+impl Unit {
+    /// The unit of a plain number.
+    pub fn none() -> Self {
+        Unit {
+            factors: Vec::new(),
+            factor: 1.0,
+            offset: 0.0,
+            dim: NONE,
+        }
+    }
+
+    fn single(name: &str, factor: f64, offset: f64, dim: Dimension) -> Self {
+        Unit {
+            factors: vec![(name.to_string(), 1)],
+            factor,
+            offset,
+            dim,
+        }
+    }
+
+    /// Looks up a unit name, with an optional SI prefix (`km`, `µs`, `kWh`) or a
+    /// plural `s` on spelled-out names (`hours`).
+    pub fn lookup(name: &str) -> Option<Unit> {
+        if let Some(&(_, factor, offset, dim, _)) = UNITS.iter().find(|u| u.0 == name) {
+            return Some(Unit::single(name, factor, offset, dim));
+        }
+        let prefixed = PREFIXES.iter().find_map(|&(prefix, scale)| {
+            let base = name.strip_prefix(prefix)?;
+            let &(_, factor, _, dim, prefixable) = UNITS.iter().find(|u| u.0 == base)?;
+            prefixable.then(|| Unit::single(name, scale * factor, 0.0, dim))
+        });
+        prefixed.or_else(|| {
+            let singular = name.strip_suffix('s').filter(|s| s.len() >= 3)?;
+            let &(_, factor, offset, dim, _) = UNITS.iter().find(|u| u.0 == singular)?;
+            Some(Unit::single(name, factor, offset, dim))
+        })
+    }
+
+    /// Parses a unit expression such as `m/s^2` or `kg*m^2`.
+    pub fn parse(text: &str) -> Result<Unit, String> {
+        let text: String = text.chars().filter(|c| !c.is_whitespace()).collect();
+        let mut result: Option<Unit> = None;
+        let mut divide = false;
+        let mut rest = text.as_str();
+        while !rest.is_empty() {
+            let end = rest.find(['*', '/']).unwrap_or(rest.len());
+            let (term, tail) = rest.split_at(end);
+            let (name, power) = match term.split_once('^') {
+                Some((name, power)) => (
+                    name,
+                    power
+                        .parse::<i32>()
+                        .map_err(|_| format!("Invalid unit exponent: {}", power))?,
+                ),
+                None => (term, 1),
+            };
+            // A leading or doubled operator, as in `/s` or `m//s`
+            if name.is_empty() {
+                return Err(format!("Incomplete unit: {}", text));
+            }
+            let unit = Unit::lookup(name)
+                .ok_or_else(|| format!("Unknown unit: {}", name))?
+                .powi(if divide { -power } else { power });
+            result = Some(match result {
+                Some(r) => r.mul(&unit),
+                None => unit,
+            });
+            divide = tail.starts_with('/');
+            rest = tail.get(1..).unwrap_or("");
+            if !tail.is_empty() && rest.is_empty() {
+                return Err(format!("Incomplete unit: {}", text));
+            }
+        }
+        result.ok_or_else(|| "Expected a unit".to_string())
+    }
+
+    pub fn is_dimensionless(&self) -> bool {
+        self.dim == NONE
+    }
+
+    /// The reading of an offset temperature scale at the freezing point of water.
+    fn ice_point(&self) -> Option<f64> {
+        if self.offset == 0.0 {
+            return None;
+        }
+        let name = &self.factors.first()?.0;
+        // Also the plural, as in `degCs`
+        ICE_POINTS
+            .iter()
+            .find(|(n, _)| name == n || name.strip_suffix('s') == Some(n))
+            .map(|&(_, reading)| reading)
+    }
+
+    pub fn mul(&self, other: &Unit) -> Unit {
+        let mut factors = self.factors.clone();
+        for (name, power) in &other.factors {
+            match factors.iter_mut().find(|(n, _)| n == name) {
+                Some((_, p)) => *p += power,
+                None => factors.push((name.clone(), *power)),
+            }
+        }
+        factors.retain(|(_, p)| *p != 0);
+        let mut dim = self.dim;
+        for (d, o) in dim.iter_mut().zip(other.dim) {
+            *d += o;
+        }
+        Unit {
+            factors,
+            factor: self.factor * other.factor,
+            offset: 0.0,
+            dim,
+        }
+    }
+
+    pub fn powi(&self, n: i32) -> Unit {
+        Unit {
+            factors: self
+                .factors
+                .iter()
+                .map(|(name, p)| (name.clone(), p * n))
+                .filter(|(_, p)| *p != 0)
+                .collect(),
+            factor: self.factor.powi(n),
+            offset: if n == 1 { self.offset } else { 0.0 },
+            dim: self.dim.map(|d| d * n),
+        }
+    }
+
+    fn describe_dimension(dim: &Dimension) -> String {
+        let unit = Unit {
+            factors: BASE_NAMES
+                .iter()
+                .zip(dim)
+                .map(|(name, p)| (name.to_string(), *p))
+                .filter(|(_, p)| *p != 0)
+                .collect(),
+            factor: 1.0,
+            offset: 0.0,
+            dim: *dim,
+        };
+        if unit.factors.is_empty() {
+            "a plain number".to_string()
+        } else {
+            unit.to_string()
+        }
+    }
+}
+
+impl fmt::Display for Unit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let term = |name: &str, p: i32| match p {
+            1 => name.to_string(),
+            _ => format!("{}^{}", name, p),
+        };
+        let numerator: Vec<String> = self
+            .factors
+            .iter()
+            .filter(|(_, p)| *p > 0)
+            .map(|(name, p)| term(name, *p))
+            .collect();
+        let denominator: Vec<String> = self
+            .factors
+            .iter()
+            .filter(|(_, p)| *p < 0)
+            .map(|(name, p)| term(name, -p))
+            .collect();
+        let mut text = match numerator.is_empty() {
+            true => "1".to_string(),
+            false => numerator.join("*"),
+        };
+        for d in denominator {
+            text = format!("{}/{}", text, d);
+        }
+        write!(f, "{}", text)
+    }
+}
+
+/// A number with a unit. The value is expressed in `unit`, not in SI base units.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Quantity {
+    pub value: f64,
+    pub unit: Unit,
+}
+
+impl Quantity {
+    pub fn new(value: f64, unit: Unit) -> Self {
+        Quantity { value, unit }
+    }
+
+    pub fn seconds(value: f64) -> Self {
+        Quantity::new(value, Unit::lookup("s").unwrap())
+    }
+
+    /// Value in SI base units.
+    pub fn si_value(&self) -> f64 {
+        self.value * self.unit.factor + self.unit.offset
+    }
+
+    fn check_same_dimension(&self, unit: &Unit) -> Result<(), String> {
+        if self.unit.dim == unit.dim {
+            Ok(())
+        } else {
+            Err(format!(
+                "Incompatible units: {} ({}) and {} ({})",
+                self.unit,
+                Unit::describe_dimension(&self.unit.dim),
+                unit,
+                Unit::describe_dimension(&unit.dim)
+            ))
+        }
+    }
+
+    /// Converts to `unit`, honouring temperature offsets: `20 degC to degF` is 68 degF.
+    pub fn convert(&self, unit: &Unit) -> Result<Quantity, String> {
+        self.check_same_dimension(unit)?;
+        // Between two offset scales, go through their exact ice points
+        let value = match (self.unit.ice_point(), unit.ice_point()) {
+            (Some(from), Some(to)) => (self.value - from) * self.unit.factor / unit.factor + to,
+            _ => (self.si_value() - unit.offset) / unit.factor,
+        };
+        Ok(Quantity::new(value, unit.clone()))
+    }
+
+    /// Adds or subtracts `other`, expressed in this quantity's unit. The right-hand
+    /// side counts as a difference, so `20 degC + 5 degC` is `25 degC`.
+    pub fn add(&self, other: &Quantity, sign: f64) -> Result<Quantity, String> {
+        self.check_same_dimension(&other.unit)?;
+        let other = other.value * other.unit.factor / self.unit.factor;
+        Ok(Quantity::new(self.value + sign * other, self.unit.clone()))
+    }
+
+    /// A temperature on an offset scale as kelvin, so that products and powers
+    /// are taken from absolute zero and labelled accordingly.
+    fn absolute(&self) -> Quantity {
+        if self.unit.offset == 0.0 {
+            return self.clone();
+        }
+        Quantity::new(self.si_value(), Unit::lookup("K").unwrap())
+    }
+
+    pub fn mul(&self, other: &Quantity) -> Quantity {
+        let (a, b) = (self.absolute(), other.absolute());
+        Quantity::new(a.value * b.value, a.unit.mul(&b.unit))
+    }
+
+    pub fn div(&self, other: &Quantity) -> Result<Quantity, String> {
+        if other.value == 0.0 {
+            return Err("Division by zero".to_string());
+        }
+        let (a, b) = (self.absolute(), other.absolute());
+        Ok(Quantity::new(
+            a.value / b.value,
+            a.unit.mul(&b.unit.powi(-1)),
+        ))
+    }
+
+    pub fn scale(&self, factor: f64) -> Quantity {
+        Quantity::new(self.value * factor, self.unit.clone())
+    }
+
+    pub fn powf(&self, n: f64) -> Result<Quantity, String> {
+        if n.fract() != 0.0 {
+            return Err("Units can only be raised to integer powers".to_string());
+        }
+        let a = self.absolute();
+        Ok(Quantity::new(a.value.powf(n), a.unit.powi(n as i32)))
+    }
+}
+
+impl fmt::Display for Quantity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.value, self.unit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn q(value: f64, unit: &str) -> Quantity {
+        Quantity::new(value, Unit::parse(unit).unwrap())
+    }
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() <= 1e-12 * b.abs().max(1.0), "{} != {}", a, b);
+    }
+
+    #[test]
+    fn test_prefixes_and_parsing() {
+        assert_eq!(Unit::parse("km").unwrap().factor, 1000.0);
+        assert_eq!(Unit::parse("ms").unwrap().factor, 0.001);
+        assert_eq!(Unit::parse("µF").unwrap().factor, 1e-6);
+        assert_eq!(Unit::parse("µx"), Err("Unknown unit: µx".to_string()));
+        assert_eq!(Unit::parse("min").unwrap().factor, 60.0);
+        let accel = Unit::parse("m/s^2").unwrap();
+        assert_eq!(accel.dim, [1, 0, -2, 0, 0, 0, 0]);
+        assert_eq!(accel.to_string(), "m/s^2");
+        assert!(Unit::parse("kft").is_err());
+        assert!(Unit::parse("m/").is_err());
+    }
+
+    #[test]
+    fn test_ambiguous_names() {
+        // Whole names win over a prefix reading: min is not milli-inch, cd not centi-day
+        assert_eq!(Unit::parse("min").unwrap().factor, 60.0);
+        assert_eq!(Unit::parse("cd").unwrap().dim, LUMINOSITY);
+        assert_eq!(Unit::parse("Pa").unwrap().dim, PRESSURE);
+        // da is tried before d
+        assert_eq!(Unit::parse("dam").unwrap().factor, 10.0);
+        assert_eq!(Unit::parse("ds").unwrap().factor, 0.1);
+        // A prefix comes before a plural reading, and plurals need a spelled-out name
+        assert_eq!(Unit::parse("ms").unwrap().factor, 0.001);
+        assert_eq!(Unit::parse("hours").unwrap().factor, 3600.0);
+        assert_eq!(Unit::parse("mins").unwrap().factor, 60.0);
+        assert_eq!(Unit::parse("Ks"), Err("Unknown unit: Ks".to_string()));
+        // The kilogram is a prefixed gram
+        assert_eq!(Unit::parse("kg").unwrap().factor, 1.0);
+        assert_eq!(Unit::parse("Mg").unwrap().factor, 1000.0);
+        assert!(Unit::parse("kh").is_err());
+    }
+
+    #[test]
+    fn test_unit_expressions() {
+        let parsed = |text: &str| Unit::parse(text).unwrap().to_string();
+        assert_eq!(parsed("m/s/s"), "m/s^2");
+        // Only the next term is divided
+        assert_eq!(parsed("m/s*kg"), "m*kg/s");
+        assert_eq!(parsed("s^-1"), "1/s");
+        assert_eq!(parsed("m^-2"), "1/m^2");
+        assert_eq!(parsed("kg * m^2 / s^2"), "kg*m^2/s^2");
+        // Cancelled units leave a plain number
+        assert!(Unit::parse("m/m").unwrap().is_dimensionless());
+        assert!(Unit::parse("m^0").unwrap().is_dimensionless());
+        assert_eq!(
+            Unit::parse("m^2.5"),
+            Err("Invalid unit exponent: 2.5".to_string())
+        );
+        assert_eq!(Unit::parse("/s"), Err("Incomplete unit: /s".to_string()));
+        assert_eq!(
+            Unit::parse("m//s"),
+            Err("Incomplete unit: m//s".to_string())
+        );
+        assert_eq!(Unit::parse("1/s"), Err("Unknown unit: 1".to_string()));
+        assert!(Unit::parse("").is_err());
+    }
+
+    #[test]
+    fn test_conversions() {
+        assert_close(
+            q(60.0, "mph")
+                .convert(&Unit::parse("m/s").unwrap())
+                .unwrap()
+                .value,
+            26.8224,
+        );
+        assert_close(
+            q(20.0, "degC")
+                .convert(&Unit::parse("degF").unwrap())
+                .unwrap()
+                .value,
+            68.0,
+        );
+        assert_close(
+            q(300.0, "K")
+                .convert(&Unit::parse("degC").unwrap())
+                .unwrap()
+                .value,
+            26.85,
+        );
+        assert_close(
+            q(1.0, "kWh")
+                .convert(&Unit::parse("J").unwrap())
+                .unwrap()
+                .value,
+            3.6e6,
+        );
+        assert!(q(1.0, "m").convert(&Unit::parse("s").unwrap()).is_err());
+        // Between the offset scales the fixed points come out exactly
+        let degrees = |value: f64, from: &str, to: &str| {
+            q(value, from)
+                .convert(&Unit::parse(to).unwrap())
+                .unwrap()
+                .value
+        };
+        assert_eq!(degrees(0.0, "degC", "degF"), 32.0);
+        assert_eq!(degrees(100.0, "°C", "°F"), 212.0);
+        assert_eq!(degrees(212.0, "degF", "degC"), 100.0);
+        assert_eq!(degrees(98.6, "degF", "degC"), 37.0);
+        assert_eq!(degrees(32.0, "degF", "degF"), 32.0);
+        // The two scales meet at -40, and absolute zero has no offset in kelvin
+        assert_close(
+            q(-40.0, "degF")
+                .convert(&Unit::parse("degC").unwrap())
+                .unwrap()
+                .value,
+            -40.0,
+        );
+        assert_close(
+            q(0.0, "K")
+                .convert(&Unit::parse("degF").unwrap())
+                .unwrap()
+                .value,
+            -459.67,
+        );
+        assert_eq!(
+            q(1.0, "mph").convert(&Unit::parse("Hz").unwrap()),
+            Err("Incompatible units: mph (m/s) and Hz (1/s)".to_string())
+        );
+    }
+
+    #[test]
+    fn test_dimensional_arithmetic() {
+        let sum = q(5.0, "km").add(&q(300.0, "m"), 1.0).unwrap();
+        assert_close(sum.value, 5.3);
+        assert_eq!(sum.unit.to_string(), "km");
+        assert!(q(1.0, "m").add(&q(1.0, "s"), 1.0).is_err());
+
+        let force = q(3.0, "kg").mul(&q(9.81, "m/s^2"));
+        assert_eq!(force.unit.dim, FORCE);
+        assert_eq!(force.unit.to_string(), "kg*m/s^2");
+        assert_eq!(q(2.0, "m").mul(&q(3.0, "m")).unit.to_string(), "m^2");
+
+        let warmer = q(20.0, "degC").add(&q(5.0, "degC"), 1.0).unwrap();
+        assert_close(warmer.value, 25.0);
+        // Multiplying a temperature works on its absolute value, in kelvin
+        assert_close(q(0.0, "degC").scale(1.0).mul(&q(1.0, "K^-1")).value, 273.15);
+        let doubled = q(20.0, "degC").mul(&Quantity::new(2.0, Unit::none()));
+        assert_close(doubled.value, 586.3);
+        assert_eq!(doubled.unit.to_string(), "K");
+        assert_eq!(q(20.0, "degC").mul(&q(1.0, "m")).unit.to_string(), "K*m");
+        let halved = q(32.0, "degF")
+            .div(&Quantity::new(2.0, Unit::none()))
+            .unwrap();
+        assert_close(halved.value, 136.575);
+        assert_eq!(halved.unit.to_string(), "K");
+        assert_eq!(q(20.0, "degC").powf(2.0).unwrap().unit.to_string(), "K^2");
+        // The right-hand side of a sum is a difference, whichever scale it is on
+        assert_close(
+            q(30.0, "degC").add(&q(10.0, "degC"), -1.0).unwrap().value,
+            20.0,
+        );
+        assert_close(q(10.0, "K").add(&q(5.0, "degC"), 1.0).unwrap().value, 15.0);
+        assert_close(q(50.0, "degF").add(&q(5.0, "K"), 1.0).unwrap().value, 59.0);
+
+        assert_eq!(
+            q(4.0, "m").div(&q(0.0, "s")),
+            Err("Division by zero".to_string())
+        );
+        assert!(
+            q(4.0, "m")
+                .div(&q(2.0, "m"))
+                .unwrap()
+                .unit
+                .is_dimensionless()
+        );
+        assert!(q(4.0, "m^2").powf(0.5).is_err());
+        assert_eq!(q(2.0, "m").powf(-2.0).unwrap().unit.to_string(), "1/m^2");
+    }
+}
@@ -0,0 +1,116 @@
+use crate::datetime::{self, Span};
+use crate::interval::Interval;
+use crate::matrix::Matrix;
+use crate::uncertainty::Uncertain;
+use crate::units::Quantity;
+use chrono::NaiveDateTime;
+use std::fmt;
+
+/// A result produced by the evaluator.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Number(f64),
+    Uncertain(Uncertain),
+    Interval(Interval),
+    Text(String),
+    Matrix(Matrix),
+    Date(NaiveDateTime),
+    Duration(Span),
+    Quantity(Quantity),
+    /// A percentage, held in percent: `Percent(15.0)` is 15%.
+    Percent(f64),
+}
+
+impl Value {
+    /// The central value, ignoring any uncertainty.
+    pub fn nominal(&self) -> f64 {
+        match self {
+            Value::Number(n) => *n,
+            Value::Uncertain(u) => u.value,
+            Value::Interval(i) => i.midpoint(),
+            Value::Quantity(q) => q.value,
+            Value::Percent(p) => p / 100.0,
+            Value::Text(_) | Value::Matrix(_) | Value::Date(_) | Value::Duration(_) => f64::NAN,
+        }
+    }
+
+    pub fn to_uncertain(&self) -> Result<Uncertain, String> {
+        match self {
+            Value::Number(n) => Ok(Uncertain::exact(*n)),
+            Value::Percent(p) => Ok(Uncertain::exact(p / 100.0)),
+            Value::Uncertain(u) => Ok(u.clone()),
+            Value::Interval(_) => {
+                Err("Intervals cannot be combined with uncertain values".to_string())
+            }
+            Value::Text(_) => Err("Expected a number, got text".to_string()),
+            Value::Matrix(_) => Err("Expected a number, got a matrix".to_string()),
+            Value::Date(_) | Value::Duration(_) => {
+                Err("Expected a number, got a date or duration".to_string())
+            }
+            Value::Quantity(q) => Err(format!(
+                "Expected a plain number, got a quantity in {}",
+                q.unit
+            )),
+        }
+    }
+
+    pub fn to_interval(&self) -> Result<Interval, String> {
+        match self {
+            Value::Number(n) => Ok(Interval::point(*n)),
+            Value::Percent(p) => Ok(Interval::point(p / 100.0)),
+            Value::Interval(i) => Ok(*i),
+            Value::Uncertain(_) => {
+                Err("Intervals cannot be combined with uncertain values".to_string())
+            }
+            Value::Text(_) => Err("Expected a number, got text".to_string()),
+            Value::Matrix(_) => Err("Expected a number, got a matrix".to_string()),
+            Value::Date(_) | Value::Duration(_) => {
+                Err("Expected a number, got a date or duration".to_string())
+            }
+            Value::Quantity(q) => Err(format!(
+                "Expected a plain number, got a quantity in {}",
+                q.unit
+            )),
+        }
+    }
+
+    /// Collapses uncertain values without any error contribution, intervals of a
+    /// single point and quantities whose units cancel back to plain numbers.
+    pub fn normalize(self) -> Value {
+        match self {
+            Value::Uncertain(u) if u.std_dev() == 0.0 => Value::Number(u.value),
+            Value::Interval(i) if i.is_point() => Value::Number(i.lo),
+            Value::Quantity(q) if q.unit.is_dimensionless() => Value::Number(q.si_value()),
+            other => other,
+        }
+    }
+}
+
+impl From<f64> for Value {
+    fn from(n: f64) -> Self {
+        Value::Number(n)
+    }
+}
+
+impl PartialEq<f64> for Value {
+    fn eq(&self, other: &f64) -> bool {
+        matches!(self, Value::Number(n) if n == other)
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Number(n) if n.is_nan() => write!(f, "nan"),
+            Value::Number(n) => write!(f, "{}", n),
+            Value::Uncertain(u) => write!(f, "{}", u),
+            Value::Interval(i) => write!(f, "{}", i),
+            Value::Text(text) => write!(f, "{}", text),
+            Value::Matrix(m) => write!(f, "{}", m),
+            Value::Date(date) => write!(f, "{}", datetime::format_date(date)),
+            Value::Duration(span) => write!(f, "{}", span),
+            Value::Quantity(q) => write!(f, "{}", q),
+            Value::Percent(p) => write!(f, "{}%", p),
+        }
+    }
+}